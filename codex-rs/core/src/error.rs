@@ -52,6 +52,46 @@ pub enum SandboxErr {
     /// Error from linux landlock
     #[error("Landlock was not able to fully enforce all sandbox rules")]
     LandlockRestrict,
+
+    /// Network access must be blocked, but seccomp is not available on this
+    /// kernel and no other mechanism could be used instead
+    #[error("seccomp is not available, so network access cannot be blocked: {0}")]
+    SeccompUnavailable(String),
+
+    /// Some sandbox rules could not be applied on this host, and the caller
+    /// asked for full enforcement
+    #[error("sandbox is only partially enforced on this host: {0}")]
+    PartiallyEnforced(String),
+
+    /// The sandbox backend that was explicitly requested cannot be used on
+    /// this host
+    #[error("sandbox backend is not available: {0}")]
+    BackendUnavailable(String),
+
+    /// Error while setting up the private network namespace used for
+    /// loopback-only network access
+    #[error("could not set up a loopback-only network namespace: {0}")]
+    NetworkNamespace(String),
+
+    /// A relative writable root resolved to a directory that must not be
+    /// made writable
+    #[error("writable root is not allowed: {0}")]
+    ProtectedWritableRoot(String),
+
+    /// A sandboxing step failed after earlier ones took effect, so the thread
+    /// is sandboxed only in part and must not run the command
+    #[error("sandbox was only partially installed: {0}")]
+    PartiallyInstalled(String),
+
+    /// The policy has more writable roots than the Landlock ruleset is
+    /// allowed to hold
+    #[error("too many writable roots: {0}")]
+    TooManyRoots(String),
+
+    /// A seccomp filter has more instructions than the kernel accepts in one
+    /// BPF program
+    #[error("seccomp filter is too large: {0}")]
+    SeccompProgramTooLarge(String),
 }
 
 #[derive(Error, Debug)]
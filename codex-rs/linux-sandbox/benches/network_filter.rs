@@ -0,0 +1,26 @@
+//! Compares assembling the network seccomp filter on every install with
+//! fetching it from the per-process cache. Run with
+//! `cargo bench -p codex-linux-sandbox --bench network_filter`.
+
+#[cfg(target_os = "linux")]
+fn main() {
+    const ITERATIONS: u32 = 10_000;
+
+    match codex_linux_sandbox::time_network_filter_cache(ITERATIONS) {
+        Ok((assembled, cached)) => {
+            println!("network filter per install, over {ITERATIONS} installs:");
+            println!("  assembled: {assembled:?}");
+            println!("  cached:    {cached:?}");
+            println!("  saved:     {:?}", assembled.saturating_sub(cached));
+        }
+        Err(e) => {
+            eprintln!("error assembling the network filter: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("codex-linux-sandbox is only supported on Linux");
+}
@@ -0,0 +1,369 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use codex_core::error::CodexErr;
+use codex_core::error::Result;
+use codex_core::error::SandboxErr;
+use codex_core::protocol::SandboxPolicy;
+
+use crate::bwrap::BWRAP_PATH;
+use crate::enforcement::EnforcementMode;
+use crate::landlock::landlock_abi;
+use crate::landlock::seccomp_available;
+use crate::procfs::proc_mounted;
+use crate::procfs::read_proc_file;
+
+/// Sandbox mechanism requested on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// Landlock and seccomp, falling back to bwrap when seccomp is missing
+    /// and network access must be blocked.
+    Auto,
+    /// Landlock and seccomp only.
+    Landlock,
+    /// bwrap only.
+    Bwrap,
+    /// No sandbox at all, only the process-level settings such as `--umask`.
+    /// Meant for debugging.
+    None,
+}
+
+/// Mechanism that will actually enforce the policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SelectedBackend {
+    /// In-process Landlock rules plus the seccomp network filter.
+    Landlock,
+    /// bwrap, which enforces the filesystem rules with mounts and blocks
+    /// network access with a fresh network namespace. Landlock would prevent
+    /// it from mounting, so it is never combined with the in-process sandbox.
+    Bwrap { bwrap: PathBuf },
+    /// The command runs unsandboxed.
+    None,
+}
+
+impl SelectedBackend {
+    /// Name used in reports, matching the `--backend` value.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            SelectedBackend::Landlock => "landlock",
+            SelectedBackend::Bwrap { .. } => "bwrap",
+            SelectedBackend::None => "none",
+        }
+    }
+}
+
+/// Sandboxing features available on this host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HostCapabilities {
+    pub(crate) seccomp: bool,
+    /// Landlock ABI version, 0 when Landlock is unavailable.
+    pub(crate) landlock_abi: i32,
+    pub(crate) bwrap: Option<PathBuf>,
+    /// Whether this process may create user namespaces, which a bwrap that
+    /// is not setuid needs. Assumed when `/proc` is not mounted.
+    pub(crate) user_namespaces: bool,
+    /// Whether `/proc` is mounted, without which the checks above that read
+    /// it fall back to assumptions.
+    pub(crate) proc_mounted: bool,
+}
+
+impl HostCapabilities {
+    pub(crate) fn detect() -> Self {
+        Self {
+            seccomp: seccomp_available(),
+            landlock_abi: landlock_abi(),
+            bwrap: BWRAP_PATH.clone(),
+            user_namespaces: user_namespaces_available(),
+            proc_mounted: proc_mounted(),
+        }
+    }
+}
+
+/// Reads the sysctls that disable user namespaces: `user.max_user_namespaces`
+/// everywhere, and `kernel.unprivileged_userns_clone` on Debian-derived
+/// kernels, which does not apply to root. Without `/proc` they cannot be
+/// read, and user namespaces are assumed to be available; bwrap then reports
+/// it if they are not.
+fn user_namespaces_available() -> bool {
+    if !proc_mounted() {
+        return true;
+    }
+    let read_sysctl = |path: &str| {
+        read_proc_file(path)
+            .ok()
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+
+    if !read_sysctl("/proc/sys/user/max_user_namespaces").is_some_and(|max| max > 0) {
+        return false;
+    }
+    let is_root = unsafe { libc::geteuid() } == 0;
+    is_root || read_sysctl("/proc/sys/kernel/unprivileged_userns_clone") != Some(0)
+}
+
+/// Picks the mechanism for `backend`. For `auto`, seccomp is preferred; when
+/// the kernel lacks it and the policy forbids network access we fall back to
+/// bwrap unless [`EnforcementMode::Strict`] asks us to fail instead of
+/// switching mechanisms. Either way, we never run the command with network
+/// access the policy forbids. An explicitly requested backend is an error
+/// when the host cannot provide it.
+pub(crate) fn select_backend(
+    backend: Backend,
+    sandbox_policy: &SandboxPolicy,
+    host: &HostCapabilities,
+    enforcement: EnforcementMode,
+) -> Result<SelectedBackend> {
+    let restrict_network = !sandbox_policy.has_full_network_access();
+    match backend {
+        Backend::Auto => {
+            if !restrict_network || host.seccomp {
+                return Ok(SelectedBackend::Landlock);
+            }
+            if enforcement == EnforcementMode::Strict {
+                return Err(CodexErr::Sandbox(SandboxErr::SeccompUnavailable(
+                    "strict enforcement does not allow falling back to bwrap".to_string(),
+                )));
+            }
+            match &host.bwrap {
+                Some(bwrap) => Ok(SelectedBackend::Bwrap {
+                    bwrap: bwrap.clone(),
+                }),
+                None => Err(CodexErr::Sandbox(SandboxErr::SeccompUnavailable(
+                    "bwrap was not found on PATH to isolate the network instead".to_string(),
+                ))),
+            }
+        }
+        Backend::Landlock => {
+            if restrict_network && !host.seccomp {
+                return Err(backend_unavailable(
+                    "--backend landlock needs seccomp to block network access",
+                ));
+            }
+            if !sandbox_policy.has_full_disk_write_access() && host.landlock_abi == 0 {
+                return Err(backend_unavailable(
+                    "--backend landlock needs Landlock, which this kernel does not support",
+                ));
+            }
+            Ok(SelectedBackend::Landlock)
+        }
+        Backend::Bwrap => match &host.bwrap {
+            Some(bwrap)
+                if host.proc_mounted && !host.user_namespaces && !bwrap_is_setuid(bwrap) =>
+            {
+                Err(backend_unavailable(
+                    "--backend bwrap needs user namespaces, which this host does not allow",
+                ))
+            }
+            Some(bwrap) => Ok(SelectedBackend::Bwrap {
+                bwrap: bwrap.clone(),
+            }),
+            None => Err(backend_unavailable(
+                "--backend bwrap was requested, but bwrap was not found on PATH",
+            )),
+        },
+        Backend::None => Ok(SelectedBackend::None),
+    }
+}
+
+/// Whether `bwrap` is installed setuid, in which case it sets up its
+/// namespaces without needing unprivileged user namespaces.
+pub(crate) fn bwrap_is_setuid(bwrap: &Path) -> bool {
+    std::fs::metadata(bwrap).is_ok_and(|metadata| metadata.permissions().mode() & 0o4000 != 0)
+}
+
+fn backend_unavailable(reason: &str) -> CodexErr {
+    CodexErr::Sandbox(SandboxErr::BackendUnavailable(reason.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn host(seccomp: bool, landlock_abi: i32, bwrap: Option<&str>) -> HostCapabilities {
+        HostCapabilities {
+            seccomp,
+            landlock_abi,
+            bwrap: bwrap.map(PathBuf::from),
+            user_namespaces: true,
+            proc_mounted: true,
+        }
+    }
+
+    fn bwrap_backend() -> SelectedBackend {
+        SelectedBackend::Bwrap {
+            bwrap: PathBuf::from("/usr/bin/bwrap"),
+        }
+    }
+
+    #[test]
+    fn auto_prefers_seccomp_when_available() {
+        let backend = select_backend(
+            Backend::Auto,
+            &SandboxPolicy::ReadOnly,
+            &host(true, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::BestEffort,
+        )
+        .expect("seccomp is available");
+        assert_eq!(backend, SelectedBackend::Landlock);
+    }
+
+    #[test]
+    fn auto_falls_back_to_bwrap_net_namespace_without_seccomp() {
+        let backend = select_backend(
+            Backend::Auto,
+            &SandboxPolicy::ReadOnly,
+            &host(false, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::BestEffort,
+        )
+        .expect("bwrap is available");
+        assert_eq!(backend, bwrap_backend());
+    }
+
+    #[test]
+    fn auto_does_not_need_seccomp_when_network_is_allowed() {
+        let backend = select_backend(
+            Backend::Auto,
+            &SandboxPolicy::DangerFullAccess,
+            &host(false, 5, None),
+            EnforcementMode::Strict,
+        )
+        .expect("nothing needs seccomp");
+        assert_eq!(backend, SelectedBackend::Landlock);
+    }
+
+    #[test]
+    fn auto_without_seccomp_or_bwrap_is_an_error() {
+        let err = select_backend(
+            Backend::Auto,
+            &SandboxPolicy::ReadOnly,
+            &host(false, 5, None),
+            EnforcementMode::BestEffort,
+        )
+        .expect_err("nothing can block network access");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: seccomp is not available, so network access cannot be blocked: bwrap was not found on PATH to isolate the network instead"
+        );
+    }
+
+    #[test]
+    fn strict_enforcement_refuses_the_bwrap_fallback() {
+        let err = select_backend(
+            Backend::Auto,
+            &SandboxPolicy::ReadOnly,
+            &host(false, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::Strict,
+        )
+        .expect_err("fallback is not allowed");
+        assert!(
+            matches!(
+                err,
+                CodexErr::Sandbox(SandboxErr::SeccompUnavailable(ref reason))
+                    if reason.contains("strict enforcement")
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn landlock_backend_is_used_even_when_bwrap_exists() {
+        let backend = select_backend(
+            Backend::Landlock,
+            &SandboxPolicy::ReadOnly,
+            &host(true, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::BestEffort,
+        )
+        .expect("landlock is available");
+        assert_eq!(backend, SelectedBackend::Landlock);
+    }
+
+    #[test]
+    fn landlock_backend_without_seccomp_is_an_error() {
+        let err = select_backend(
+            Backend::Landlock,
+            &SandboxPolicy::ReadOnly,
+            &host(false, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::BestEffort,
+        )
+        .expect_err("seccomp is missing");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox backend is not available: --backend landlock needs seccomp to block network access"
+        );
+    }
+
+    #[test]
+    fn landlock_backend_without_landlock_is_an_error() {
+        let err = select_backend(
+            Backend::Landlock,
+            &SandboxPolicy::ReadOnly,
+            &host(true, 0, None),
+            EnforcementMode::BestEffort,
+        )
+        .expect_err("landlock is missing");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox backend is not available: --backend landlock needs Landlock, which this kernel does not support"
+        );
+    }
+
+    #[test]
+    fn bwrap_backend_is_used_even_when_seccomp_exists() {
+        let backend = select_backend(
+            Backend::Bwrap,
+            &SandboxPolicy::ReadOnly,
+            &host(true, 5, Some("/usr/bin/bwrap")),
+            EnforcementMode::BestEffort,
+        )
+        .expect("bwrap is available");
+        assert_eq!(backend, bwrap_backend());
+    }
+
+    #[test]
+    fn bwrap_backend_without_bwrap_is_an_error() {
+        let err = select_backend(
+            Backend::Bwrap,
+            &SandboxPolicy::ReadOnly,
+            &host(true, 5, None),
+            EnforcementMode::BestEffort,
+        )
+        .expect_err("bwrap is missing");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox backend is not available: --backend bwrap was requested, but bwrap was not found on PATH"
+        );
+    }
+
+    #[test]
+    fn bwrap_backend_without_user_namespaces_is_an_error() {
+        let err = select_backend(
+            Backend::Bwrap,
+            &SandboxPolicy::ReadOnly,
+            &HostCapabilities {
+                user_namespaces: false,
+                ..host(true, 5, Some("/nonexistent/bwrap"))
+            },
+            EnforcementMode::BestEffort,
+        )
+        .expect_err("user namespaces are disabled");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox backend is not available: --backend bwrap needs user namespaces, which this host does not allow"
+        );
+    }
+
+    #[test]
+    fn none_backend_needs_nothing_from_the_host() {
+        let backend = select_backend(
+            Backend::None,
+            &SandboxPolicy::ReadOnly,
+            &host(false, 0, None),
+            EnforcementMode::Strict,
+        )
+        .expect("none is always available");
+        assert_eq!(backend, SelectedBackend::None);
+    }
+}
@@ -0,0 +1,882 @@
+//! Runs the command under bubblewrap (`bwrap`) for hosts where the in-process
+//! Landlock/seccomp sandbox cannot be installed.
+//!
+//! Instead of filtering syscalls, bwrap mounts the filesystem read-only with the
+//! writable roots bound back in, and blocks network access by running the
+//! command in a fresh network namespace that only has loopback.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::LazyLock;
+
+use codex_core::error::Result;
+use codex_core::protocol::SandboxPolicy;
+
+use crate::landlock::SandboxOptions;
+use crate::writable_roots::writable_roots_with_cwd;
+
+/// Location of `bwrap` on `PATH`, looked up once per process.
+pub(crate) static BWRAP_PATH: LazyLock<Option<PathBuf>> = LazyLock::new(find_bwrap);
+
+fn find_bwrap() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join("bwrap"))
+        .find(|candidate| is_executable_file(candidate))
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Version of the `bwrap` at [`BWRAP_PATH`], queried once per process.
+/// `None` when it is missing or its version could not be read, in which case
+/// every feature is assumed to be available.
+pub(crate) static BWRAP_VERSION: LazyLock<Option<BwrapVersion>> =
+    LazyLock::new(|| BWRAP_PATH.as_deref().and_then(query_bwrap_version));
+
+fn query_bwrap_version(bwrap: &Path) -> Option<BwrapVersion> {
+    let output = Command::new(bwrap).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    BwrapVersion::parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct BwrapVersion(pub(crate) u32, pub(crate) u32, pub(crate) u32);
+
+impl BwrapVersion {
+    /// Parses the output of `bwrap --version`, such as `bubblewrap 0.8.0`.
+    /// Anything after the numbers, like a `-rc1` suffix, is ignored.
+    pub(crate) fn parse(output: &str) -> Option<Self> {
+        let version = output.trim().strip_prefix("bubblewrap ")?;
+        let mut parts = version.split('.').map(|part| {
+            let digits = part
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(part.len());
+            part[..digits].parse::<u32>().ok()
+        });
+        let major = parts.next()??;
+        let minor = parts.next()??;
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Self(major, minor, patch))
+    }
+}
+
+impl fmt::Display for BwrapVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(major, minor, patch) = self;
+        write!(f, "{major}.{minor}.{patch}")
+    }
+}
+
+/// bwrap options the launcher passes that older releases lack. Passing one
+/// to such a release fails with a usage error, so they are left out instead.
+/// The other options, `--chdir` included, are older than `--new-session`,
+/// so a release that lacks one of them lacks most options here too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BwrapFeature {
+    NewSession,
+    DieWithParent,
+    /// `--unshare-pid`, along with the `--proc /proc` that mounts the new
+    /// namespace's own procfs.
+    UnsharePid,
+    Argv0,
+}
+
+impl BwrapFeature {
+    pub(crate) fn flag(self) -> &'static str {
+        match self {
+            Self::NewSession => "--new-session",
+            Self::DieWithParent => "--die-with-parent",
+            Self::UnsharePid => "--unshare-pid",
+            Self::Argv0 => "--argv0",
+        }
+    }
+
+    /// The first bwrap release with the option.
+    pub(crate) fn min_version(self) -> BwrapVersion {
+        match self {
+            Self::UnsharePid => BwrapVersion(0, 1, 0),
+            Self::NewSession | Self::DieWithParent => BwrapVersion(0, 1, 8),
+            Self::Argv0 => BwrapVersion(0, 9, 0),
+        }
+    }
+
+    /// What the command runs without when the option is left out.
+    pub(crate) fn consequence(self) -> &'static str {
+        match self {
+            Self::NewSession => "the command can inject input into the launcher's terminal",
+            Self::DieWithParent => "the command keeps running if the launcher is killed",
+            Self::UnsharePid => {
+                "the command sees the host's processes and can reach their files through /proc"
+            }
+            Self::Argv0 => "the command gets its program path as argv[0]",
+        }
+    }
+
+    fn supported_by(self, version: Option<BwrapVersion>) -> bool {
+        version.is_none_or(|version| version >= self.min_version())
+    }
+}
+
+/// The features passed as a bare flag on every run.
+const BWRAP_FLAG_FEATURES: [BwrapFeature; 3] = [
+    BwrapFeature::NewSession,
+    BwrapFeature::DieWithParent,
+    BwrapFeature::UnsharePid,
+];
+
+/// The features a run needs that `version` lacks: `--argv0` only when
+/// `argv0` was requested.
+pub(crate) fn unsupported_bwrap_features(
+    version: Option<BwrapVersion>,
+    argv0: bool,
+) -> Vec<BwrapFeature> {
+    BWRAP_FLAG_FEATURES
+        .into_iter()
+        .chain(argv0.then_some(BwrapFeature::Argv0))
+        .filter(|feature| !feature.supported_by(version))
+        .collect()
+}
+
+/// Host directories mounted for [`SandboxOptions::minimal_root`], when they
+/// exist: enough to run the usual dynamically linked programs.
+pub(crate) const MINIMAL_ROOT_DIRS: &[&str] =
+    &["/bin", "/etc", "/lib", "/lib32", "/lib64", "/sbin", "/usr"];
+
+/// Where Nix keeps every package. On NixOS nearly every program, `/bin/sh`
+/// included, links against it, so a minimal root without it runs nothing.
+pub(crate) const NIX_STORE: &str = "/nix/store";
+
+/// `keep_mounts` plus `nix_store`, if it is a directory that none of them
+/// already covers, for [`SandboxOptions::minimal_root`].
+pub(crate) fn with_nix_store(mut keep_mounts: Vec<PathBuf>, nix_store: &Path) -> Vec<PathBuf> {
+    if nix_store.is_dir() && !keep_mounts.iter().any(|mount| nix_store.starts_with(mount)) {
+        keep_mounts.push(nix_store.to_path_buf());
+    }
+    keep_mounts
+}
+
+/// Builds the full argv that runs `command` under `bwrap` with the filesystem
+/// and network restrictions of `sandbox_policy`. With a private tmp in
+/// `options`, the command gets an empty tmpfs on `/tmp` instead of the
+/// host's, and its devices are bound into the minimal `/dev` with device
+/// access. With a minimal root, only [`MINIMAL_ROOT_DIRS`] and the kept mounts
+/// of the host are visible instead of all of `/`. Each existing read-deny
+/// path is masked: a file by `/dev/null`, a directory by an empty read-only
+/// tmpfs. The command starts in `command_cwd`, as it does under the other
+/// backends, and `argv0` overrides the `argv[0]` bwrap passes to it.
+///
+/// bwrap has no option for mount propagation: it always makes the mounts it
+/// starts with recursive slaves in the sandbox's namespace. The launcher
+/// starts it in a private mount namespace of its own, see
+/// [`crate::mountns`], so there is nothing for them to receive mounts from.
+///
+/// Options that `version` predates are left out; see
+/// [`unsupported_bwrap_features`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_bwrap_command(
+    bwrap: &Path,
+    version: Option<BwrapVersion>,
+    sandbox_policy: &SandboxPolicy,
+    sandbox_policy_cwd: &Path,
+    options: &SandboxOptions,
+    command_cwd: &Path,
+    argv0: Option<&str>,
+    command: &[OsString],
+) -> Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = vec![bwrap.into()];
+    for feature in BWRAP_FLAG_FEATURES {
+        if feature.supported_by(version) {
+            args.push(feature.flag().into());
+        }
+    }
+
+    let full_write = sandbox_policy.has_full_disk_write_access();
+    match &options.minimal_root {
+        None => {
+            let bind = if full_write { "--bind" } else { "--ro-bind" };
+            args.extend([bind.into(), "/".into(), "/".into()]);
+        }
+        Some(keep_mounts) => {
+            let bind_try = if full_write {
+                "--bind-try"
+            } else {
+                "--ro-bind-try"
+            };
+            for dir in MINIMAL_ROOT_DIRS {
+                args.extend([bind_try.into(), (*dir).into(), (*dir).into()]);
+            }
+            let bind = if full_write { "--bind" } else { "--ro-bind" };
+            for keep_mount in keep_mounts {
+                args.extend([bind.into(), keep_mount.into(), keep_mount.into()]);
+            }
+        }
+    }
+    // Either way the command gets an empty /tmp: a minimal root has none of
+    // its own. Mounted before the writable binds and the read-deny masks, so
+    // that those under /tmp stay visible on top of it.
+    let private_tmp = options.private_tmp.is_some();
+    if private_tmp || options.minimal_root.is_some() {
+        args.extend(["--tmpfs".into(), "/tmp".into()]);
+    }
+    // A minimal /dev with the usual pseudo-devices; `/dev/null` stays writable
+    // just like under Landlock.
+    args.extend(["--dev".into(), "/dev".into()]);
+    // The procfs of the new pid namespace, in place of the host's, whose
+    // `/proc/<pid>/root` and `/proc/<pid>/cwd` lead back into the host's
+    // mounts past the read-only binds.
+    if BwrapFeature::UnsharePid.supported_by(version) {
+        args.extend(["--proc".into(), "/proc".into()]);
+    }
+    for device in &options.devices {
+        args.extend([
+            "--dev-bind".into(),
+            device.clone().into(),
+            device.clone().into(),
+        ]);
+    }
+
+    // bwrap mounts every bind `nosuid` and always sets `PR_SET_NO_NEW_PRIVS`,
+    // so a setuid file the command creates in a writable root grants nothing.
+    if !full_write {
+        for writable_root in writable_roots_with_cwd(sandbox_policy, sandbox_policy_cwd)? {
+            // The private tmpfs replaces the host's /tmp, as under Landlock.
+            if private_tmp && writable_root.root == Path::new("/tmp") {
+                continue;
+            }
+            args.extend([
+                "--bind".into(),
+                writable_root.root.clone().into(),
+                writable_root.root.into(),
+            ]);
+            // Later mounts shadow earlier ones, so these must follow the
+            // writable bind of their root.
+            for subpath in writable_root.read_only_subpaths {
+                args.extend(["--ro-bind".into(), subpath.clone().into(), subpath.into()]);
+            }
+        }
+    }
+
+    // After the writable binds, so a path under a writable root is hidden
+    // too. A path that does not exist has nothing to hide.
+    for path in &options.read_deny {
+        let Ok(path) = path.canonicalize() else {
+            continue;
+        };
+        if path.is_dir() {
+            args.extend([
+                "--tmpfs".into(),
+                path.clone().into(),
+                "--remount-ro".into(),
+                path.into(),
+            ]);
+        } else {
+            args.extend(["--ro-bind".into(), "/dev/null".into(), path.into()]);
+        }
+    }
+
+    if !sandbox_policy.has_full_network_access() {
+        args.push("--unshare-net".into());
+    }
+
+    if let Some(argv0) = argv0
+        && BwrapFeature::Argv0.supported_by(version)
+    {
+        args.extend([BwrapFeature::Argv0.flag().into(), argv0.into()]);
+    }
+
+    // Without it, bwrap keeps the launcher's own directory when the sandbox
+    // has it, so the command could start somewhere else than under Landlock.
+    args.extend(["--chdir".into(), command_cwd.into()]);
+
+    // Separator so that command arguments starting with `-` are not parsed as
+    // options of bwrap itself.
+    args.push("--".into());
+    args.extend(command.iter().cloned());
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::landlock::DEFAULT_MAX_ROOTS;
+    use crate::landlock::NetworkMode;
+    use pretty_assertions::assert_eq;
+
+    fn options(private_tmp: bool, devices: &[&str]) -> SandboxOptions {
+        SandboxOptions {
+            network: NetworkMode::Blocked,
+            private_tmp: private_tmp.then(|| PathBuf::from("/tmp")),
+            devices: devices.iter().map(PathBuf::from).collect(),
+            minimal_root: None,
+            read_deny: Vec::new(),
+            max_roots: DEFAULT_MAX_ROOTS,
+            restrict_ioctls: false,
+            seccomp_tsync: false,
+        }
+    }
+
+    fn as_strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn read_only_policy_unshares_network() {
+        let args = build_bwrap_command(
+            Path::new("/usr/bin/bwrap"),
+            None,
+            &SandboxPolicy::ReadOnly,
+            Path::new("/work"),
+            &options(false, &[]),
+            Path::new("/work"),
+            None,
+            &[OsString::from("echo"), OsString::from("-n")],
+        )
+        .expect("build bwrap command");
+
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "/usr/bin/bwrap",
+                "--new-session",
+                "--die-with-parent",
+                "--unshare-pid",
+                "--ro-bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--unshare-net",
+                "--chdir",
+                "/work",
+                "--",
+                "echo",
+                "-n",
+            ]
+        );
+    }
+
+    #[test]
+    fn workspace_write_binds_writable_roots() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().join("cwd");
+        let extra = tmpdir.path().join("extra");
+        std::fs::create_dir_all(cwd.join(".git")).expect("create cwd/.git");
+        std::fs::create_dir_all(&extra).expect("create extra");
+        let policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![extra.clone()],
+            network_access: true,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        };
+
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            None,
+            &policy,
+            &cwd,
+            &options(false, &[]),
+            &cwd,
+            None,
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        let extra = extra.to_string_lossy().into_owned();
+        let git = cwd.join(".git").to_string_lossy().into_owned();
+        let cwd = cwd.to_string_lossy().into_owned();
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "bwrap".to_string(),
+                "--new-session".to_string(),
+                "--die-with-parent".to_string(),
+                "--unshare-pid".to_string(),
+                "--ro-bind".to_string(),
+                "/".to_string(),
+                "/".to_string(),
+                "--dev".to_string(),
+                "/dev".to_string(),
+                "--proc".to_string(),
+                "/proc".to_string(),
+                "--bind".to_string(),
+                extra.clone(),
+                extra,
+                "--bind".to_string(),
+                cwd.clone(),
+                cwd.clone(),
+                "--ro-bind".to_string(),
+                git.clone(),
+                git,
+                "--chdir".to_string(),
+                cwd,
+                "--".to_string(),
+                "true".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_requested_devices_are_bound() {
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            None,
+            &SandboxPolicy::DangerFullAccess,
+            Path::new("/work"),
+            &options(false, &["/dev/kvm", "/dev/dri"]),
+            Path::new("/work"),
+            None,
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "bwrap",
+                "--new-session",
+                "--die-with-parent",
+                "--unshare-pid",
+                "--bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--dev-bind",
+                "/dev/kvm",
+                "/dev/kvm",
+                "--dev-bind",
+                "/dev/dri",
+                "/dev/dri",
+                "--chdir",
+                "/work",
+                "--",
+                "true",
+            ]
+        );
+    }
+
+    #[test]
+    fn private_tmp_mounts_a_tmpfs_over_tmp() {
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            None,
+            &SandboxPolicy::DangerFullAccess,
+            Path::new("/work"),
+            &options(true, &[]),
+            Path::new("/work"),
+            Some("-sh"),
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "bwrap",
+                "--new-session",
+                "--die-with-parent",
+                "--unshare-pid",
+                "--bind",
+                "/",
+                "/",
+                "--tmpfs",
+                "/tmp",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--argv0",
+                "-sh",
+                "--chdir",
+                "/work",
+                "--",
+                "true",
+            ]
+        );
+    }
+
+    #[test]
+    fn read_deny_masks_existing_files_and_directories() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let root = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+        let secret = root.join("secret");
+        let ssh = root.join(".ssh");
+        std::fs::write(&secret, "hunter2").expect("write secret");
+        std::fs::create_dir(&ssh).expect("create .ssh");
+        let options = SandboxOptions {
+            read_deny: vec![secret.clone(), ssh.clone(), root.join("missing")],
+            ..options(false, &[])
+        };
+
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            None,
+            &SandboxPolicy::DangerFullAccess,
+            Path::new("/work"),
+            &options,
+            Path::new("/work"),
+            None,
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        let secret = secret.to_string_lossy().into_owned();
+        let ssh = ssh.to_string_lossy().into_owned();
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "bwrap".to_string(),
+                "--new-session".to_string(),
+                "--die-with-parent".to_string(),
+                "--unshare-pid".to_string(),
+                "--bind".to_string(),
+                "/".to_string(),
+                "/".to_string(),
+                "--dev".to_string(),
+                "/dev".to_string(),
+                "--proc".to_string(),
+                "/proc".to_string(),
+                "--ro-bind".to_string(),
+                "/dev/null".to_string(),
+                secret,
+                "--tmpfs".to_string(),
+                ssh.clone(),
+                "--remount-ro".to_string(),
+                ssh,
+                "--chdir".to_string(),
+                "/work".to_string(),
+                "--".to_string(),
+                "true".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn minimal_root_mounts_system_dirs_and_kept_mounts() {
+        let options = SandboxOptions {
+            minimal_root: Some(vec![PathBuf::from("/opt/toolchain")]),
+            ..options(false, &[])
+        };
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            None,
+            &SandboxPolicy::ReadOnly,
+            Path::new("/work"),
+            &options,
+            Path::new("/work"),
+            None,
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        let mut expected = vec![
+            "bwrap",
+            "--new-session",
+            "--die-with-parent",
+            "--unshare-pid",
+        ];
+        for dir in MINIMAL_ROOT_DIRS {
+            expected.extend(["--ro-bind-try", dir, dir]);
+        }
+        expected.extend([
+            "--ro-bind",
+            "/opt/toolchain",
+            "/opt/toolchain",
+            "--tmpfs",
+            "/tmp",
+            "--dev",
+            "/dev",
+            "--proc",
+            "/proc",
+            "--unshare-net",
+            "--chdir",
+            "/work",
+            "--",
+            "true",
+        ]);
+        assert_eq!(as_strings(args), expected);
+    }
+
+    #[test]
+    fn a_detected_nix_store_is_kept_read_only() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let nix = tmpdir.path().join("nix");
+        let store = nix.join("store");
+        assert_eq!(with_nix_store(Vec::new(), &store), Vec::<PathBuf>::new());
+
+        std::fs::create_dir_all(&store).expect("create the store fixture");
+        let keep_mounts = with_nix_store(vec![PathBuf::from("/opt")], &store);
+        assert_eq!(keep_mounts, vec![PathBuf::from("/opt"), store.clone()]);
+        // Already mounted along with its parent.
+        assert_eq!(with_nix_store(vec![nix.clone()], &store), vec![nix]);
+
+        let options = SandboxOptions {
+            minimal_root: Some(keep_mounts),
+            ..options(false, &[])
+        };
+        let args = as_strings(
+            build_bwrap_command(
+                Path::new("bwrap"),
+                None,
+                &SandboxPolicy::ReadOnly,
+                Path::new("/work"),
+                &options,
+                Path::new("/work"),
+                None,
+                &[OsString::from("true")],
+            )
+            .expect("build bwrap command"),
+        );
+        let store = store.to_string_lossy().into_owned();
+        assert!(
+            args.windows(3)
+                .any(|window| window == ["--ro-bind", store.as_str(), store.as_str()]),
+            "{args:?}"
+        );
+    }
+
+    /// Full argv for policies and options combined, where the order of the
+    /// mounts matters most: later mounts shadow earlier ones.
+    #[test]
+    fn argv_matrix() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let root = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+        let cwd = root.join("cwd");
+        let secret = root.join("cwd/secret");
+        std::fs::create_dir_all(cwd.join(".git")).expect("create cwd/.git");
+        std::fs::write(&secret, "hunter2").expect("write secret");
+        let workspace = |network_access| SandboxPolicy::WorkspaceWrite {
+            writable_roots: Vec::new(),
+            network_access,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        };
+        let cwd_str = cwd.to_string_lossy().into_owned();
+        let git = cwd.join(".git").to_string_lossy().into_owned();
+        let secret_str = secret.to_string_lossy().into_owned();
+        let chdir_cwd = vec!["--chdir".to_string(), cwd_str.clone()];
+        let cwd_binds = vec![
+            "--bind".to_string(),
+            cwd_str.clone(),
+            cwd_str.clone(),
+            "--ro-bind".to_string(),
+            git.clone(),
+            git.clone(),
+        ];
+        let minimal_root_dirs = |bind_try: &str| {
+            MINIMAL_ROOT_DIRS
+                .iter()
+                .flat_map(|dir| [bind_try.to_string(), dir.to_string(), dir.to_string()])
+                .collect::<Vec<_>>()
+        };
+        let strings = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        let cases = [
+            (
+                "workspace write without network, private tmp and a hidden file",
+                workspace(false),
+                SandboxOptions {
+                    read_deny: vec![secret.clone()],
+                    ..options(true, &[])
+                },
+                [
+                    strings(&[
+                        "bwrap",
+                        "--new-session",
+                        "--die-with-parent",
+                        "--unshare-pid",
+                    ]),
+                    strings(&["--ro-bind", "/", "/", "--tmpfs", "/tmp"]),
+                    strings(&["--dev", "/dev", "--proc", "/proc"]),
+                    cwd_binds.clone(),
+                    vec!["--ro-bind".to_string(), "/dev/null".to_string(), secret_str],
+                    strings(&["--unshare-net"]),
+                    chdir_cwd.clone(),
+                    strings(&["--", "true"]),
+                ]
+                .concat(),
+            ),
+            (
+                "workspace write with /tmp and a root under it, and private tmp",
+                SandboxPolicy::WorkspaceWrite {
+                    writable_roots: vec![PathBuf::from("/tmp/codex-build")],
+                    network_access: true,
+                    exclude_tmpdir_env_var: true,
+                    exclude_slash_tmp: false,
+                },
+                options(true, &[]),
+                [
+                    strings(&[
+                        "bwrap",
+                        "--new-session",
+                        "--die-with-parent",
+                        "--unshare-pid",
+                    ]),
+                    strings(&["--ro-bind", "/", "/", "--tmpfs", "/tmp"]),
+                    strings(&["--dev", "/dev", "--proc", "/proc"]),
+                    strings(&["--bind", "/tmp/codex-build", "/tmp/codex-build"]),
+                    cwd_binds.clone(),
+                    chdir_cwd.clone(),
+                    strings(&["--", "true"]),
+                ]
+                .concat(),
+            ),
+            (
+                "workspace write with network in a minimal root",
+                workspace(true),
+                SandboxOptions {
+                    minimal_root: Some(Vec::new()),
+                    ..options(false, &["/dev/kvm"])
+                },
+                [
+                    strings(&[
+                        "bwrap",
+                        "--new-session",
+                        "--die-with-parent",
+                        "--unshare-pid",
+                    ]),
+                    minimal_root_dirs("--ro-bind-try"),
+                    strings(&["--tmpfs", "/tmp", "--dev", "/dev", "--proc", "/proc"]),
+                    strings(&["--dev-bind", "/dev/kvm", "/dev/kvm"]),
+                    cwd_binds,
+                    chdir_cwd.clone(),
+                    strings(&["--", "true"]),
+                ]
+                .concat(),
+            ),
+            (
+                "full access in a minimal root",
+                SandboxPolicy::DangerFullAccess,
+                SandboxOptions {
+                    minimal_root: Some(vec![PathBuf::from("/opt")]),
+                    ..options(false, &[])
+                },
+                [
+                    strings(&[
+                        "bwrap",
+                        "--new-session",
+                        "--die-with-parent",
+                        "--unshare-pid",
+                    ]),
+                    minimal_root_dirs("--bind-try"),
+                    strings(&["--bind", "/opt", "/opt", "--tmpfs", "/tmp"]),
+                    strings(&["--dev", "/dev", "--proc", "/proc"]),
+                    chdir_cwd,
+                    strings(&["--", "true"]),
+                ]
+                .concat(),
+            ),
+        ];
+
+        for (name, policy, options, expected) in cases {
+            let args = build_bwrap_command(
+                Path::new("bwrap"),
+                None,
+                &policy,
+                &cwd,
+                &options,
+                &cwd,
+                None,
+                &[OsString::from("true")],
+            )
+            .expect("build bwrap command");
+            assert_eq!(as_strings(args), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn bwrap_versions_are_parsed() {
+        assert_eq!(
+            BwrapVersion::parse("bubblewrap 0.8.0\n"),
+            Some(BwrapVersion(0, 8, 0))
+        );
+        assert_eq!(
+            BwrapVersion::parse("bubblewrap 0.11.0-rc1"),
+            Some(BwrapVersion(0, 11, 0))
+        );
+        assert_eq!(
+            BwrapVersion::parse("bubblewrap 0.3"),
+            Some(BwrapVersion(0, 3, 0))
+        );
+        assert_eq!(BwrapVersion::parse("bwrap: Unknown option --version"), None);
+        assert_eq!(BwrapVersion::parse(""), None);
+    }
+
+    #[test]
+    fn features_are_gated_on_the_bwrap_version() {
+        assert_eq!(
+            unsupported_bwrap_features(Some(BwrapVersion(0, 1, 7)), true),
+            vec![
+                BwrapFeature::NewSession,
+                BwrapFeature::DieWithParent,
+                BwrapFeature::Argv0
+            ]
+        );
+        // `--argv0` came in 0.9.0.
+        assert_eq!(
+            unsupported_bwrap_features(Some(BwrapVersion(0, 8, 0)), true),
+            vec![BwrapFeature::Argv0]
+        );
+        assert_eq!(
+            unsupported_bwrap_features(Some(BwrapVersion(0, 8, 0)), false),
+            Vec::new()
+        );
+        assert_eq!(
+            unsupported_bwrap_features(Some(BwrapVersion(0, 9, 0)), true),
+            Vec::new()
+        );
+        // An unknown version is assumed to support everything.
+        assert_eq!(unsupported_bwrap_features(None, true), Vec::new());
+    }
+
+    #[test]
+    fn old_bwrap_gets_only_the_options_it_knows() {
+        let args = build_bwrap_command(
+            Path::new("bwrap"),
+            Some(BwrapVersion(0, 1, 2)),
+            &SandboxPolicy::DangerFullAccess,
+            Path::new("/work"),
+            &options(false, &[]),
+            Path::new("/work"),
+            Some("-sh"),
+            &[OsString::from("true")],
+        )
+        .expect("build bwrap command");
+
+        assert_eq!(
+            as_strings(args),
+            vec![
+                "bwrap",
+                "--unshare-pid",
+                "--bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--chdir",
+                "/work",
+                "--",
+                "true"
+            ]
+        );
+    }
+}
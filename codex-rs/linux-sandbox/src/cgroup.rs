@@ -0,0 +1,228 @@
+//! The cgroup v2 the command runs in, in `--wait` mode, when
+//! `--freeze-until-ready` or `--memory-report` asks for one. The child is
+//! moved into it right after the fork, before it installs the sandbox or
+//! execs anything.
+//!
+//! With `--freeze-until-ready`, the cgroup starts frozen and is only thawed
+//! once a ready signal arrives on a file descriptor, so nothing of the
+//! command runs before the caller has finished its own preparation. Either
+//! way, its `memory.peak` is read once the command has exited.
+
+use std::io::ErrorKind;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::fd::RawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::procfs::read_proc_file;
+
+/// A cgroup created for the command under the launcher's own cgroup.
+#[derive(Debug)]
+pub(crate) struct CommandCgroup {
+    dir: PathBuf,
+}
+
+impl CommandCgroup {
+    /// Creates the cgroup. When `frozen`, the freezer is engaged before
+    /// anything is moved into it, so processes moved into it stop
+    /// immediately.
+    fn create(frozen: bool) -> std::io::Result<Self> {
+        let mountinfo = read_proc_file("/proc/self/mountinfo")?;
+        let mount = cgroup2_mount_point(&mountinfo).ok_or_else(|| {
+            std::io::Error::new(ErrorKind::NotFound, "no cgroup2 filesystem is mounted")
+        })?;
+        let cgroup = read_proc_file("/proc/self/cgroup")?;
+        let own = unified_cgroup_path(&cgroup).ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::NotFound,
+                "this process is not in a cgroup v2 hierarchy",
+            )
+        })?;
+
+        let parent = mount.join(own.strip_prefix("/").unwrap_or(own));
+        let dir = parent.join(format!("codex-linux-sandbox-{}", std::process::id()));
+        std::fs::create_dir(&dir)
+            .map_err(|err| with_context(err, &format!("creating {}", dir.display())))?;
+        let cgroup = Self { dir };
+        if frozen && let Err(err) = cgroup.write("cgroup.freeze", "1") {
+            cgroup.remove();
+            return Err(err);
+        }
+        Ok(cgroup)
+    }
+
+    fn write(&self, file: &str, contents: &str) -> std::io::Result<()> {
+        let path = self.dir.join(file);
+        std::fs::write(&path, contents)
+            .map_err(|err| with_context(err, &format!("writing {}", path.display())))
+    }
+
+    fn thaw(&self) -> std::io::Result<()> {
+        self.write("cgroup.freeze", "0")
+    }
+
+    /// Highest memory usage of the cgroup so far, in bytes. `None` when the
+    /// kernel has no `memory.peak` (before 5.19) or the memory controller is
+    /// not enabled for the launcher's cgroup's children.
+    pub(crate) fn memory_peak(&self) -> Option<u64> {
+        std::fs::read_to_string(self.dir.join("memory.peak"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Best effort: the cgroup stays behind if the command left processes
+    /// running in it.
+    pub(crate) fn remove(self) {
+        let _ = std::fs::remove_dir(&self.dir);
+    }
+}
+
+/// State set up before the fork and consumed on either side of it.
+pub(crate) struct CgroupStart {
+    cgroup: CommandCgroup,
+    /// The `--freeze-until-ready` fd, when the cgroup starts frozen.
+    ready_fd: Option<RawFd>,
+    /// The child blocks reading this until the launcher closes `moved_write`,
+    /// which it does only once the child is in the cgroup.
+    moved_read: OwnedFd,
+    moved_write: OwnedFd,
+}
+
+impl CgroupStart {
+    pub(crate) fn prepare(ready_fd: Option<RawFd>) -> std::io::Result<Self> {
+        let cgroup = CommandCgroup::create(ready_fd.is_some())?;
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            let err = std::io::Error::last_os_error();
+            cgroup.remove();
+            return Err(err);
+        }
+        let (moved_read, moved_write) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        Ok(Self {
+            cgroup,
+            ready_fd,
+            moved_read,
+            moved_write,
+        })
+    }
+
+    /// Child side: returns once the child has been moved into the cgroup and,
+    /// if it started frozen, thawed.
+    pub(crate) fn wait_until_released(self) {
+        drop(self.moved_write);
+        // Only returns at end of file, after the launcher moved us and closed
+        // its end; a freezer keeps us here until the thaw.
+        let _ = read_one_byte(self.moved_read.as_raw_fd());
+    }
+
+    /// Launcher side: moves `pid` into the cgroup and, if it started frozen,
+    /// waits for the ready signal and thaws it. On error, the child is
+    /// killed.
+    pub(crate) fn release(self, pid: libc::pid_t) -> std::io::Result<CommandCgroup> {
+        let Self {
+            cgroup,
+            ready_fd,
+            moved_read,
+            moved_write,
+        } = self;
+        drop(moved_read);
+
+        let result = cgroup
+            .write("cgroup.procs", &pid.to_string())
+            .and_then(|()| {
+                drop(moved_write);
+                let Some(ready_fd) = ready_fd else {
+                    return Ok(());
+                };
+                read_one_byte(ready_fd)
+                    .map_err(|err| with_context(err, &format!("reading fd {ready_fd}")))
+                    .and_then(|()| cgroup.thaw())
+            });
+        match result {
+            Ok(()) => Ok(cgroup),
+            Err(err) => {
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Blocks until `fd` has a byte to read or reaches end of file.
+pub(crate) fn read_one_byte(fd: RawFd) -> std::io::Result<()> {
+    let mut byte = 0u8;
+    loop {
+        if unsafe { libc::read(fd, (&mut byte as *mut u8).cast(), 1) } >= 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+fn with_context(err: std::io::Error, context: &str) -> std::io::Error {
+    std::io::Error::new(err.kind(), format!("{context}: {err}"))
+}
+
+/// Mount point of the cgroup2 filesystem in `/proc/self/mountinfo` contents,
+/// which on hybrid hosts is not `/sys/fs/cgroup` itself.
+fn cgroup2_mount_point(mountinfo: &str) -> Option<PathBuf> {
+    mountinfo.lines().find_map(|line| {
+        let (mount, filesystem) = line.split_once(" - ")?;
+        if filesystem.split(' ').next()? != "cgroup2" {
+            return None;
+        }
+        mount.split(' ').nth(4).map(PathBuf::from)
+    })
+}
+
+/// Path of this process in the cgroup v2 hierarchy, from the `0::` line of
+/// `/proc/self/cgroup` contents.
+fn unified_cgroup_path(cgroup: &str) -> Option<&Path> {
+    cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(Path::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn finds_the_cgroup2_mount_on_hybrid_hosts() {
+        let mountinfo = "\
+25 30 0:22 / /sys/fs/cgroup rw,relatime - tmpfs tmpfs rw,mode=755
+26 25 0:23 / /sys/fs/cgroup/freezer rw,relatime - cgroup cgroup rw,freezer
+27 25 0:24 / /sys/fs/cgroup/unified rw,relatime - cgroup2 cgroup2 rw
+";
+        assert_eq!(
+            cgroup2_mount_point(mountinfo),
+            Some(PathBuf::from("/sys/fs/cgroup/unified"))
+        );
+        assert_eq!(
+            cgroup2_mount_point("26 25 0:23 / /sys/fs/cgroup/freezer rw - cgroup cgroup rw\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_the_unified_cgroup_path() {
+        let cgroup = "6:freezer:/\n4:memory:/jobs/1\n0::/user.slice/session-1.scope\n";
+        assert_eq!(
+            unified_cgroup_path(cgroup),
+            Some(Path::new("/user.slice/session-1.scope"))
+        );
+        assert_eq!(unified_cgroup_path("6:freezer:/\n"), None);
+    }
+}
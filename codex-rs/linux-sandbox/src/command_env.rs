@@ -0,0 +1,95 @@
+//! Variables the launcher sets in the command's environment, on top of the
+//! one it inherits. From lowest to highest precedence:
+//!
+//! 1. the launcher's own environment, with the `env` of a `--spec` applied;
+//! 2. `--env-allow` and `--env-deny`, which only remove variables;
+//! 3. variables derived from the sandbox, see [`derived_env`];
+//! 4. `--setenv`, which is neither filtered nor overridden.
+//!
+//! Derived variables are set before anything is computed from the policy,
+//! since a `workspace-write` policy may make `$TMPDIR` writable. `--setenv`
+//! only reaches the command: it is applied once the sandbox is in place, so
+//! it cannot widen the policy.
+
+use std::ffi::OsString;
+
+use crate::landlock::SandboxOptions;
+
+/// Variables that describe the sandbox to the command: `TMPDIR` points at
+/// the private tmp of `--private-tmp`.
+pub(crate) fn derived_env(options: &SandboxOptions) -> Vec<(String, OsString)> {
+    options
+        .private_tmp
+        .iter()
+        .map(|dir| ("TMPDIR".to_string(), dir.clone().into_os_string()))
+        .collect()
+}
+
+/// Sets `vars` in this process's environment, which the command inherits on
+/// exec.
+///
+/// # Safety
+///
+/// No other thread may be reading or writing the environment.
+pub(crate) unsafe fn set_vars(vars: &[(String, OsString)]) {
+    for (name, value) in vars {
+        unsafe {
+            std::env::set_var(name, value);
+        }
+    }
+}
+
+/// Parses a `--setenv` value, `NAME=VALUE`. The value may be empty and may
+/// contain `=`.
+pub(crate) fn parse_setenv(value: &str) -> Result<(String, OsString), String> {
+    let Some((name, value)) = value.split_once('=') else {
+        return Err("expected NAME=VALUE".to_string());
+    };
+    if name.is_empty() {
+        return Err("the variable name must not be empty".to_string());
+    }
+    if name.contains('\0') || value.contains('\0') {
+        return Err("the variable must not contain NUL bytes".to_string());
+    }
+    Ok((name.to_string(), OsString::from(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::protocol::SandboxPolicy;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn private_tmp_sets_tmpdir() {
+        let mut options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+        assert_eq!(derived_env(&options), Vec::new());
+
+        options.private_tmp = Some(PathBuf::from("/tmp/codex-private-tmp.abc"));
+        assert_eq!(
+            derived_env(&options),
+            vec![(
+                "TMPDIR".to_string(),
+                OsString::from("/tmp/codex-private-tmp.abc")
+            )]
+        );
+    }
+
+    #[test]
+    fn setenv_values_split_at_the_first_equals_sign() {
+        assert_eq!(
+            parse_setenv("KEY=a=b"),
+            Ok(("KEY".to_string(), OsString::from("a=b")))
+        );
+        assert_eq!(
+            parse_setenv("EMPTY="),
+            Ok(("EMPTY".to_string(), OsString::new()))
+        );
+        assert_eq!(parse_setenv("KEY"), Err("expected NAME=VALUE".to_string()));
+        assert_eq!(
+            parse_setenv("=value"),
+            Err("the variable name must not be empty".to_string())
+        );
+    }
+}
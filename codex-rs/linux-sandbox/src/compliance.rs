@@ -0,0 +1,417 @@
+//! `--compliance-log`: one JSON object per run, appended to a log and synced
+//! to disk before the command is exec'd, recording which paths the command
+//! was given and with what access.
+//!
+//! Unlike the `--manifest`, which describes the policy, the `paths` of a
+//! record describe what the backend applied:
+//!
+//! - under Landlock, read access to `/` and write access to `/dev/null` and
+//!   each writable path of the ruleset. Landlock cannot make a subpath of a
+//!   writable root read-only, so read-only subpaths are not listed;
+//! - under bwrap, each mount of its command line, in order, later mounts
+//!   shadowing earlier ones;
+//! - without a sandbox, write access to `/`.
+//!
+//! Fields: `timestamp` (RFC 3339, UTC), `backend`, `policy`, `cwd`, `argv`
+//! (as in the manifest), `network`, `paths` (each with a `path` and an
+//! `access` of `read-write`, `read-only`, `hidden`, `tmpfs` or `dev`),
+//! `enforced` and `enforcement_reason`.
+
+use std::ffi::CString;
+use std::fmt;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use codex_core::error::Result;
+use codex_core::protocol::SandboxPolicy;
+
+use crate::backend::SelectedBackend;
+use crate::enforcement::EnforcementVerdict;
+use crate::enforcement::SandboxEnforcement;
+use crate::json::json_string;
+use crate::json::json_string_array;
+use crate::landlock::LandlockRules;
+use crate::landlock::SandboxOptions;
+
+/// What the command may do with an applied path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    ReadWrite,
+    ReadOnly,
+    /// Replaced by `/dev/null` or an empty read-only directory.
+    Hidden,
+    /// An empty writable tmpfs, discarded when the sandbox exits.
+    Tmpfs,
+    /// bwrap's minimal `/dev`, with only the usual pseudo-devices.
+    Dev,
+}
+
+impl Access {
+    fn name(self) -> &'static str {
+        match self {
+            Access::ReadWrite => "read-write",
+            Access::ReadOnly => "read-only",
+            Access::Hidden => "hidden",
+            Access::Tmpfs => "tmpfs",
+            Access::Dev => "dev",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AppliedPath {
+    path: PathBuf,
+    access: Access,
+}
+
+/// The record of one run, but for its verdict and timestamp, which are only
+/// known once the sandbox is in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ComplianceRecord {
+    backend: &'static str,
+    policy: String,
+    cwd: PathBuf,
+    argv: Vec<String>,
+    network: &'static str,
+    paths: Vec<AppliedPath>,
+}
+
+impl ComplianceRecord {
+    pub(crate) fn new(
+        selected_backend: &SelectedBackend,
+        sandbox_policy: &SandboxPolicy,
+        sandbox_policy_cwd: &Path,
+        options: &SandboxOptions,
+        landlock_abi: i32,
+        command_cwd: &Path,
+        argv: &[CString],
+    ) -> Result<Self> {
+        let paths = match selected_backend {
+            SelectedBackend::Landlock => {
+                // Degradations are the self-test's concern, not this record's.
+                let rules = LandlockRules::for_policy(
+                    sandbox_policy,
+                    options,
+                    sandbox_policy_cwd,
+                    landlock_abi,
+                    &mut SandboxEnforcement::default(),
+                )?;
+                landlock_paths(rules.as_ref())
+            }
+            SelectedBackend::Bwrap { .. } => bwrap_paths(argv),
+            SelectedBackend::None => vec![AppliedPath {
+                path: PathBuf::from("/"),
+                access: Access::ReadWrite,
+            }],
+        };
+        Ok(Self {
+            backend: selected_backend.name(),
+            policy: sandbox_policy.to_string(),
+            cwd: command_cwd.to_path_buf(),
+            argv: argv
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            network: options.network.name(),
+            paths,
+        })
+    }
+
+    /// Single-line JSON object, without the trailing newline.
+    pub(crate) fn to_json(&self, timestamp: SystemTime, verdict: &EnforcementVerdict) -> String {
+        let paths = self
+            .paths
+            .iter()
+            .map(|applied| {
+                format!(
+                    r#"{{"path":{},"access":{}}}"#,
+                    json_string(&applied.path.to_string_lossy()),
+                    json_string(applied.access.name()),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"timestamp":{},"backend":{},"policy":{},"cwd":{},"argv":{},"network":{},"paths":[{paths}],"enforced":{},"enforcement_reason":{}}}"#,
+            json_string(&rfc3339(timestamp)),
+            json_string(self.backend),
+            json_string(&self.policy),
+            json_string(&self.cwd.to_string_lossy()),
+            json_string_array(&self.argv),
+            json_string(self.network),
+            verdict.enforced,
+            json_string(&verdict.reason),
+        )
+    }
+}
+
+fn landlock_paths(rules: Option<&LandlockRules>) -> Vec<AppliedPath> {
+    let Some(rules) = rules else {
+        return vec![AppliedPath {
+            path: PathBuf::from("/"),
+            access: Access::ReadWrite,
+        }];
+    };
+    let mut paths = vec![
+        AppliedPath {
+            path: PathBuf::from("/"),
+            access: Access::ReadOnly,
+        },
+        AppliedPath {
+            path: PathBuf::from("/dev/null"),
+            access: Access::ReadWrite,
+        },
+    ];
+    paths.extend(rules.writable_roots.iter().map(|root| AppliedPath {
+        path: root.clone(),
+        access: Access::ReadWrite,
+    }));
+    paths
+}
+
+/// The mounts of a bwrap command line as built by
+/// [`crate::bwrap::build_bwrap_command`], up to the `--` before the command.
+fn bwrap_paths(argv: &[CString]) -> Vec<AppliedPath> {
+    let args: Vec<String> = argv
+        .iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .take_while(|arg| arg != "--")
+        .collect();
+    let mut paths: Vec<AppliedPath> = Vec::new();
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        match option.as_str() {
+            "--bind" | "--bind-try" | "--dev-bind" | "--ro-bind" | "--ro-bind-try" => {
+                let (Some(source), Some(dest)) = (args.next(), args.next()) else {
+                    break;
+                };
+                let access = match option.as_str() {
+                    "--ro-bind" | "--ro-bind-try"
+                        if source == "/dev/null" && dest != "/dev/null" =>
+                    {
+                        Access::Hidden
+                    }
+                    "--ro-bind" | "--ro-bind-try" => Access::ReadOnly,
+                    _ => Access::ReadWrite,
+                };
+                paths.push(AppliedPath {
+                    path: PathBuf::from(dest),
+                    access,
+                });
+            }
+            "--tmpfs" | "--dev" => {
+                let Some(dest) = args.next() else {
+                    break;
+                };
+                let access = if option == "--tmpfs" {
+                    Access::Tmpfs
+                } else {
+                    Access::Dev
+                };
+                paths.push(AppliedPath {
+                    path: PathBuf::from(dest),
+                    access,
+                });
+            }
+            "--remount-ro" => {
+                let Some(dest) = args.next() else {
+                    break;
+                };
+                // Only follows the tmpfs of a hidden directory.
+                if let Some(applied) = paths
+                    .iter_mut()
+                    .rev()
+                    .find(|applied| applied.path == Path::new(dest))
+                {
+                    applied.access = if applied.access == Access::Tmpfs {
+                        Access::Hidden
+                    } else {
+                        Access::ReadOnly
+                    };
+                }
+            }
+            "--chdir" | "--argv0" => {
+                args.next();
+            }
+            _ => {}
+        }
+    }
+    paths
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = libc::time_t::try_from(since_epoch.as_secs()).unwrap_or(libc::time_t::MAX);
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::gmtime_r(&seconds, &mut tm) }.is_null() {
+        return "unknown".to_string();
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The `--compliance-log` file, opened for appending before the sandbox is
+/// installed, like the [`crate::manifest::ManifestFile`].
+#[derive(Debug)]
+pub(crate) struct ComplianceLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl ComplianceLog {
+    pub(crate) fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Appends `record` as one line in a single write, so concurrent runs
+    /// appending to the same log never interleave, and waits until it is on
+    /// disk.
+    pub(crate) fn append(
+        &mut self,
+        record: &ComplianceRecord,
+        verdict: &EnforcementVerdict,
+    ) -> std::io::Result<()> {
+        let line = format!("{}\n", record.to_json(SystemTime::now(), verdict));
+        let written = self.file.write(line.as_bytes())?;
+        if written != line.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                format!("only {written} of {} bytes were written", line.len()),
+            ));
+        }
+        self.file.sync_data()
+    }
+}
+
+impl fmt::Display for ComplianceLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn applied(path: &str, access: Access) -> AppliedPath {
+        AppliedPath {
+            path: PathBuf::from(path),
+            access,
+        }
+    }
+
+    #[test]
+    fn bwrap_mounts_are_read_from_its_command_line() {
+        let argv: Vec<CString> = [
+            "bwrap",
+            "--new-session",
+            "--ro-bind",
+            "/",
+            "/",
+            "--dev",
+            "/dev",
+            "--bind",
+            "/work",
+            "/work",
+            "--ro-bind",
+            "/work/.git",
+            "/work/.git",
+            "--ro-bind",
+            "/dev/null",
+            "/work/.env",
+            "--tmpfs",
+            "/home/me/.ssh",
+            "--remount-ro",
+            "/home/me/.ssh",
+            "--tmpfs",
+            "/tmp",
+            "--unshare-net",
+            "--chdir",
+            "/work",
+            "--",
+            "--bind",
+            "/",
+            "/",
+        ]
+        .into_iter()
+        .map(|arg| CString::new(arg).expect("no NUL"))
+        .collect();
+
+        assert_eq!(
+            bwrap_paths(&argv),
+            vec![
+                applied("/", Access::ReadOnly),
+                applied("/dev", Access::Dev),
+                applied("/work", Access::ReadWrite),
+                applied("/work/.git", Access::ReadOnly),
+                applied("/work/.env", Access::Hidden),
+                applied("/home/me/.ssh", Access::Hidden),
+                applied("/tmp", Access::Tmpfs),
+            ]
+        );
+    }
+
+    #[test]
+    fn landlock_lists_dev_null_and_the_writable_roots() {
+        let rules = LandlockRules {
+            writable_roots: vec![PathBuf::from("/work")],
+            restrict_network: true,
+        };
+
+        assert_eq!(
+            landlock_paths(Some(&rules)),
+            vec![
+                applied("/", Access::ReadOnly),
+                applied("/dev/null", Access::ReadWrite),
+                applied("/work", Access::ReadWrite),
+            ]
+        );
+        assert_eq!(landlock_paths(None), vec![applied("/", Access::ReadWrite)]);
+    }
+
+    #[test]
+    fn records_are_appended_one_per_line() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let path = tmpdir.path().join("compliance.jsonl");
+        let record = ComplianceRecord {
+            backend: "none",
+            policy: "danger-full-access".to_string(),
+            cwd: PathBuf::from("/work"),
+            argv: vec!["true".to_string()],
+            network: "full",
+            paths: vec![applied("/", Access::ReadWrite)],
+        };
+        let verdict = EnforcementVerdict::unsandboxed();
+
+        for _ in 0..2 {
+            let mut log = ComplianceLog::open(path.clone()).expect("open log");
+            log.append(&record, &verdict).expect("append record");
+        }
+
+        let expected = r#"{"timestamp":"1970-01-01T00:00:01.500Z","backend":"none","policy":"danger-full-access","cwd":"/work","argv":["true"],"network":"full","paths":[{"path":"/","access":"read-write"}],"enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox"}"#;
+        assert_eq!(
+            record.to_json(UNIX_EPOCH + Duration::from_millis(1500), &verdict),
+            expected
+        );
+        let log = std::fs::read_to_string(&path).expect("read log");
+        assert_eq!(log.lines().count(), 2, "{log}");
+        assert!(log.ends_with("\n"), "{log}");
+    }
+}
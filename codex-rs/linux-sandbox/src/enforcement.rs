@@ -0,0 +1,304 @@
+//! Record of what the sandbox actually enforced on this host, which can be
+//! less than the policy asked for on kernels with partial Landlock support.
+
+use std::fmt;
+
+use clap::ValueEnum;
+use codex_core::error::CodexErr;
+use codex_core::error::Result;
+use codex_core::error::SandboxErr;
+
+/// How to handle restrictions the host cannot apply, set with
+/// `--enforcement`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum EnforcementMode {
+    /// Install whatever the kernel supports and run the command anyway,
+    /// recording the gaps as [`Degradation`]s.
+    #[default]
+    BestEffort,
+    /// Fail unless every requested restriction is enforced by the preferred
+    /// mechanism: no degradations and no fallback from seccomp to bwrap.
+    Strict,
+}
+
+impl EnforcementMode {
+    /// `--require-enforcement` is shorthand for `--enforcement strict`, and
+    /// takes precedence over `--enforcement best-effort`.
+    pub(crate) fn from_flags(enforcement: Self, require_enforcement: bool) -> Self {
+        if require_enforcement {
+            Self::Strict
+        } else {
+            enforcement
+        }
+    }
+}
+
+/// Enforcement report returned by
+/// [`install_sandbox_on_current_thread`](crate::install_sandbox_on_current_thread).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SandboxEnforcement {
+    /// Whether `PR_SET_NO_NEW_PRIVS` was set. It is set whenever any other
+    /// restriction is installed.
+    pub no_new_privs: bool,
+    pub network: NetworkEnforcement,
+    /// Outcome of the Landlock ruleset, or `None` when the policy needed none.
+    pub landlock: Option<LandlockEnforcement>,
+    /// Restrictions the policy asked for that this host could not apply.
+    pub degradations: Vec<Degradation>,
+}
+
+/// How network access was restricted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkEnforcement {
+    /// The policy allows network access.
+    #[default]
+    Unrestricted,
+    /// Seccomp filter allowing only AF_UNIX sockets.
+    Seccomp,
+    /// Seccomp filter allowing AF_UNIX and UDP sockets.
+    SeccompWithUdp,
+    /// Loopback-only network namespace plus a seccomp filter allowing only
+    /// AF_UNIX and IP sockets.
+    LoopbackNamespace,
+}
+
+/// How much of the Landlock ruleset the kernel enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandlockEnforcement {
+    Full,
+    /// The kernel's ABI predates some of the requested filesystem rights,
+    /// which are then not restricted.
+    Partial,
+}
+
+/// A restriction that was skipped rather than failing the whole install.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// Landlock on this kernel predates network rules, so TCP bind/connect
+    /// are only blocked by the seccomp filter.
+    LandlockNetworkUnsupported { abi: i32 },
+    /// Landlock on this kernel predates some of the filesystem rights the
+    /// ruleset handles, which are then not restricted.
+    LandlockFilesystemRightsUnsupported { abi: i32 },
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Degradation::LandlockNetworkUnsupported { abi } => write!(
+                f,
+                "Landlock ABI {abi} cannot restrict network access (ABI 4 is required)"
+            ),
+            Degradation::LandlockFilesystemRightsUnsupported { abi } => write!(
+                f,
+                "Landlock ABI {abi} cannot restrict every filesystem right (ABI 5 is required)"
+            ),
+        }
+    }
+}
+
+impl SandboxEnforcement {
+    /// Fails in [`EnforcementMode::Strict`] if any requested restriction was
+    /// skipped.
+    pub(crate) fn ensure(&self, mode: EnforcementMode) -> Result<()> {
+        match mode {
+            EnforcementMode::BestEffort => Ok(()),
+            EnforcementMode::Strict => self.ensure_fully_enforced(),
+        }
+    }
+
+    /// Fails if any requested restriction was skipped.
+    pub(crate) fn ensure_fully_enforced(&self) -> Result<()> {
+        if self.degradations.is_empty() {
+            return Ok(());
+        }
+
+        let reasons = self
+            .degradations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(CodexErr::Sandbox(SandboxErr::PartiallyEnforced(reasons)))
+    }
+}
+
+/// Whether the command actually runs sandboxed, as reported in the manifest
+/// and the `--wait` report.
+///
+/// Only [`EnforcementVerdict::for_landlock`] can say the sandbox is
+/// enforced, and only when the self-test (see [`crate::self_test`]) passed
+/// in the sandboxed process itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EnforcementVerdict {
+    pub(crate) enforced: bool,
+    pub(crate) reason: String,
+}
+
+impl EnforcementVerdict {
+    /// `--backend none`.
+    pub(crate) fn unsandboxed() -> Self {
+        Self {
+            enforced: false,
+            reason: "--backend none runs the command without a sandbox".to_string(),
+        }
+    }
+
+    /// bwrap sets up the sandbox while exec'ing the command, after the last
+    /// point where the launcher could test it.
+    pub(crate) fn unverified_bwrap() -> Self {
+        Self {
+            enforced: false,
+            reason: "bwrap enforces the policy, but the self-test cannot run inside it".to_string(),
+        }
+    }
+
+    /// In `--wait` mode, when the child exited before reporting a verdict.
+    pub(crate) fn never_reported() -> Self {
+        Self {
+            enforced: false,
+            reason: "the command's process exited before the sandbox was verified".to_string(),
+        }
+    }
+
+    /// After the Landlock backend installed `enforcement`. `self_test` is the
+    /// outcome of [`crate::self_test::SelfTest::run`].
+    pub(crate) fn for_landlock(
+        enforcement: &SandboxEnforcement,
+        self_test: std::result::Result<(), String>,
+    ) -> Self {
+        if let Err(e) = self_test {
+            return Self {
+                enforced: false,
+                reason: format!("the self-test failed: {e}"),
+            };
+        }
+        if !enforcement.degradations.is_empty() {
+            let gaps = enforcement
+                .degradations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Self {
+                enforced: false,
+                reason: format!("best-effort enforcement left gaps: {gaps}"),
+            };
+        }
+        Self {
+            enforced: true,
+            reason: "the self-test confirmed every restriction the policy asked for".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn full_enforcement_passes() {
+        assert!(
+            SandboxEnforcement::default()
+                .ensure_fully_enforced()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn require_enforcement_means_strict() {
+        for (enforcement, require_enforcement, expected) in [
+            (
+                EnforcementMode::BestEffort,
+                false,
+                EnforcementMode::BestEffort,
+            ),
+            (EnforcementMode::BestEffort, true, EnforcementMode::Strict),
+            (EnforcementMode::Strict, false, EnforcementMode::Strict),
+            (EnforcementMode::Strict, true, EnforcementMode::Strict),
+        ] {
+            assert_eq!(
+                EnforcementMode::from_flags(enforcement, require_enforcement),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn best_effort_accepts_degradations() {
+        let enforcement = SandboxEnforcement {
+            degradations: vec![
+                Degradation::LandlockNetworkUnsupported { abi: 3 },
+                Degradation::LandlockFilesystemRightsUnsupported { abi: 3 },
+            ],
+            ..Default::default()
+        };
+
+        assert!(enforcement.ensure(EnforcementMode::BestEffort).is_ok());
+        let err = enforcement
+            .ensure(EnforcementMode::Strict)
+            .expect_err("strict mode rejects degradations");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox is only partially enforced on this host: Landlock ABI 3 cannot restrict network access (ABI 4 is required); Landlock ABI 3 cannot restrict every filesystem right (ABI 5 is required)"
+        );
+    }
+
+    #[test]
+    fn degradations_fail_full_enforcement() {
+        let enforcement = SandboxEnforcement {
+            degradations: vec![Degradation::LandlockNetworkUnsupported { abi: 3 }],
+            ..Default::default()
+        };
+
+        let err = enforcement
+            .ensure_fully_enforced()
+            .expect_err("network rules were skipped");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: sandbox is only partially enforced on this host: Landlock ABI 3 cannot restrict network access (ABI 4 is required)"
+        );
+    }
+
+    #[test]
+    fn only_a_passing_self_test_without_gaps_is_enforced() {
+        let full = SandboxEnforcement {
+            no_new_privs: true,
+            network: NetworkEnforcement::Seccomp,
+            landlock: Some(LandlockEnforcement::Full),
+            degradations: Vec::new(),
+        };
+        let degraded = SandboxEnforcement {
+            landlock: Some(LandlockEnforcement::Partial),
+            degradations: vec![Degradation::LandlockFilesystemRightsUnsupported { abi: 3 }],
+            ..full.clone()
+        };
+
+        assert_eq!(
+            EnforcementVerdict::for_landlock(&full, Ok(())),
+            EnforcementVerdict {
+                enforced: true,
+                reason: "the self-test confirmed every restriction the policy asked for"
+                    .to_string(),
+            }
+        );
+        assert_eq!(
+            EnforcementVerdict::for_landlock(&full, Err("no_new_privs is not set".to_string())),
+            EnforcementVerdict {
+                enforced: false,
+                reason: "the self-test failed: no_new_privs is not set".to_string(),
+            }
+        );
+        assert_eq!(
+            EnforcementVerdict::for_landlock(&degraded, Ok(())),
+            EnforcementVerdict {
+                enforced: false,
+                reason: "best-effort enforcement left gaps: Landlock ABI 3 cannot restrict every filesystem right (ABI 5 is required)".to_string(),
+            }
+        );
+        assert!(!EnforcementVerdict::unsandboxed().enforced);
+        assert!(!EnforcementVerdict::unverified_bwrap().enforced);
+        assert!(!EnforcementVerdict::never_reported().enforced);
+    }
+}
@@ -0,0 +1,171 @@
+//! Filtering of the environment the command inherits, for `--env-allow` and
+//! `--env-deny`.
+//!
+//! Patterns are variable names in which `*` matches any run of characters and
+//! `?` matches a single one. A variable that matches a deny pattern is always
+//! removed, even if an allow pattern matches it too.
+
+/// Credential variables removed whenever the environment is filtered, unless
+/// `--no-default-env-deny` is given.
+pub(crate) const DEFAULT_ENV_DENY: &[&str] = &[
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "AZURE_CLIENT_SECRET",
+    "GH_TOKEN",
+    "GITHUB_TOKEN",
+    "GITLAB_TOKEN",
+    "NPM_TOKEN",
+    "OPENAI_API_KEY",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EnvFilter {
+    /// When empty, every variable not denied is kept.
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl EnvFilter {
+    /// Returns `None` when no filtering was requested, in which case the
+    /// environment is passed through untouched, default deny set included.
+    pub(crate) fn new(allow: Vec<String>, deny: Vec<String>, default_deny: bool) -> Option<Self> {
+        if allow.is_empty() && deny.is_empty() {
+            return None;
+        }
+
+        let mut deny = deny;
+        if default_deny {
+            deny.extend(DEFAULT_ENV_DENY.iter().map(ToString::to_string));
+        }
+        Some(Self { allow, deny })
+    }
+
+    pub(crate) fn allow(&self) -> &[String] {
+        &self.allow
+    }
+
+    /// The deny patterns, default deny set included.
+    pub(crate) fn deny(&self) -> &[String] {
+        &self.deny
+    }
+
+    pub(crate) fn keeps(&self, name: &str) -> bool {
+        if self.deny.iter().any(|pattern| glob_matches(pattern, name)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|pattern| glob_matches(pattern, name))
+    }
+
+    /// Removes the variables the filter does not keep from this process's
+    /// environment, which the command inherits on exec.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may be reading or writing the environment.
+    pub(crate) unsafe fn apply_to_process_env(&self) {
+        for (name, _) in std::env::vars_os() {
+            if !self.keeps(&name.to_string_lossy()) {
+                unsafe {
+                    std::env::remove_var(&name);
+                }
+            }
+        }
+    }
+}
+
+/// Matches `name` against a pattern with `*` and `?` wildcards.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it was tried at, to
+    // backtrack to when the rest of the pattern stops matching.
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn filter(allow: &[&str], deny: &[&str], default_deny: bool) -> EnvFilter {
+        EnvFilter::new(
+            allow.iter().map(ToString::to_string).collect(),
+            deny.iter().map(ToString::to_string).collect(),
+            default_deny,
+        )
+        .expect("filtering was requested")
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        for (pattern, name, expected) in [
+            ("PATH", "PATH", true),
+            ("PATH", "PATHS", false),
+            ("*", "", true),
+            ("*", "ANYTHING", true),
+            ("LC_*", "LC_ALL", true),
+            ("LC_*", "LANG", false),
+            ("*_TOKEN", "GITHUB_TOKEN", true),
+            ("*_TOKEN", "GITHUB_TOKENS", false),
+            ("A*B*C", "AxxBxxBxxC", true),
+            ("A*B*C", "AxxBxx", false),
+            ("HOM?", "HOME", true),
+            ("HOM?", "HOM", false),
+        ] {
+            assert_eq!(glob_matches(pattern, name), expected, "{pattern} vs {name}");
+        }
+    }
+
+    #[test]
+    fn nothing_requested_means_no_filter() {
+        assert_eq!(EnvFilter::new(vec![], vec![], true), None);
+    }
+
+    #[test]
+    fn deny_beats_allow() {
+        let filter = filter(&["*"], &["SECRET_*"], false);
+
+        assert!(filter.keeps("PATH"));
+        assert!(!filter.keeps("SECRET_KEY"));
+    }
+
+    #[test]
+    fn allowlist_keeps_only_matching_names() {
+        let filter = filter(&["PATH", "LC_*"], &[], false);
+
+        assert!(filter.keeps("PATH"));
+        assert!(filter.keeps("LC_ALL"));
+        assert!(!filter.keeps("HOME"));
+    }
+
+    #[test]
+    fn default_deny_set_applies_unless_disabled() {
+        assert!(!filter(&["*"], &[], true).keeps("GITHUB_TOKEN"));
+        assert!(filter(&["*"], &[], false).keeps("GITHUB_TOKEN"));
+        // Deny patterns alone also enable filtering, and the defaults with it.
+        assert!(!filter(&[], &["SECRET"], true).keeps("AWS_SECRET_ACCESS_KEY"));
+    }
+}
@@ -0,0 +1,92 @@
+//! `--events-fd`: newline-delimited JSON events written as each step of the
+//! sandbox setup completes, for tools that follow a run live instead of
+//! reading the manifest afterwards.
+//!
+//! Events, in the order they can occur:
+//!
+//! - `{"event":"bwrap_fallback"}`: `--backend auto` fell back to bwrap
+//!   because seccomp is unavailable.
+//! - `{"event":"seccomp_installed","network":"blocked"}`: the seccomp network
+//!   filter is installed, with the network mode it enforces.
+//! - `{"event":"landlock_installed","abi":3,"full":true}`: the Landlock
+//!   ruleset is in effect; `full` is false when the kernel's ABI predates
+//!   some of the requested rights.
+//! - `{"event":"enforcement","enforced":true,"reason":"..."}`: the
+//!   self-test verdict; see [`crate::self_test`].
+//! - `{"event":"exec","argv":[...]}`: the argv about to be exec'd, including
+//!   any bwrap or exec wrapper. Written before a syscall allowlist is
+//!   installed, since the allowlist may deny the write.
+//!
+//! In `--wait` mode the events come from the forked child.
+
+use std::fs::File;
+use std::io::Write as _;
+use std::os::fd::FromRawFd;
+use std::os::fd::RawFd;
+
+use tracing::warn;
+
+use crate::json::json_string;
+use crate::wait::set_cloexec;
+
+/// Without `--events-fd`, the default stream drops every event.
+#[derive(Debug, Default)]
+pub(crate) struct EventStream {
+    /// `None` without `--events-fd`, or once a write has failed.
+    file: Option<(RawFd, File)>,
+}
+
+impl EventStream {
+    /// Takes ownership of `fd`, marking it close-on-exec so the command
+    /// cannot write to it.
+    pub(crate) fn from_fd(fd: RawFd) -> std::io::Result<Self> {
+        set_cloexec(fd)?;
+        Ok(Self {
+            file: Some((fd, unsafe { File::from_raw_fd(fd) })),
+        })
+    }
+
+    /// Writes one event. `fields` hold already-encoded JSON values. After a
+    /// failed write, which usually means the reader went away, the stream
+    /// stops rather than failing the run.
+    pub(crate) fn emit(&mut self, event: &str, fields: &[(&str, String)]) {
+        let Some((fd, file)) = &mut self.file else {
+            return;
+        };
+        // One write per event, so a reader never sees half a line.
+        if let Err(e) = file.write_all(event_line(event, fields).as_bytes()) {
+            warn!("could not write to --events-fd {fd}, dropping further events: {e}");
+            self.file = None;
+        }
+    }
+}
+
+fn event_line(event: &str, fields: &[(&str, String)]) -> String {
+    let mut line = format!(r#"{{"event":{}"#, json_string(event));
+    for (name, value) in fields {
+        line.push_str(&format!(",{}:{value}", json_string(name)));
+    }
+    line.push_str("}\n");
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn events_are_single_json_lines() {
+        assert_eq!(
+            event_line("bwrap_fallback", &[]),
+            "{\"event\":\"bwrap_fallback\"}\n"
+        );
+        assert_eq!(
+            event_line(
+                "landlock_installed",
+                &[("abi", "3".to_string()), ("full", "true".to_string())]
+            ),
+            "{\"event\":\"landlock_installed\",\"abi\":3,\"full\":true}\n"
+        );
+    }
+}
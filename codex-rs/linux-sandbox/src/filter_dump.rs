@@ -0,0 +1,361 @@
+//! Human-readable dump of the seccomp programs and Landlock ruleset that are
+//! about to be installed, written by `--dump-filter` for security review.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use codex_core::error::Result;
+use codex_core::protocol::SandboxPolicy;
+use seccompiler::sock_filter;
+
+use crate::backend::SelectedBackend;
+use crate::bwrap::BWRAP_VERSION;
+use crate::bwrap::build_bwrap_command;
+use crate::enforcement::SandboxEnforcement;
+use crate::ioctl_filter::ALLOWED_IOCTLS;
+use crate::ioctl_filter::build_ioctl_filter;
+use crate::landlock::LandlockRules;
+use crate::landlock::NetworkMode;
+use crate::landlock::SandboxOptions;
+use crate::landlock::build_network_seccomp_filter;
+use crate::landlock::landlock_abi;
+use crate::syscall_allowlist::SyscallAllowlist;
+use crate::syscall_allowlist::SyscallDenyAction;
+use crate::syscall_allowlist::build_syscall_allowlist_filter;
+use crate::syscalls::syscall_name;
+
+/// Writes the dump for this invocation to `path`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_filter_dump(
+    path: &Path,
+    sandbox_policy: &SandboxPolicy,
+    options: &SandboxOptions,
+    sandbox_policy_cwd: &Path,
+    selected_backend: &SelectedBackend,
+    syscall_allowlist: Option<(&SyscallAllowlist, SyscallDenyAction)>,
+    command_cwd: &Path,
+    command: &[OsString],
+) -> Result<()> {
+    let dump = render_filter_dump(
+        sandbox_policy,
+        options,
+        sandbox_policy_cwd,
+        selected_backend,
+        syscall_allowlist,
+        command_cwd,
+        command,
+        landlock_abi(),
+    )?;
+    std::fs::write(path, dump)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_filter_dump(
+    sandbox_policy: &SandboxPolicy,
+    options: &SandboxOptions,
+    sandbox_policy_cwd: &Path,
+    selected_backend: &SelectedBackend,
+    syscall_allowlist: Option<(&SyscallAllowlist, SyscallDenyAction)>,
+    command_cwd: &Path,
+    command: &[OsString],
+    landlock_abi: i32,
+) -> Result<String> {
+    let mut dump = String::new();
+    let network = options.network;
+
+    match selected_backend {
+        SelectedBackend::Landlock => {
+            if network == NetworkMode::LoopbackOnly {
+                dump.push_str("# private network namespace with only lo\n\n");
+            }
+            if network != NetworkMode::Full {
+                dump.push_str("# seccomp network filter\n");
+                dump.push_str(&disassemble(&build_network_seccomp_filter(network)?));
+                dump.push('\n');
+            }
+
+            let _ = writeln!(dump, "# Landlock ruleset (kernel ABI {landlock_abi})");
+            match LandlockRules::for_policy(
+                sandbox_policy,
+                options,
+                sandbox_policy_cwd,
+                landlock_abi,
+                &mut SandboxEnforcement::default(),
+            )? {
+                Some(rules) => dump.push_str(&describe_landlock_rules(&rules)),
+                None => dump.push_str("not installed: the policy allows writing anywhere\n"),
+            }
+        }
+        SelectedBackend::Bwrap { bwrap } => {
+            dump.push_str("# bwrap\n");
+            let argv = build_bwrap_command(
+                bwrap,
+                *BWRAP_VERSION,
+                sandbox_policy,
+                sandbox_policy_cwd,
+                options,
+                command_cwd,
+                None,
+                command,
+            )?;
+            let argv: Vec<_> = argv.iter().map(|arg| arg.to_string_lossy()).collect();
+            let _ = writeln!(dump, "{}", argv.join(" "));
+            dump.push_str("bwrap enforces the policy; no seccomp filter or Landlock ruleset is installed by the launcher\n");
+        }
+        SelectedBackend::None => {
+            dump.push_str("# no sandbox\n");
+            dump.push_str("--backend none installs no seccomp filter or Landlock ruleset\n");
+        }
+    }
+
+    if options.restrict_ioctls {
+        let allowed: Vec<&str> = ALLOWED_IOCTLS.iter().map(|&(name, _)| name).collect();
+        let _ = writeln!(
+            dump,
+            "\n# seccomp ioctl filter (allows {})",
+            allowed.join(", ")
+        );
+        dump.push_str(&disassemble(&build_ioctl_filter()?));
+    }
+
+    if let Some((allowlist, deny_action)) = syscall_allowlist {
+        dump.push_str("\n# seccomp syscall allowlist\n");
+        dump.push_str(&disassemble(&build_syscall_allowlist_filter(
+            allowlist,
+            deny_action,
+        )?));
+    }
+
+    Ok(dump)
+}
+
+fn describe_landlock_rules(rules: &LandlockRules) -> String {
+    let mut description = String::from("handled: all filesystem rights");
+    if rules.restrict_network {
+        description.push_str(", TCP bind, TCP connect");
+    }
+    description.push('\n');
+    description.push_str("read-only: /\n");
+    description.push_str("read-write: /dev/null\n");
+    for root in &rules.writable_roots {
+        let _ = writeln!(description, "read-write: {}", root.display());
+    }
+    description
+}
+
+/// Offset of `nr` in `struct seccomp_data`.
+const SECCOMP_DATA_NR: u32 = 0;
+
+/// Disassembles a classic BPF program, one instruction per line, annotating
+/// `seccomp_data` fields, syscall names and seccomp return actions.
+fn disassemble(prog: &[sock_filter]) -> String {
+    let mut out = String::new();
+    // Whether the accumulator holds the syscall number, so comparisons
+    // against it can be annotated with the syscall name.
+    let mut acc_is_nr = false;
+
+    for (pc, insn) in prog.iter().enumerate() {
+        let k = insn.k;
+        let jt = pc + 1 + usize::from(insn.jt);
+        let jf = pc + 1 + usize::from(insn.jf);
+        let (text, comment) = match insn.code {
+            BPF_LD_W_ABS => {
+                acc_is_nr = k == SECCOMP_DATA_NR;
+                (format!("ld [{k}]"), seccomp_data_field(k))
+            }
+            BPF_JMP_JA => (format!("ja {:04}", pc + 1 + k as usize), None),
+            BPF_JMP_JEQ_K | BPF_JMP_JGT_K | BPF_JMP_JGE_K | BPF_JMP_JSET_K => {
+                let op = match insn.code {
+                    BPF_JMP_JEQ_K => "jeq",
+                    BPF_JMP_JGT_K => "jgt",
+                    BPF_JMP_JGE_K => "jge",
+                    _ => "jset",
+                };
+                let comment = if acc_is_nr {
+                    syscall_name(i64::from(k)).map(str::to_string)
+                } else {
+                    None
+                };
+                (format!("{op} #{k:#x}, {jt:04}, {jf:04}"), comment)
+            }
+            BPF_ALU_AND_K => {
+                acc_is_nr = false;
+                (format!("and #{k:#x}"), None)
+            }
+            BPF_RET_K => (format!("ret #{k:#010x}"), Some(seccomp_ret_action(k))),
+            code => (format!("unknown opcode {code:#06x}, k={k:#x}"), None),
+        };
+
+        match comment {
+            Some(comment) => {
+                let _ = writeln!(out, "{pc:04}: {text:<32} ; {comment}");
+            }
+            None => {
+                let _ = writeln!(out, "{pc:04}: {text}");
+            }
+        }
+    }
+    out
+}
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_ALU_AND_K: u16 = 0x54;
+const BPF_JMP_JA: u16 = 0x05;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JGT_K: u16 = 0x25;
+const BPF_JMP_JGE_K: u16 = 0x35;
+const BPF_JMP_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+
+fn seccomp_data_field(offset: u32) -> Option<String> {
+    match offset {
+        0 => Some("syscall number".to_string()),
+        4 => Some("arch".to_string()),
+        8 => Some("instruction pointer (low)".to_string()),
+        12 => Some("instruction pointer (high)".to_string()),
+        16..=63 => {
+            let arg = (offset - 16) / 8;
+            let half = if offset.is_multiple_of(8) {
+                "low"
+            } else {
+                "high"
+            };
+            Some(format!("args[{arg}] ({half})"))
+        }
+        _ => None,
+    }
+}
+
+fn seccomp_ret_action(k: u32) -> String {
+    let data = k & libc::SECCOMP_RET_DATA;
+    match k & libc::SECCOMP_RET_ACTION_FULL {
+        libc::SECCOMP_RET_KILL_PROCESS => "KILL_PROCESS".to_string(),
+        libc::SECCOMP_RET_KILL_THREAD => "KILL_THREAD".to_string(),
+        libc::SECCOMP_RET_TRAP => "TRAP".to_string(),
+        libc::SECCOMP_RET_ERRNO => format!("ERRNO({data})"),
+        libc::SECCOMP_RET_TRACE => format!("TRACE({data})"),
+        libc::SECCOMP_RET_LOG => "LOG".to_string(),
+        libc::SECCOMP_RET_ALLOW => "ALLOW".to_string(),
+        action => format!("unknown action {action:#x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::landlock::DEFAULT_MAX_ROOTS;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn insn(code: u16, jt: u8, jf: u8, k: u32) -> sock_filter {
+        sock_filter { code, jt, jf, k }
+    }
+
+    #[test]
+    fn disassembles_a_seccomp_program() {
+        let prog = [
+            insn(BPF_LD_W_ABS, 0, 0, 4),
+            insn(BPF_JMP_JEQ_K, 1, 0, 0xc000_003e),
+            insn(BPF_RET_K, 0, 0, libc::SECCOMP_RET_KILL_PROCESS),
+            insn(BPF_LD_W_ABS, 0, 0, 0),
+            insn(BPF_JMP_JEQ_K, 0, 1, libc::SYS_connect as u32),
+            insn(
+                BPF_RET_K,
+                0,
+                0,
+                libc::SECCOMP_RET_ERRNO | libc::EPERM as u32,
+            ),
+            insn(BPF_LD_W_ABS, 0, 0, 16),
+            insn(BPF_RET_K, 0, 0, libc::SECCOMP_RET_ALLOW),
+        ];
+
+        let connect = libc::SYS_connect;
+        let expected = [
+            "0000: ld [4]                           ; arch".to_string(),
+            "0001: jeq #0xc000003e, 0003, 0002".to_string(),
+            "0002: ret #0x80000000                  ; KILL_PROCESS".to_string(),
+            "0003: ld [0]                           ; syscall number".to_string(),
+            format!(
+                "{:<38} ; connect",
+                format!("0004: jeq #{connect:#x}, 0005, 0006")
+            ),
+            "0005: ret #0x00050001                  ; ERRNO(1)".to_string(),
+            "0006: ld [16]                          ; args[0] (low)".to_string(),
+            "0007: ret #0x7fff0000                  ; ALLOW".to_string(),
+        ];
+        assert_eq!(
+            disassemble(&prog).lines().collect::<Vec<_>>(),
+            expected.iter().map(String::as_str).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn landlock_rules_list_rights_per_path() {
+        let rules = LandlockRules {
+            writable_roots: vec![PathBuf::from("/work"), PathBuf::from("/tmp")],
+            restrict_network: true,
+        };
+
+        assert_eq!(
+            describe_landlock_rules(&rules),
+            "handled: all filesystem rights, TCP bind, TCP connect\n\
+             read-only: /\n\
+             read-write: /dev/null\n\
+             read-write: /work\n\
+             read-write: /tmp\n"
+        );
+    }
+
+    #[test]
+    fn no_sandbox_dump_says_so() {
+        let dump = render_filter_dump(
+            &SandboxPolicy::ReadOnly,
+            &SandboxOptions {
+                network: NetworkMode::Blocked,
+                private_tmp: None,
+                devices: Vec::new(),
+                minimal_root: None,
+                read_deny: Vec::new(),
+                max_roots: DEFAULT_MAX_ROOTS,
+                restrict_ioctls: false,
+                seccomp_tsync: false,
+            },
+            Path::new("/work"),
+            &SelectedBackend::None,
+            None,
+            Path::new("/work"),
+            &[OsString::from("true")],
+            5,
+        )
+        .expect("render dump");
+
+        assert_eq!(
+            dump,
+            "# no sandbox\n--backend none installs no seccomp filter or Landlock ruleset\n"
+        );
+    }
+
+    #[test]
+    fn ioctl_filter_is_dumped_with_its_requests() {
+        let mut options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+        options.restrict_ioctls = true;
+        let dump = render_filter_dump(
+            &SandboxPolicy::ReadOnly,
+            &options,
+            Path::new("/work"),
+            &SelectedBackend::None,
+            None,
+            Path::new("/work"),
+            &[OsString::from("true")],
+            5,
+        )
+        .expect("render dump");
+
+        assert!(
+            dump.contains("\n# seccomp ioctl filter (allows TCGETS, TCSETS, "),
+            "{dump}"
+        );
+    }
+}
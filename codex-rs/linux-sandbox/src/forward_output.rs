@@ -0,0 +1,289 @@
+//! `--forward-output`: in `--wait` mode, the command's stdout and stderr are
+//! pipes the launcher drains and copies to its own stdout and stderr, a line
+//! at a time. Each stream keeps its order, and a line is never split by one
+//! from the other stream when both end up in the same pipe, which they may
+//! be when the command writes to the inherited descriptors directly.
+//!
+//! A line is forwarded as soon as its newline arrives. An unfinished line,
+//! such as a progress bar redrawn with `\r`, is forwarded once it has waited
+//! [`PARTIAL_LINE_DELAY`] for the rest, or once it reaches
+//! [`MAX_PENDING_BYTES`].
+
+use std::io::ErrorKind;
+use std::io::Write;
+use std::os::fd::AsRawFd as _;
+use std::os::fd::FromRawFd as _;
+use std::os::fd::OwnedFd;
+use std::time::Duration;
+use std::time::Instant;
+
+use tracing::warn;
+
+/// How long an unfinished line waits for the rest of it.
+pub(crate) const PARTIAL_LINE_DELAY: Duration = Duration::from_millis(50);
+
+/// Most bytes held back waiting for a newline.
+pub(crate) const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+/// The pipes replacing the command's stdout and stderr, created before the
+/// fork and split between its two sides.
+#[derive(Debug)]
+pub(crate) struct OutputPipes {
+    stdout: (OwnedFd, OwnedFd),
+    stderr: (OwnedFd, OwnedFd),
+}
+
+impl OutputPipes {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            stdout: pipe()?,
+            stderr: pipe()?,
+        })
+    }
+
+    /// Child side of the fork: makes the write ends its stdout and stderr.
+    /// The originals are close-on-exec, so once the command has exec'd, the
+    /// launcher sees end of file when the command and everything it started
+    /// have closed them.
+    pub(crate) fn attach(self) -> std::io::Result<()> {
+        for (write, target) in [
+            (&self.stdout.1, libc::STDOUT_FILENO),
+            (&self.stderr.1, libc::STDERR_FILENO),
+        ] {
+            if unsafe { libc::dup2(write.as_raw_fd(), target) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Launcher side of the fork: forwards both streams to the launcher's
+    /// own stdout and stderr until both reach end of file.
+    pub(crate) fn forward(self) -> std::io::Result<()> {
+        let Self {
+            stdout: (stdout_read, stdout_write),
+            stderr: (stderr_read, stderr_write),
+        } = self;
+        drop(stdout_write);
+        drop(stderr_write);
+        let mut stdout = std::io::stdout();
+        let mut stderr = std::io::stderr();
+        forward_streams([
+            Stream::new(stdout_read, &mut stdout)?,
+            Stream::new(stderr_read, &mut stderr)?,
+        ])
+    }
+}
+
+fn pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/// One stream being forwarded: the read end of its pipe, or `None` once it
+/// is closed, and what was read but not written yet.
+struct Stream<'a> {
+    read: Option<OwnedFd>,
+    output: &'a mut dyn Write,
+    pending: Vec<u8>,
+    /// When the unfinished line in `pending` has to be written.
+    deadline: Option<Instant>,
+}
+
+impl<'a> Stream<'a> {
+    fn new(read: OwnedFd, output: &'a mut dyn Write) -> std::io::Result<Self> {
+        let flags = unsafe { libc::fcntl(read.as_raw_fd(), libc::F_GETFL) };
+        if flags < 0
+            || unsafe { libc::fcntl(read.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            read: Some(read),
+            output,
+            pending: Vec::new(),
+            deadline: None,
+        })
+    }
+
+    /// Reads everything available, then writes the complete lines, or all
+    /// of it at end of file or once too much is pending.
+    fn drain(&mut self) -> std::io::Result<()> {
+        let Some(read) = &self.read else {
+            return Ok(());
+        };
+        let mut buf = [0u8; 8192];
+        let at_eof = loop {
+            let n = unsafe { libc::read(read.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if n > 0 {
+                self.pending.extend_from_slice(&buf[..n as usize]);
+                continue;
+            }
+            if n == 0 {
+                break true;
+            }
+            let err = std::io::Error::last_os_error();
+            match err.kind() {
+                ErrorKind::Interrupted => {}
+                ErrorKind::WouldBlock => break false,
+                _ => return Err(err),
+            }
+        };
+        if at_eof || self.pending.len() >= MAX_PENDING_BYTES {
+            self.write_pending(self.pending.len());
+        } else if let Some(last_newline) = self.pending.iter().rposition(|&byte| byte == b'\n') {
+            self.write_pending(last_newline + 1);
+        }
+        if at_eof {
+            self.read = None;
+        }
+        if !self.pending.is_empty() && self.deadline.is_none() {
+            self.deadline = Some(Instant::now() + PARTIAL_LINE_DELAY);
+        }
+        Ok(())
+    }
+
+    /// Writes the first `len` pending bytes. If the output is gone, the
+    /// stream is closed so the command gets `EPIPE` as it would writing to
+    /// the output itself.
+    fn write_pending(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let result = self
+            .output
+            .write_all(&self.pending[..len])
+            .and_then(|()| self.output.flush());
+        self.pending.drain(..len);
+        if self.pending.is_empty() {
+            self.deadline = None;
+        }
+        if let Err(e) = result {
+            if e.kind() != ErrorKind::BrokenPipe {
+                warn!("could not forward the command's output: {e}");
+            }
+            self.read = None;
+            self.pending.clear();
+            self.deadline = None;
+        }
+    }
+}
+
+fn forward_streams(mut streams: [Stream<'_>; 2]) -> std::io::Result<()> {
+    while streams.iter().any(|stream| stream.read.is_some()) {
+        let mut fds = streams.each_ref().map(|stream| libc::pollfd {
+            // Negative fds are ignored by poll.
+            fd: stream.read.as_ref().map_or(-1, |read| read.as_raw_fd()),
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        let now = Instant::now();
+        let timeout = streams
+            .iter()
+            .filter_map(|stream| stream.deadline)
+            .min()
+            .map_or(-1, |deadline| {
+                deadline
+                    .saturating_duration_since(now)
+                    .as_micros()
+                    .div_ceil(1000) as libc::c_int
+            });
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        for (stream, fd) in streams.iter_mut().zip(&fds) {
+            if fd.revents != 0 {
+                stream.drain()?;
+            }
+            if stream
+                .deadline
+                .is_some_and(|deadline| deadline <= Instant::now())
+            {
+                stream.write_pending(stream.pending.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Records each write separately, so tests can see how output was
+    /// split.
+    #[derive(Default)]
+    struct Writes(Vec<Vec<u8>>);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn write_and_close(write: OwnedFd, chunks: &'static [&'static [u8]]) {
+        let mut write = std::fs::File::from(write);
+        for chunk in chunks {
+            write.write_all(chunk).expect("write chunk");
+        }
+    }
+
+    #[test]
+    fn everything_is_written_by_end_of_file() {
+        let (stdout_read, stdout_write) = pipe().expect("stdout pipe");
+        let (stderr_read, stderr_write) = pipe().expect("stderr pipe");
+        write_and_close(stdout_write, &[b"one\ntw", b"o\nthree"]);
+        write_and_close(stderr_write, &[b"warning\n"]);
+
+        let mut stdout = Writes::default();
+        let mut stderr = Writes::default();
+        forward_streams([
+            Stream::new(stdout_read, &mut stdout).expect("stdout stream"),
+            Stream::new(stderr_read, &mut stderr).expect("stderr stream"),
+        ])
+        .expect("forward");
+
+        assert_eq!(stdout.0.concat(), b"one\ntwo\nthree");
+        assert_eq!(stderr.0, vec![b"warning\n".to_vec()]);
+    }
+
+    #[test]
+    fn an_unfinished_line_is_written_once_the_stream_is_quiet() {
+        let (stdout_read, stdout_write) = pipe().expect("stdout pipe");
+        let (stderr_read, stderr_write) = pipe().expect("stderr pipe");
+        drop(stderr_write);
+        let writer = std::thread::spawn(move || {
+            let mut write = std::fs::File::from(stdout_write);
+            write.write_all(b"50%\r").expect("write progress");
+            std::thread::sleep(PARTIAL_LINE_DELAY * 10);
+            write.write_all(b"100%\n").expect("write progress");
+        });
+
+        let started = Instant::now();
+        let mut stdout = Writes::default();
+        let mut stderr = Writes::default();
+        forward_streams([
+            Stream::new(stdout_read, &mut stdout).expect("stdout stream"),
+            Stream::new(stderr_read, &mut stderr).expect("stderr stream"),
+        ])
+        .expect("forward");
+        writer.join().expect("writer thread");
+
+        assert!(started.elapsed() >= PARTIAL_LINE_DELAY * 10);
+        assert_eq!(stdout.0, vec![b"50%\r".to_vec(), b"100%\n".to_vec()]);
+    }
+}
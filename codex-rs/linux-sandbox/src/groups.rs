@@ -0,0 +1,73 @@
+//! `--groups`: the supplementary groups the command runs with.
+//!
+//! Only the Landlock backend and `--backend none` support it. bwrap runs the
+//! command in a user namespace, where supplementary groups cannot be changed
+//! without privileges bwrap drops.
+
+use std::collections::BTreeSet;
+use std::io::Error;
+use std::io::ErrorKind;
+
+/// The calling process's supplementary groups.
+pub(crate) fn current_groups() -> std::io::Result<BTreeSet<libc::gid_t>> {
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count < 0 {
+        return Err(Error::last_os_error());
+    }
+    let mut gids = vec![0; count as usize];
+    let count = unsafe { libc::getgroups(count, gids.as_mut_ptr()) };
+    if count < 0 {
+        return Err(Error::last_os_error());
+    }
+    gids.truncate(count as usize);
+    Ok(gids.into_iter().collect())
+}
+
+/// Replaces the supplementary groups of the calling process with `gids`.
+///
+/// Succeeds without privileges when `gids` already is the current set in
+/// some order. Otherwise this needs `CAP_SETGID`, and without it fails with
+/// an error saying so rather than a bare `EPERM`.
+pub(crate) fn set_supplementary_groups(gids: &[libc::gid_t]) -> std::io::Result<()> {
+    let requested: BTreeSet<libc::gid_t> = gids.iter().copied().collect();
+    if current_groups()? == requested {
+        return Ok(());
+    }
+
+    let max = unsafe { libc::sysconf(libc::_SC_NGROUPS_MAX) };
+    if max >= 0 && requested.len() > max as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} groups exceed the limit of {max}", requested.len()),
+        ));
+    }
+    let requested: Vec<libc::gid_t> = requested.into_iter().collect();
+    if unsafe { libc::setgroups(requested.len(), requested.as_ptr()) } != 0 {
+        let err = Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EPERM) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "changing supplementary groups needs CAP_SETGID; without it, only the \
+                 launcher's current groups can be requested",
+            ));
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn current_groups_in_any_order_need_no_privileges() {
+        let current = current_groups().expect("current groups");
+        let mut reversed: Vec<libc::gid_t> = current.iter().copied().collect();
+        reversed.reverse();
+
+        set_supplementary_groups(&reversed).expect("set the current groups");
+        assert_eq!(current_groups().expect("current groups"), current);
+    }
+}
@@ -0,0 +1,120 @@
+//! `--restrict-ioctls`: a seccomp filter that only lets `ioctl` through for a
+//! short list of terminal and stream requests, and fails every other request
+//! with `EPERM`.
+//!
+//! The filter matches the request number alone. It cannot see which driver
+//! the fd belongs to, and request numbers are only unique by convention, so a
+//! device-specific request that happens to share a number with an allowed one
+//! gets through. Treat it as a way to cut down the attack surface of device
+//! drivers, not as a boundary.
+
+use std::collections::BTreeMap;
+
+use codex_core::error::SandboxErr;
+use seccompiler::BpfProgram;
+use seccompiler::SeccompAction;
+use seccompiler::SeccompCmpArgLen;
+use seccompiler::SeccompCmpOp;
+use seccompiler::SeccompCondition;
+use seccompiler::SeccompFilter;
+use seccompiler::SeccompRule;
+
+use crate::landlock::apply_seccomp_program;
+use crate::landlock::seccomp_target_arch;
+
+/// Requests programs make on terminals, pipes and plain files. `TIOCSTI`,
+/// which injects input into a terminal, and `TIOCSCTTY` are left out on
+/// purpose.
+pub(crate) const ALLOWED_IOCTLS: &[(&str, libc::Ioctl)] = &[
+    ("TCGETS", libc::TCGETS),
+    ("TCSETS", libc::TCSETS),
+    ("TCSETSW", libc::TCSETSW),
+    ("TCSETSF", libc::TCSETSF),
+    ("TIOCGWINSZ", libc::TIOCGWINSZ),
+    ("TIOCSWINSZ", libc::TIOCSWINSZ),
+    ("TIOCGPGRP", libc::TIOCGPGRP),
+    ("TIOCSPGRP", libc::TIOCSPGRP),
+    ("TIOCGSID", libc::TIOCGSID),
+    ("FIONREAD", libc::FIONREAD),
+    ("FIONBIO", libc::FIONBIO),
+    ("FIOCLEX", libc::FIOCLEX),
+    ("FIONCLEX", libc::FIONCLEX),
+];
+
+/// Assembles the filter installed by [`install_ioctl_filter_on_current_thread`].
+pub(crate) fn build_ioctl_filter() -> Result<BpfProgram, SandboxErr> {
+    // The kernel truncates the request to an `unsigned int`, so only the low
+    // 32 bits are compared. Conditions within a rule must all match, so this
+    // denies any request not in the list.
+    let unlisted_request = SeccompRule::new(
+        ALLOWED_IOCTLS
+            .iter()
+            .map(|&(_, request)| {
+                SeccompCondition::new(
+                    1, // second argument (request)
+                    SeccompCmpArgLen::Dword,
+                    SeccompCmpOp::Ne,
+                    u64::from(request as u32),
+                )
+            })
+            .collect::<Result<_, _>>()?,
+    )?;
+    let rules = BTreeMap::from([(libc::SYS_ioctl, vec![unlisted_request])]);
+
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,                     // default – allow
+        SeccompAction::Errno(libc::EPERM as u32), // when rule matches – return EPERM
+        seccomp_target_arch(),
+    )?;
+
+    Ok(filter.try_into()?)
+}
+
+/// Installs the ioctl filter on the current thread, or on all threads with
+/// `all_threads`.
+pub(crate) fn install_ioctl_filter_on_current_thread(all_threads: bool) -> Result<(), SandboxErr> {
+    let prog = build_ioctl_filter()?;
+    apply_seccomp_program(&prog, all_threads)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn only_listed_requests_get_through() {
+        let errnos = std::thread::spawn(|| {
+            let mut fds = [0; 2];
+            assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+            install_ioctl_filter_on_current_thread(false).expect("install ioctl filter");
+
+            // errno of `ioctl(fd, request, &arg)`, or `None` if it succeeded.
+            let ioctl_errno = |request: libc::Ioctl| {
+                let mut arg: libc::c_int = 0;
+                if unsafe { libc::ioctl(fds[0], request, &mut arg) } < 0 {
+                    return std::io::Error::last_os_error().raw_os_error();
+                }
+                None
+            };
+            let errnos = [
+                ioctl_errno(libc::FIONREAD),
+                // Allowed, and fails as usual on a pipe.
+                ioctl_errno(libc::TCGETS),
+                ioctl_errno(libc::TIOCSTI),
+                ioctl_errno(0x1234),
+            ];
+            for fd in fds {
+                unsafe { libc::close(fd) };
+            }
+            errnos
+        })
+        .join()
+        .expect("ioctl thread panicked");
+
+        let eperm = Some(libc::EPERM);
+        assert_eq!(errnos, [None, Some(libc::ENOTTY), eperm, eperm]);
+    }
+}
@@ -0,0 +1,48 @@
+//! Helpers for the small hand-written JSON documents the launcher emits
+//! (preflight reports, run manifests).
+
+use std::fmt::Write as _;
+
+/// Quotes `value` as a JSON string.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A JSON array of the quoted `values`.
+pub(crate) fn json_string_array<S: AsRef<str>>(values: impl IntoIterator<Item = S>) -> String {
+    let values: Vec<String> = values
+        .into_iter()
+        .map(|value| json_string(value.as_ref()))
+        .collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("a \"b\" \\ c\n\t"), r#""a \"b\" \\ c\n\u0009""#);
+    }
+
+    #[test]
+    fn string_arrays() {
+        assert_eq!(json_string_array(Vec::<String>::new()), "[]");
+        assert_eq!(json_string_array(["a", "\"b\""]), r#"["a","\"b\""]"#);
+    }
+}
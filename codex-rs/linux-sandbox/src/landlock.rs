@@ -1,6 +1,13 @@
 use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
 
 use codex_core::error::CodexErr;
 use codex_core::error::Result;
@@ -10,6 +17,7 @@ use codex_core::protocol::SandboxPolicy;
 use landlock::ABI;
 use landlock::Access;
 use landlock::AccessFs;
+use landlock::AccessNet;
 use landlock::CompatLevel;
 use landlock::Compatible;
 use landlock::Ruleset;
@@ -24,67 +32,467 @@ use seccompiler::SeccompFilter;
 use seccompiler::SeccompRule;
 use seccompiler::TargetArch;
 use seccompiler::apply_filter;
+use seccompiler::apply_filter_all_threads;
 
-/// Apply sandbox policies inside this thread so only the child inherits
-/// them, not the entire CLI process.
-pub(crate) fn apply_sandbox_policy_to_current_thread(
+use crate::enforcement::Degradation;
+use crate::enforcement::EnforcementMode;
+use crate::enforcement::LandlockEnforcement;
+use crate::enforcement::NetworkEnforcement;
+use crate::enforcement::SandboxEnforcement;
+use crate::netns::enter_loopback_only_network_namespace;
+use crate::writable_roots::writable_roots_with_cwd;
+
+/// First Landlock ABI that can restrict TCP bind/connect.
+pub(crate) const LANDLOCK_NETWORK_ABI: i32 = 4;
+
+/// First Landlock ABI that handles every filesystem right the ruleset asks
+/// for (`LANDLOCK_ACCESS_FS_IOCTL_DEV`); older kernels leave some of them
+/// unrestricted.
+pub(crate) const LANDLOCK_FULL_FILESYSTEM_ABI: i32 = 5;
+
+/// How much network access the command gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum NetworkMode {
+    Full,
+    /// Only AF_UNIX sockets.
+    Blocked,
+    /// AF_UNIX sockets plus IP over the loopback interface of a private
+    /// network namespace, which has no route anywhere else.
+    LoopbackOnly,
+    /// AF_UNIX sockets plus UDP sockets to any destination, e.g. for DNS.
+    /// TCP stays blocked. Seccomp cannot see ports, so restricting UDP
+    /// destinations is left to the network setup around the sandbox.
+    Udp,
+}
+
+impl NetworkMode {
+    pub(crate) fn for_policy(
+        sandbox_policy: &SandboxPolicy,
+        allow_loopback: bool,
+        allow_udp: bool,
+    ) -> Self {
+        if sandbox_policy.has_full_network_access() {
+            NetworkMode::Full
+        } else if allow_loopback {
+            NetworkMode::LoopbackOnly
+        } else if allow_udp {
+            NetworkMode::Udp
+        } else {
+            NetworkMode::Blocked
+        }
+    }
+
+    /// Name used in reports.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            NetworkMode::Full => "full",
+            NetworkMode::Blocked => "blocked",
+            NetworkMode::LoopbackOnly => "loopback-only",
+            NetworkMode::Udp => "udp",
+        }
+    }
+}
+
+/// Settings beyond the policy itself that shape the installed sandbox.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SandboxOptions {
+    pub(crate) network: NetworkMode,
+    /// Writable directory that replaces `/tmp` for `--private-tmp`.
+    pub(crate) private_tmp: Option<PathBuf>,
+    /// Device nodes the command may use in addition to `/dev/null`.
+    pub(crate) devices: Vec<PathBuf>,
+    /// bwrap only: mount just the system directories plus these host paths
+    /// instead of all of `/`, for `--minimal-root` and `--keep-mount`.
+    pub(crate) minimal_root: Option<Vec<PathBuf>>,
+    /// bwrap only: paths hidden from the command even though the policy lets
+    /// it read them, for `--read-deny`.
+    pub(crate) read_deny: Vec<PathBuf>,
+    /// Most writable paths the Landlock ruleset may hold, for `--max-roots`.
+    pub(crate) max_roots: usize,
+    /// Install the ioctl filter right before the exec, with either backend,
+    /// for `--restrict-ioctls`.
+    pub(crate) restrict_ioctls: bool,
+    /// Load every seccomp filter with `SECCOMP_FILTER_FLAG_TSYNC`, so it
+    /// covers all threads of the process and not just the calling one, for
+    /// `--seccomp-tsync`. Off for embedders, whose other threads must stay
+    /// unrestricted.
+    pub(crate) seccomp_tsync: bool,
+}
+
+/// Default for [`SandboxOptions::max_roots`]: far more than any normal
+/// policy needs, but few enough to fail clearly before the kernel does.
+pub(crate) const DEFAULT_MAX_ROOTS: usize = 64;
+
+impl SandboxOptions {
+    pub(crate) fn for_policy(sandbox_policy: &SandboxPolicy) -> Self {
+        Self {
+            network: NetworkMode::for_policy(sandbox_policy, false, false),
+            private_tmp: None,
+            devices: Vec::new(),
+            minimal_root: None,
+            read_deny: Vec::new(),
+            max_roots: DEFAULT_MAX_ROOTS,
+            restrict_ioctls: false,
+            seccomp_tsync: false,
+        }
+    }
+}
+
+/// Installs the whole sandbox for `sandbox_policy` on the current thread, so
+/// only a child exec'd from it inherits the restrictions, not the entire CLI
+/// process. Network access is blocked entirely when the policy forbids it.
+///
+/// The steps always run in the same order: `PR_SET_NO_NEW_PRIVS`, then the
+/// seccomp network filter, then Landlock. The returned report says what was
+/// actually enforced. None of the steps can be undone, so when one fails
+/// after another was installed the error is
+/// [`SandboxErr::PartiallyInstalled`] and the thread must not go on to run
+/// the command.
+pub fn install_sandbox_on_current_thread(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> Result<SandboxEnforcement> {
+    install_sandbox_with_options_on_current_thread(
+        sandbox_policy,
+        &SandboxOptions::for_policy(sandbox_policy),
+        cwd,
+        EnforcementMode::BestEffort,
+    )
+}
+
+/// Like [`install_sandbox_on_current_thread`], with `options` instead of the
+/// defaults for the policy. In [`EnforcementMode::Strict`], fails before
+/// installing anything if the kernel cannot enforce every restriction.
+pub(crate) fn install_sandbox_with_options_on_current_thread(
     sandbox_policy: &SandboxPolicy,
+    options: &SandboxOptions,
     cwd: &Path,
-) -> Result<()> {
-    if !sandbox_policy.has_full_network_access() {
-        install_network_seccomp_filter_on_current_thread()?;
+    mode: EnforcementMode,
+) -> Result<SandboxEnforcement> {
+    install_sandbox_steps(
+        sandbox_policy,
+        options,
+        cwd,
+        mode,
+        install_landlock_rules_on_current_thread,
+    )
+}
+
+/// The body of [`install_sandbox_with_options_on_current_thread`], with the
+/// Landlock step passed in so tests can make it fail.
+fn install_sandbox_steps(
+    sandbox_policy: &SandboxPolicy,
+    options: &SandboxOptions,
+    cwd: &Path,
+    mode: EnforcementMode,
+    install_landlock: impl FnOnce(&LandlockRules) -> Result<LandlockEnforcement>,
+) -> Result<SandboxEnforcement> {
+    let network = options.network;
+    let (landlock_rules, mut enforcement) =
+        plan_landlock_rules(sandbox_policy, options, cwd, landlock_abi(), mode)?;
+    if network == NetworkMode::Full && landlock_rules.is_none() {
+        return Ok(enforcement);
     }
 
-    if !sandbox_policy.has_full_disk_write_access() {
-        let writable_roots = sandbox_policy
-            .get_writable_roots_with_cwd(cwd)
-            .into_iter()
-            .map(|writable_root| writable_root.root)
-            .collect();
-        install_filesystem_landlock_rules_on_current_thread(writable_roots)?;
+    set_no_new_privs()?;
+    enforcement.no_new_privs = true;
+
+    // `PR_SET_NO_NEW_PRIVS` restricts nothing the command may do by itself,
+    // so a failure right after it is reported as is.
+    let mut installed = InstalledSteps::default();
+
+    match network {
+        NetworkMode::Full => {}
+        NetworkMode::Blocked | NetworkMode::Udp => {
+            installed.run("the seccomp network filter", || {
+                Ok(install_network_seccomp_filter_on_current_thread(
+                    network,
+                    options.seccomp_tsync,
+                )?)
+            })?;
+            enforcement.network = if network == NetworkMode::Udp {
+                NetworkEnforcement::SeccompWithUdp
+            } else {
+                NetworkEnforcement::Seccomp
+            };
+        }
+        NetworkMode::LoopbackOnly => {
+            // Needs /proc and the namespace syscalls, so it must come before
+            // both the seccomp filter and Landlock.
+            installed.run(
+                "the loopback-only network namespace",
+                enter_loopback_only_network_namespace,
+            )?;
+            installed.run("the seccomp network filter", || {
+                Ok(install_network_seccomp_filter_on_current_thread(
+                    network,
+                    options.seccomp_tsync,
+                )?)
+            })?;
+            enforcement.network = NetworkEnforcement::LoopbackNamespace;
+        }
+    }
+
+    if let Some(rules) = landlock_rules {
+        enforcement.landlock = Some(installed.run("Landlock", || install_landlock(&rules))?);
     }
 
     // TODO(ragona): Add appropriate restrictions if
     // `sandbox_policy.has_full_disk_read_access()` is `false`.
 
+    Ok(enforcement)
+}
+
+/// Restrictions of [`install_sandbox_steps`] that are already in effect.
+#[derive(Debug, Default)]
+struct InstalledSteps(Vec<&'static str>);
+
+impl InstalledSteps {
+    /// Runs `install` and records `step` as installed if it succeeds. When it
+    /// fails after an earlier step took effect, the error names those steps.
+    fn run<T>(&mut self, step: &'static str, install: impl FnOnce() -> Result<T>) -> Result<T> {
+        match install() {
+            Ok(value) => {
+                self.0.push(step);
+                Ok(value)
+            }
+            Err(err) if self.0.is_empty() => Err(err),
+            Err(err) => Err(CodexErr::Sandbox(SandboxErr::PartiallyInstalled(format!(
+                "installing {step} failed after {} took effect: {err}",
+                self.0.join(" and ")
+            )))),
+        }
+    }
+}
+
+/// The Landlock rules for `abi`, along with the report of what they leave
+/// unenforced, which [`EnforcementMode::Strict`] turns into an error.
+fn plan_landlock_rules(
+    sandbox_policy: &SandboxPolicy,
+    options: &SandboxOptions,
+    cwd: &Path,
+    abi: i32,
+    mode: EnforcementMode,
+) -> Result<(Option<LandlockRules>, SandboxEnforcement)> {
+    let mut enforcement = SandboxEnforcement::default();
+    let landlock_rules =
+        LandlockRules::for_policy(sandbox_policy, options, cwd, abi, &mut enforcement)?;
+    enforcement.ensure(mode)?;
+    Ok((landlock_rules, enforcement))
+}
+
+fn set_no_new_privs() -> Result<()> {
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
     Ok(())
 }
 
-/// Installs Landlock file-system rules on the current thread allowing read
-/// access to the entire file-system while restricting write access to
-/// `/dev/null` and the provided list of `writable_roots`.
+/// Returns the Landlock ABI version supported by the running kernel, or 0
+/// when Landlock is unavailable.
+pub(crate) fn landlock_abi() -> i32 {
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+
+    let abi = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0_usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    i32::try_from(abi).unwrap_or_default().max(0)
+}
+
+/// The Landlock ruleset for a policy: read access to the entire file-system,
+/// write access to `/dev/null` and the writable roots, and optionally no TCP
+/// bind/connect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LandlockRules {
+    pub(crate) writable_roots: Vec<PathBuf>,
+    pub(crate) restrict_network: bool,
+}
+
+impl LandlockRules {
+    /// Returns `None` when the policy needs no Landlock rules at all, and an
+    /// error when a relative writable root resolves to a protected directory
+    /// (see [`crate::writable_roots`]) or there are more writable paths than
+    /// [`SandboxOptions::max_roots`]. Network
+    /// rules are only included when the network mode is
+    /// [`NetworkMode::Blocked`] and `abi` supports them; see
+    /// [`landlock_network_supported`]. With a private tmp, `/tmp` itself is
+    /// never writable and the private directory is instead.
+    pub(crate) fn for_policy(
+        sandbox_policy: &SandboxPolicy,
+        options: &SandboxOptions,
+        cwd: &Path,
+        abi: i32,
+        enforcement: &mut SandboxEnforcement,
+    ) -> Result<Option<Self>> {
+        if sandbox_policy.has_full_disk_write_access() {
+            return Ok(None);
+        }
+
+        let mut writable_roots: Vec<PathBuf> = writable_roots_with_cwd(sandbox_policy, cwd)?
+            .into_iter()
+            .map(|writable_root| writable_root.root)
+            .collect();
+        if let Some(private_tmp) = &options.private_tmp {
+            writable_roots.retain(|root| root != Path::new("/tmp"));
+            if !writable_roots.contains(private_tmp) {
+                writable_roots.push(private_tmp.clone());
+            }
+        }
+        writable_roots.extend(options.devices.iter().cloned());
+        if writable_roots.len() > options.max_roots {
+            return Err(CodexErr::Sandbox(SandboxErr::TooManyRoots(format!(
+                "{} writable paths exceed the limit of {}; drop duplicate roots and roots nested \
+                 in other roots, or raise --max-roots",
+                writable_roots.len(),
+                options.max_roots
+            ))));
+        }
+        // Without Landlock at all, the filesystem ruleset fails on its own.
+        if abi > 0 && abi < LANDLOCK_FULL_FILESYSTEM_ABI {
+            enforcement
+                .degradations
+                .push(Degradation::LandlockFilesystemRightsUnsupported { abi });
+        }
+        // Landlock network rules only cover TCP, so they also apply when UDP
+        // is allowed.
+        let restrict_network = matches!(options.network, NetworkMode::Blocked | NetworkMode::Udp)
+            && landlock_network_supported(abi, enforcement);
+        Ok(Some(Self {
+            writable_roots,
+            restrict_network,
+        }))
+    }
+}
+
+/// Kernels that support filesystem but not network Landlock reject a ruleset
+/// that handles network access outright, so network rules are only added when
+/// `abi` supports them. Skipping them is recorded in `enforcement`; seccomp
+/// still blocks network access either way.
+fn landlock_network_supported(abi: i32, enforcement: &mut SandboxEnforcement) -> bool {
+    if abi >= LANDLOCK_NETWORK_ABI {
+        return true;
+    }
+
+    // Without Landlock at all, the filesystem ruleset fails on its own.
+    if abi > 0 {
+        enforcement
+            .degradations
+            .push(Degradation::LandlockNetworkUnsupported { abi });
+    }
+    false
+}
+
+/// Installs `rules` on the current thread.
 ///
 /// # Errors
 /// Returns [`CodexErr::Sandbox`] variants when the ruleset fails to apply.
-fn install_filesystem_landlock_rules_on_current_thread(writable_roots: Vec<PathBuf>) -> Result<()> {
+fn install_landlock_rules_on_current_thread(rules: &LandlockRules) -> Result<LandlockEnforcement> {
     let abi = ABI::V5;
     let access_rw = AccessFs::from_all(abi);
     let access_ro = AccessFs::from_read(abi);
 
     let mut ruleset = Ruleset::default()
         .set_compatibility(CompatLevel::BestEffort)
-        .handle_access(access_rw)?
+        .handle_access(access_rw)?;
+    // No port rules are added, so every TCP bind and connect is denied.
+    if rules.restrict_network {
+        ruleset = ruleset.handle_access(AccessNet::from_all(ABI::V4))?;
+    }
+
+    let mut ruleset = ruleset
         .create()?
         .add_rules(landlock::path_beneath_rules(&["/"], access_ro))?
         .add_rules(landlock::path_beneath_rules(&["/dev/null"], access_rw))?
         .set_no_new_privs(true);
 
-    if !writable_roots.is_empty() {
-        ruleset = ruleset.add_rules(landlock::path_beneath_rules(&writable_roots, access_rw))?;
+    if !rules.writable_roots.is_empty() {
+        ruleset = ruleset.add_rules(landlock::path_beneath_rules(
+            &rules.writable_roots,
+            access_rw,
+        ))?;
     }
 
+    // The kernel always creates the ruleset fd with O_CLOEXEC, and
+    // `restrict_self` consumes the ruleset and closes the fd once the
+    // restriction is in place, so it can neither leak into the command nor
+    // outlive this call.
     let status = ruleset.restrict_self()?;
 
-    if status.ruleset == landlock::RulesetStatus::NotEnforced {
-        return Err(CodexErr::Sandbox(SandboxErr::LandlockRestrict));
+    match status.ruleset {
+        landlock::RulesetStatus::NotEnforced => {
+            Err(CodexErr::Sandbox(SandboxErr::LandlockRestrict))
+        }
+        landlock::RulesetStatus::PartiallyEnforced => Ok(LandlockEnforcement::Partial),
+        _ => Ok(LandlockEnforcement::Full),
     }
+}
 
+/// Installs a seccomp filter that blocks outbound network access except for
+/// AF_UNIX domain sockets (and, for [`NetworkMode::LoopbackOnly`], IP sockets
+/// in the private network namespace). With `all_threads`, the other threads
+/// of the process get it too.
+fn install_network_seccomp_filter_on_current_thread(
+    network: NetworkMode,
+    all_threads: bool,
+) -> std::result::Result<(), SandboxErr> {
+    let prog = cached_network_seccomp_filter(network)?;
+    apply_seccomp_program(&prog, all_threads)?;
     Ok(())
 }
 
-/// Installs a seccomp filter that blocks outbound network access except for
-/// AF_UNIX domain sockets.
-fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(), SandboxErr> {
+/// Network filters assembled so far in this process. The filter depends only
+/// on the network mode, so embedders that install the sandbox on many threads
+/// assemble each one once.
+static NETWORK_SECCOMP_FILTERS: LazyLock<Mutex<HashMap<NetworkMode, BpfProgram>>> =
+    LazyLock::new(Default::default);
+
+/// Returns the program built by [`build_network_seccomp_filter`], assembling
+/// it only on first use for `network`.
+fn cached_network_seccomp_filter(
+    network: NetworkMode,
+) -> std::result::Result<BpfProgram, SandboxErr> {
+    let mut filters = NETWORK_SECCOMP_FILTERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match filters.entry(network) {
+        Entry::Occupied(entry) => Ok(entry.get().clone()),
+        Entry::Vacant(entry) => Ok(entry.insert(build_network_seccomp_filter(network)?).clone()),
+    }
+}
+
+/// The average time to assemble the blocked-network filter and to fetch it
+/// from the cache, over `iterations` of each. Only for
+/// `benches/network_filter.rs`, which cannot reach the private functions.
+#[doc(hidden)]
+pub fn time_network_filter_cache(
+    iterations: u32,
+) -> std::result::Result<(Duration, Duration), SandboxErr> {
+    let network = NetworkMode::Blocked;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(build_network_seccomp_filter(network)?);
+    }
+    let assembled = start.elapsed() / iterations;
+
+    cached_network_seccomp_filter(network)?;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(cached_network_seccomp_filter(network)?);
+    }
+    let cached = start.elapsed() / iterations;
+    Ok((assembled, cached))
+}
+
+/// Assembles the filter installed by
+/// [`install_network_seccomp_filter_on_current_thread`].
+pub(crate) fn build_network_seccomp_filter(
+    network: NetworkMode,
+) -> std::result::Result<BpfProgram, SandboxErr> {
     // Build rule map.
     let mut rules: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
 
@@ -93,53 +501,710 @@ fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(),
         rules.insert(nr, vec![]); // empty rule vec = unconditional match
     };
 
-    deny_syscall(libc::SYS_connect);
-    deny_syscall(libc::SYS_accept);
-    deny_syscall(libc::SYS_accept4);
-    deny_syscall(libc::SYS_bind);
-    deny_syscall(libc::SYS_listen);
-    deny_syscall(libc::SYS_getpeername);
-    deny_syscall(libc::SYS_getsockname);
-    deny_syscall(libc::SYS_shutdown);
-    deny_syscall(libc::SYS_sendto);
-    deny_syscall(libc::SYS_sendmsg);
-    deny_syscall(libc::SYS_sendmmsg);
-    // NOTE: allowing recvfrom allows some tools like: `cargo clippy` to run
-    // with their socketpair + child processes for sub-proc management
-    // deny_syscall(libc::SYS_recvfrom);
-    deny_syscall(libc::SYS_recvmsg);
-    deny_syscall(libc::SYS_recvmmsg);
-    deny_syscall(libc::SYS_getsockopt);
-    deny_syscall(libc::SYS_setsockopt);
+    match network {
+        // With loopback-only access the network namespace is what keeps
+        // traffic local, so the socket calls themselves can be allowed.
+        NetworkMode::LoopbackOnly => {}
+        // UDP needs the calls that send, receive and address datagrams; only
+        // the TCP-only calls stay denied.
+        NetworkMode::Udp => {
+            deny_syscall(libc::SYS_accept);
+            deny_syscall(libc::SYS_accept4);
+            deny_syscall(libc::SYS_listen);
+        }
+        NetworkMode::Full | NetworkMode::Blocked => {
+            deny_syscall(libc::SYS_connect);
+            deny_syscall(libc::SYS_accept);
+            deny_syscall(libc::SYS_accept4);
+            deny_syscall(libc::SYS_bind);
+            deny_syscall(libc::SYS_listen);
+            deny_syscall(libc::SYS_getpeername);
+            deny_syscall(libc::SYS_getsockname);
+            deny_syscall(libc::SYS_shutdown);
+            deny_syscall(libc::SYS_sendto);
+            deny_syscall(libc::SYS_sendmsg);
+            deny_syscall(libc::SYS_sendmmsg);
+            // NOTE: allowing recvfrom allows some tools like: `cargo clippy` to run
+            // with their socketpair + child processes for sub-proc management
+            // deny_syscall(libc::SYS_recvfrom);
+            deny_syscall(libc::SYS_recvmsg);
+            deny_syscall(libc::SYS_recvmmsg);
+            deny_syscall(libc::SYS_getsockopt);
+            deny_syscall(libc::SYS_setsockopt);
+        }
+    }
     deny_syscall(libc::SYS_ptrace);
 
     // For `socket` we allow AF_UNIX (arg0 == AF_UNIX) and deny everything else.
-    let unix_only_rule = SeccompRule::new(vec![SeccompCondition::new(
-        0, // first argument (domain)
-        SeccompCmpArgLen::Dword,
-        SeccompCmpOp::Ne,
-        libc::AF_UNIX as u64,
-    )?])?;
+    // Loopback-only and UDP access also allow IPv4/IPv6 sockets. Conditions
+    // within a rule must all match, so this denies any domain not in the list.
+    let mut allowed_domains = vec![libc::AF_UNIX];
+    if matches!(network, NetworkMode::LoopbackOnly | NetworkMode::Udp) {
+        allowed_domains.extend([libc::AF_INET, libc::AF_INET6]);
+    }
+    let unix_only_rule = SeccompRule::new(
+        allowed_domains
+            .into_iter()
+            .map(|domain| {
+                SeccompCondition::new(
+                    0, // first argument (domain)
+                    SeccompCmpArgLen::Dword,
+                    SeccompCmpOp::Ne,
+                    domain as u64,
+                )
+            })
+            .collect::<std::result::Result<_, _>>()?,
+    )?;
+
+    let mut socket_rules = vec![unix_only_rule.clone()];
+    if network == NetworkMode::Udp {
+        for domain in [libc::AF_INET, libc::AF_INET6] {
+            socket_rules.push(non_datagram_socket_rule(domain)?);
+        }
+    }
 
-    rules.insert(libc::SYS_socket, vec![unix_only_rule.clone()]);
+    rules.insert(libc::SYS_socket, socket_rules);
     rules.insert(libc::SYS_socketpair, vec![unix_only_rule]); // always deny (Unix can use socketpair but fine, keep open?)
 
     let filter = SeccompFilter::new(
         rules,
         SeccompAction::Allow,                     // default – allow
         SeccompAction::Errno(libc::EPERM as u32), // when rule matches – return EPERM
-        if cfg!(target_arch = "x86_64") {
-            TargetArch::x86_64
-        } else if cfg!(target_arch = "aarch64") {
-            TargetArch::aarch64
-        } else {
-            unimplemented!("unsupported architecture for seccomp filter");
-        },
+        seccomp_target_arch(),
     )?;
 
-    let prog: BpfProgram = filter.try_into()?;
+    Ok(filter.try_into()?)
+}
 
-    apply_filter(&prog)?;
+/// Matches `socket(domain, type, ...)` calls for `domain` whose type is not
+/// `SOCK_DGRAM`, with or without the `SOCK_NONBLOCK`/`SOCK_CLOEXEC` flags.
+fn non_datagram_socket_rule(domain: libc::c_int) -> std::result::Result<SeccompRule, SandboxErr> {
+    let mut conditions = vec![SeccompCondition::new(
+        0, // first argument (domain)
+        SeccompCmpArgLen::Dword,
+        SeccompCmpOp::Eq,
+        domain as u64,
+    )?];
+    for flags in [
+        0,
+        libc::SOCK_NONBLOCK,
+        libc::SOCK_CLOEXEC,
+        libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+    ] {
+        conditions.push(SeccompCondition::new(
+            1, // second argument (type)
+            SeccompCmpArgLen::Dword,
+            SeccompCmpOp::Ne,
+            (libc::SOCK_DGRAM | flags) as u64,
+        )?);
+    }
+    Ok(SeccompRule::new(conditions)?)
+}
+
+/// Returns whether the kernel supports seccomp. `PR_GET_SECCOMP` fails with
+/// `EINVAL` when the kernel was built without `CONFIG_SECCOMP`.
+pub(crate) fn seccomp_available() -> bool {
+    unsafe { libc::prctl(libc::PR_GET_SECCOMP) != -1 }
+}
+
+/// The most instructions the kernel accepts in one classic BPF program
+/// (`BPF_MAXINSNS`).
+pub(crate) const MAX_BPF_INSTRUCTIONS: usize = 4096;
+
+/// Fails with [`SandboxErr::SeccompProgramTooLarge`] if `prog` is too long
+/// for the kernel, which would otherwise reject it with a bare `EINVAL`.
+fn check_seccomp_program_size(prog: &BpfProgram) -> std::result::Result<(), SandboxErr> {
+    if prog.len() > MAX_BPF_INSTRUCTIONS {
+        return Err(SandboxErr::SeccompProgramTooLarge(format!(
+            "{} instructions, but the kernel accepts at most {MAX_BPF_INSTRUCTIONS}; \
+             use fewer argument-inspecting rules or a shorter allowlist",
+            prog.len()
+        )));
+    }
+    Ok(())
+}
 
+/// Installs `prog` on the current thread, after checking that the kernel
+/// will accept its size. Every seccomp filter goes through here. With
+/// `all_threads`, it is loaded with `SECCOMP_FILTER_FLAG_TSYNC`, which puts
+/// it on every thread of the process at once, or fails if one of them has
+/// filters the calling thread does not.
+pub(crate) fn apply_seccomp_program(
+    prog: &BpfProgram,
+    all_threads: bool,
+) -> std::result::Result<(), SandboxErr> {
+    check_seccomp_program_size(prog)?;
+    if all_threads {
+        apply_filter_all_threads(prog)?;
+    } else {
+        apply_filter(prog)?;
+    }
     Ok(())
 }
+
+/// Returns the seccomp target architecture this binary was built for.
+pub(crate) fn seccomp_target_arch() -> TargetArch {
+    if cfg!(target_arch = "x86_64") {
+        TargetArch::x86_64
+    } else if cfg!(target_arch = "aarch64") {
+        TargetArch::aarch64
+    } else {
+        unimplemented!("unsupported architecture for seccomp filter");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn network_mode_follows_policy_and_loopback_flag() {
+        assert_eq!(
+            NetworkMode::for_policy(&SandboxPolicy::DangerFullAccess, true, true),
+            NetworkMode::Full
+        );
+        assert_eq!(
+            NetworkMode::for_policy(&SandboxPolicy::ReadOnly, false, false),
+            NetworkMode::Blocked
+        );
+        assert_eq!(
+            NetworkMode::for_policy(&SandboxPolicy::ReadOnly, true, false),
+            NetworkMode::LoopbackOnly
+        );
+        assert_eq!(
+            NetworkMode::for_policy(&SandboxPolicy::ReadOnly, false, true),
+            NetworkMode::Udp
+        );
+    }
+
+    #[test]
+    fn loopback_only_skips_landlock_network_rules() {
+        let mut enforcement = SandboxEnforcement::default();
+        let rules = LandlockRules::for_policy(
+            &SandboxPolicy::ReadOnly,
+            &SandboxOptions {
+                network: NetworkMode::LoopbackOnly,
+                ..SandboxOptions::for_policy(&SandboxPolicy::ReadOnly)
+            },
+            Path::new("/work"),
+            3,
+            &mut enforcement,
+        )
+        .expect("compute rules");
+
+        assert_eq!(
+            rules,
+            Some(LandlockRules {
+                writable_roots: vec![],
+                restrict_network: false,
+            })
+        );
+        // Only the filesystem rights ABI 3 lacks; skipping the network rules
+        // is not a degradation here.
+        assert_eq!(
+            enforcement,
+            SandboxEnforcement {
+                degradations: vec![Degradation::LandlockFilesystemRightsUnsupported { abi: 3 }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn private_tmp_replaces_slash_tmp_as_writable_root() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().join("cwd");
+        let private_tmp = tmpdir.path().join("private-tmp");
+        let policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![],
+            network_access: true,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: false,
+        };
+
+        let rules = LandlockRules::for_policy(
+            &policy,
+            &SandboxOptions {
+                private_tmp: Some(private_tmp.clone()),
+                ..SandboxOptions::for_policy(&policy)
+            },
+            &cwd,
+            5,
+            &mut SandboxEnforcement::default(),
+        )
+        .expect("compute rules");
+
+        assert_eq!(
+            rules,
+            Some(LandlockRules {
+                writable_roots: vec![cwd, private_tmp],
+                restrict_network: false,
+            })
+        );
+    }
+
+    #[test]
+    fn private_tmp_is_writable_under_a_read_only_policy() {
+        let rules = LandlockRules::for_policy(
+            &SandboxPolicy::ReadOnly,
+            &SandboxOptions {
+                private_tmp: Some(PathBuf::from("/tmp/codex-private-tmp.abc123")),
+                ..SandboxOptions::for_policy(&SandboxPolicy::ReadOnly)
+            },
+            Path::new("/work"),
+            5,
+            &mut SandboxEnforcement::default(),
+        )
+        .expect("compute rules");
+
+        assert_eq!(
+            rules,
+            Some(LandlockRules {
+                writable_roots: vec![PathBuf::from("/tmp/codex-private-tmp.abc123")],
+                restrict_network: true,
+            })
+        );
+    }
+
+    #[test]
+    fn allowed_devices_are_read_write() {
+        let rules = LandlockRules::for_policy(
+            &SandboxPolicy::ReadOnly,
+            &SandboxOptions {
+                network: NetworkMode::Full,
+                devices: vec![PathBuf::from("/dev/kvm")],
+                ..SandboxOptions::for_policy(&SandboxPolicy::ReadOnly)
+            },
+            Path::new("/work"),
+            5,
+            &mut SandboxEnforcement::default(),
+        )
+        .expect("compute rules");
+
+        assert_eq!(
+            rules,
+            Some(LandlockRules {
+                writable_roots: vec![PathBuf::from("/dev/kvm")],
+                restrict_network: false,
+            })
+        );
+    }
+
+    #[test]
+    fn too_many_writable_roots_fail_before_install() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().join("cwd");
+        let writable_roots: Vec<PathBuf> = (0..3)
+            .map(|i| tmpdir.path().join(format!("root-{i}")))
+            .collect();
+        for dir in writable_roots.iter().chain([&cwd]) {
+            std::fs::create_dir(dir).expect("create root");
+        }
+        let policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots,
+            network_access: true,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        };
+
+        let err = LandlockRules::for_policy(
+            &policy,
+            &SandboxOptions {
+                max_roots: 3,
+                ..SandboxOptions::for_policy(&policy)
+            },
+            &cwd,
+            5,
+            &mut SandboxEnforcement::default(),
+        )
+        .expect_err("four writable roots exceed the limit");
+
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: too many writable roots: 4 writable paths exceed the limit of 3; \
+             drop duplicate roots and roots nested in other roots, or raise --max-roots"
+        );
+    }
+
+    #[test]
+    fn network_rules_are_used_from_abi_4() {
+        let mut enforcement = SandboxEnforcement::default();
+        assert!(landlock_network_supported(4, &mut enforcement));
+        assert!(landlock_network_supported(5, &mut enforcement));
+        assert_eq!(enforcement, SandboxEnforcement::default());
+    }
+
+    #[test]
+    fn network_rules_are_skipped_on_partial_support_kernels() {
+        let mut enforcement = SandboxEnforcement::default();
+        assert!(!landlock_network_supported(3, &mut enforcement));
+        assert_eq!(
+            enforcement,
+            SandboxEnforcement {
+                degradations: vec![Degradation::LandlockNetworkUnsupported { abi: 3 }],
+                ..Default::default()
+            }
+        );
+    }
+
+    /// Fully enforced from ABI 5 (`LANDLOCK_ACCESS_FS_IOCTL_DEV`), the newest
+    /// right the ruleset asks for.
+    fn expected_landlock(abi: i32) -> LandlockEnforcement {
+        if abi >= 5 {
+            LandlockEnforcement::Full
+        } else {
+            LandlockEnforcement::Partial
+        }
+    }
+
+    /// What `abi` leaves unenforced, in the order it is recorded.
+    fn expected_degradations(abi: i32, restrict_network: bool) -> Vec<Degradation> {
+        let mut degradations = Vec::new();
+        if abi < LANDLOCK_FULL_FILESYSTEM_ABI {
+            degradations.push(Degradation::LandlockFilesystemRightsUnsupported { abi });
+        }
+        if restrict_network && abi < LANDLOCK_NETWORK_ABI {
+            degradations.push(Degradation::LandlockNetworkUnsupported { abi });
+        }
+        degradations
+    }
+
+    #[test]
+    fn cached_network_filter_matches_a_fresh_build() {
+        for network in [NetworkMode::Blocked, NetworkMode::LoopbackOnly] {
+            let fresh = build_network_seccomp_filter(network).expect("build filter");
+            // The first call may populate the cache, the second must hit it.
+            for _ in 0..2 {
+                assert_eq!(
+                    cached_network_seccomp_filter(network).expect("cached filter"),
+                    fresh
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn best_effort_plans_around_old_abis() {
+        let options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+        for abi in [3, 4, 5] {
+            let (rules, enforcement) = plan_landlock_rules(
+                &SandboxPolicy::ReadOnly,
+                &options,
+                Path::new("/work"),
+                abi,
+                EnforcementMode::BestEffort,
+            )
+            .expect("best effort accepts any ABI");
+
+            assert_eq!(
+                (rules, enforcement.degradations),
+                (
+                    Some(LandlockRules {
+                        writable_roots: vec![],
+                        restrict_network: abi >= LANDLOCK_NETWORK_ABI,
+                    }),
+                    expected_degradations(abi, true),
+                ),
+                "ABI {abi}"
+            );
+        }
+    }
+
+    #[test]
+    fn strict_requires_every_right() {
+        let options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+        let plan = |abi| {
+            plan_landlock_rules(
+                &SandboxPolicy::ReadOnly,
+                &options,
+                Path::new("/work"),
+                abi,
+                EnforcementMode::Strict,
+            )
+        };
+
+        for (abi, reasons) in [
+            (
+                3,
+                "Landlock ABI 3 cannot restrict every filesystem right (ABI 5 is required); Landlock ABI 3 cannot restrict network access (ABI 4 is required)",
+            ),
+            (
+                4,
+                "Landlock ABI 4 cannot restrict every filesystem right (ABI 5 is required)",
+            ),
+        ] {
+            let err = plan(abi).expect_err("strict rejects old ABIs");
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "sandbox error: sandbox is only partially enforced on this host: {reasons}"
+                )
+            );
+        }
+        let (_, enforcement) = plan(5).expect("ABI 5 enforces everything");
+        assert_eq!(enforcement, SandboxEnforcement::default());
+    }
+
+    /// Installs the real sandbox on a scratch thread, so the outcome depends
+    /// on the Landlock ABI of the kernel running the tests.
+    fn install_on_scratch_thread(
+        sandbox_policy: SandboxPolicy,
+        network: NetworkMode,
+    ) -> SandboxEnforcement {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().to_path_buf();
+        std::thread::spawn(move || {
+            let options = SandboxOptions {
+                network,
+                ..SandboxOptions::for_policy(&sandbox_policy)
+            };
+            install_sandbox_with_options_on_current_thread(
+                &sandbox_policy,
+                &options,
+                &cwd,
+                EnforcementMode::BestEffort,
+            )
+        })
+        .join()
+        .expect("sandbox thread panicked")
+        .expect("sandbox should install")
+    }
+
+    #[test]
+    fn combined_report_for_a_read_only_policy() {
+        let abi = landlock_abi();
+        if abi == 0 {
+            eprintln!("skipping: Landlock is not available on this kernel");
+            return;
+        }
+
+        let enforcement = install_on_scratch_thread(SandboxPolicy::ReadOnly, NetworkMode::Blocked);
+
+        assert_eq!(
+            enforcement,
+            SandboxEnforcement {
+                no_new_privs: true,
+                network: NetworkEnforcement::Seccomp,
+                landlock: Some(expected_landlock(abi)),
+                degradations: expected_degradations(abi, true),
+            }
+        );
+    }
+
+    #[test]
+    fn combined_report_for_full_access_installs_nothing() {
+        let enforcement =
+            install_on_scratch_thread(SandboxPolicy::DangerFullAccess, NetworkMode::Full);
+        assert_eq!(enforcement, SandboxEnforcement::default());
+    }
+
+    #[test]
+    fn combined_report_for_workspace_write_with_network() {
+        let abi = landlock_abi();
+        if abi == 0 {
+            eprintln!("skipping: Landlock is not available on this kernel");
+            return;
+        }
+
+        let enforcement = install_on_scratch_thread(
+            SandboxPolicy::WorkspaceWrite {
+                writable_roots: vec![],
+                network_access: true,
+                exclude_tmpdir_env_var: true,
+                exclude_slash_tmp: true,
+            },
+            NetworkMode::Full,
+        );
+        assert_eq!(
+            enforcement,
+            SandboxEnforcement {
+                no_new_privs: true,
+                network: NetworkEnforcement::Unrestricted,
+                landlock: Some(expected_landlock(abi)),
+                degradations: expected_degradations(abi, false),
+            }
+        );
+    }
+
+    #[test]
+    fn udp_mode_allows_udp_sockets_but_not_tcp() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().to_path_buf();
+        let outcome = std::thread::spawn(move || {
+            let options = SandboxOptions {
+                network: NetworkMode::Udp,
+                ..SandboxOptions::for_policy(&SandboxPolicy::DangerFullAccess)
+            };
+            let enforcement = install_sandbox_with_options_on_current_thread(
+                &SandboxPolicy::DangerFullAccess,
+                &options,
+                &cwd,
+                EnforcementMode::BestEffort,
+            )
+            .expect("sandbox should install");
+
+            // errno of `socket(domain, ty, 0)`, or `None` if it succeeded.
+            let socket_errno = |domain, ty| {
+                let fd = unsafe { libc::socket(domain, ty, 0) };
+                if fd < 0 {
+                    return std::io::Error::last_os_error().raw_os_error();
+                }
+                unsafe { libc::close(fd) };
+                None
+            };
+            (
+                enforcement.network,
+                [
+                    socket_errno(libc::AF_INET, libc::SOCK_DGRAM),
+                    socket_errno(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC),
+                    socket_errno(libc::AF_INET, libc::SOCK_STREAM),
+                    socket_errno(libc::AF_INET6, libc::SOCK_STREAM | libc::SOCK_NONBLOCK),
+                    socket_errno(libc::AF_INET, libc::SOCK_RAW),
+                    socket_errno(libc::AF_NETLINK, libc::SOCK_DGRAM),
+                ],
+            )
+        })
+        .join()
+        .expect("sandbox thread panicked");
+
+        let eperm = Some(libc::EPERM);
+        assert_eq!(
+            outcome,
+            (
+                NetworkEnforcement::SeccompWithUdp,
+                [None, None, eperm, eperm, eperm, eperm]
+            )
+        );
+    }
+
+    #[test]
+    fn landlock_failure_after_seccomp_reports_a_partial_install() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let cwd = tmpdir.path().to_path_buf();
+        let (err, tcp_errno) = std::thread::spawn(move || {
+            let options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+            let err = install_sandbox_steps(
+                &SandboxPolicy::ReadOnly,
+                &options,
+                &cwd,
+                EnforcementMode::BestEffort,
+                |_| Err(CodexErr::Sandbox(SandboxErr::LandlockRestrict)),
+            )
+            .expect_err("a Landlock failure should fail the install");
+
+            let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+            let tcp_errno = if fd < 0 {
+                std::io::Error::last_os_error().raw_os_error()
+            } else {
+                unsafe { libc::close(fd) };
+                None
+            };
+            (err, tcp_errno)
+        })
+        .join()
+        .expect("sandbox thread panicked");
+
+        let CodexErr::Sandbox(SandboxErr::PartiallyInstalled(message)) = err else {
+            panic!("expected a partial install, got {err:?}");
+        };
+        assert_eq!(
+            message,
+            "installing Landlock failed after the seccomp network filter took effect: \
+             sandbox error: Landlock was not able to fully enforce all sandbox rules"
+        );
+        // The seccomp filter stays in place.
+        assert_eq!(tcp_errno, Some(libc::EPERM));
+    }
+
+    #[test]
+    fn oversized_seccomp_programs_are_rejected_before_loading() {
+        // `BPF_RET | BPF_K`, allow: harmless if it were ever installed.
+        let allow = seccompiler::sock_filter {
+            code: 0x06,
+            jt: 0,
+            jf: 0,
+            k: 0x7fff_0000,
+        };
+        let prog = vec![allow; MAX_BPF_INSTRUCTIONS + 1];
+
+        let err = apply_seccomp_program(&prog, false).expect_err("the program is too large");
+        let SandboxErr::SeccompProgramTooLarge(message) = err else {
+            panic!("expected an oversized program, got {err:?}");
+        };
+        assert_eq!(
+            message,
+            "4097 instructions, but the kernel accepts at most 4096; \
+             use fewer argument-inspecting rules or a shorter allowlist"
+        );
+        assert!(check_seccomp_program_size(&vec![allow; MAX_BPF_INSTRUCTIONS]).is_ok());
+    }
+
+    /// Set to `on` or `off` in the copy of the test binary that
+    /// [`sibling_thread_opens_tcp_after_install`] runs [`tsync_helper`] in.
+    const TSYNC_HELPER_ENV: &str = "CODEX_LINUX_SANDBOX_TSYNC_HELPER";
+
+    /// Exit statuses [`tsync_helper`] reports its outcome with.
+    const SIBLING_TCP_ALLOWED: i32 = 10;
+    const SIBLING_TCP_DENIED: i32 = 11;
+
+    /// Runs [`tsync_helper`] alone in a fresh copy of the test binary, since
+    /// filtering every thread would restrict the rest of the harness, and
+    /// forking it while other tests run is not safe. Reports whether a thread
+    /// that was already running when the network filter went in can still
+    /// open a TCP socket.
+    fn sibling_thread_opens_tcp_after_install(all_threads: bool) -> bool {
+        let output = std::process::Command::new(std::env::current_exe().expect("test binary"))
+            .args([
+                "--ignored",
+                "--exact",
+                "landlock::tests::tsync_helper",
+                "--test-threads",
+                "1",
+            ])
+            .env(TSYNC_HELPER_ENV, if all_threads { "on" } else { "off" })
+            .output()
+            .expect("run the helper");
+        match output.status.code() {
+            Some(SIBLING_TCP_ALLOWED) => true,
+            Some(SIBLING_TCP_DENIED) => false,
+            _ => panic!("the helper did not report an outcome: {output:?}"),
+        }
+    }
+
+    /// Only does something as the helper of
+    /// [`sibling_thread_opens_tcp_after_install`]: installs the network
+    /// filter while a second thread is already running, then lets that
+    /// thread try to open a TCP socket, and exits with the outcome.
+    #[test]
+    #[ignore = "run by sibling_thread_opens_tcp_after_install"]
+    fn tsync_helper() {
+        let Ok(mode) = std::env::var(TSYNC_HELPER_ENV) else {
+            return;
+        };
+        let (installed, wait_for_install) = std::sync::mpsc::channel::<()>();
+        let sibling = std::thread::spawn(move || {
+            let _ = wait_for_install.recv();
+            let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) };
+            if fd >= 0 {
+                unsafe { libc::close(fd) };
+            }
+            fd >= 0
+        });
+        install_network_seccomp_filter_on_current_thread(NetworkMode::Blocked, mode == "on")
+            .expect("install the network filter");
+        installed.send(()).expect("wake the sibling thread");
+        let opened = sibling.join().expect("join the sibling thread");
+        std::process::exit(if opened {
+            SIBLING_TCP_ALLOWED
+        } else {
+            SIBLING_TCP_DENIED
+        });
+    }
+
+    #[test]
+    fn tsync_filters_threads_that_already_exist() {
+        assert!(!sibling_thread_opens_tcp_after_install(true));
+        assert!(sibling_thread_opens_tcp_after_install(false));
+    }
+}
@@ -0,0 +1,307 @@
+//! `--spec`: a JSON launch spec, for tools that generate launches, holding
+//! what would otherwise be many command-line arguments:
+//!
+//! ```json
+//! {
+//!   "policy": {"mode": "workspace-write", "writable_roots": []},
+//!   "cwd": "/work",
+//!   "env": {"RUST_LOG": "info"},
+//!   "command": ["cargo", "test"],
+//!   "flags": {"allow-loopback": true, "umask": "077", "writable-root": ["/cache"]}
+//! }
+//! ```
+//!
+//! Every field is optional. `policy` and `cwd` stand for `--sandbox-policy`
+//! and `--sandbox-policy-cwd`, and `flags` holds any other launcher flag
+//! under its long name: `true` passes a switch, `false` leaves it out, and an
+//! array repeats the flag once per value. `env` sets variables in the
+//! launcher's environment, which the command inherits.
+//!
+//! The spec's flags go before the command-line arguments, so:
+//!
+//! - a flag given on the command line overrides the spec's value for it;
+//! - values of repeatable flags such as `--writable-root` are combined;
+//! - a switch the spec turns on cannot be turned off again;
+//! - the command on the command line, if any, replaces the spec's `command`;
+//! - `env` overrides inherited variables, and is then filtered by
+//!   `--env-allow` and `--env-deny` like the rest of the environment.
+
+use std::ffi::OsString;
+use std::os::fd::RawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+const FIELDS: &[&str] = &["policy", "cwd", "env", "command", "flags"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LaunchSpec {
+    /// `policy`, `cwd` and `flags` as launcher arguments.
+    pub(crate) args: Vec<OsString>,
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) command: Vec<OsString>,
+}
+
+impl LaunchSpec {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let spec: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let Some(fields) = spec.as_object() else {
+            return Err("the spec must be a JSON object".to_string());
+        };
+
+        if let Some(field) = fields
+            .keys()
+            .find(|field| !FIELDS.contains(&field.as_str()))
+        {
+            return Err(format!("unknown field `{field}`"));
+        }
+
+        // In a fixed order, since the map's order depends on serde_json's
+        // features.
+        let mut launch_spec = Self::default();
+        if let Some(policy) = fields.get("policy") {
+            // An object like the policy's own JSON, or the string
+            // `--sandbox-policy` takes.
+            let policy = match policy {
+                Value::String(policy) => policy.clone(),
+                Value::Object(_) => policy.to_string(),
+                _ => return Err("`policy` must be an object".to_string()),
+            };
+            launch_spec
+                .args
+                .extend(["--sandbox-policy".into(), policy.into()]);
+        }
+        if let Some(cwd) = fields.get("cwd") {
+            let cwd = cwd.as_str().ok_or("`cwd` must be a string")?;
+            launch_spec
+                .args
+                .extend(["--sandbox-policy-cwd".into(), cwd.into()]);
+        }
+        if let Some(flags) = fields.get("flags") {
+            let flags = flags.as_object().ok_or("`flags` must be an object")?;
+            for (flag, value) in flags {
+                launch_spec.push_flag(flag, value)?;
+            }
+        }
+        if let Some(env) = fields.get("env") {
+            let vars = env.as_object().ok_or("`env` must be an object")?;
+            for (name, value) in vars {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| format!("`env.{name}` must be a string"))?;
+                launch_spec.env.push((name.clone(), value.to_string()));
+            }
+        }
+        if let Some(command) = fields.get("command") {
+            let command = command.as_array().ok_or("`command` must be an array")?;
+            launch_spec.command = command
+                .iter()
+                .map(|arg| arg.as_str().map(OsString::from))
+                .collect::<Option<_>>()
+                .ok_or("`command` must hold strings")?;
+        }
+        Ok(launch_spec)
+    }
+
+    fn push_flag(&mut self, flag: &str, value: &Value) -> Result<(), String> {
+        let name = || OsString::from(format!("--{flag}"));
+        let scalar = |value: &Value| match value {
+            Value::String(value) => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        };
+        match value {
+            Value::Bool(true) => self.args.push(name()),
+            Value::Bool(false) => {}
+            Value::Array(values) => {
+                for value in values {
+                    let value = scalar(value)
+                        .ok_or_else(|| format!("`flags.{flag}` must hold strings or numbers"))?;
+                    self.args.extend([name(), value.into()]);
+                }
+            }
+            value => {
+                let value = scalar(value).ok_or_else(|| {
+                    format!("`flags.{flag}` must be a boolean, string, number or array")
+                })?;
+                self.args.extend([name(), value.into()]);
+            }
+        }
+        Ok(())
+    }
+
+    /// `args` with the spec's arguments right after the program name, so
+    /// the command-line arguments that follow override them.
+    pub(crate) fn expand_args(&self, args: Vec<OsString>) -> Vec<OsString> {
+        let mut args = args.into_iter();
+        args.next()
+            .into_iter()
+            .chain(self.args.iter().cloned())
+            .chain(args)
+            .collect()
+    }
+}
+
+/// The `--spec` path in `args`, found before clap parses them since the
+/// spec may supply arguments clap requires. Only launcher flags, which come
+/// before `--` and the command, are searched.
+pub(crate) fn spec_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            return None;
+        }
+        if arg == "--spec" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--spec=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// The fd a `--spec` of `/dev/fd/N` or `/proc/self/fd/N` is read from, such
+/// as a pipe the caller wrote the spec into. The standard streams are left
+/// to the command.
+pub(crate) fn spec_fd(path: &Path) -> Option<RawFd> {
+    let fd = path
+        .strip_prefix("/dev/fd")
+        .or_else(|_| path.strip_prefix("/proc/self/fd"))
+        .ok()?
+        .to_str()?
+        .parse::<RawFd>()
+        .ok()?;
+    (fd > libc::STDERR_FILENO).then_some(fd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn os_strings(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn spec_fields_become_launcher_arguments() {
+        let spec = LaunchSpec::parse(
+            r#"{
+                "policy": {"mode": "read-only"},
+                "cwd": "/work",
+                "env": {"FOO": "bar"},
+                "command": ["echo", "hi"],
+                "flags": {"allow-loopback": true, "max-roots": 8, "umask": "077", "wait": false, "writable-root": ["a", "b"]}
+            }"#,
+        )
+        .expect("parse spec");
+
+        assert_eq!(
+            spec,
+            LaunchSpec {
+                args: os_strings(&[
+                    "--sandbox-policy",
+                    r#"{"mode":"read-only"}"#,
+                    "--sandbox-policy-cwd",
+                    "/work",
+                    "--allow-loopback",
+                    "--max-roots",
+                    "8",
+                    "--umask",
+                    "077",
+                    "--writable-root",
+                    "a",
+                    "--writable-root",
+                    "b",
+                ]),
+                env: vec![("FOO".to_string(), "bar".to_string())],
+                command: os_strings(&["echo", "hi"]),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_specs_are_rejected() {
+        assert_eq!(
+            LaunchSpec::parse("[]"),
+            Err("the spec must be a JSON object".to_string())
+        );
+        assert_eq!(
+            LaunchSpec::parse(r#"{"argv": []}"#),
+            Err("unknown field `argv`".to_string())
+        );
+        assert_eq!(
+            LaunchSpec::parse(r#"{"command": ["echo", 1]}"#),
+            Err("`command` must hold strings".to_string())
+        );
+        assert_eq!(
+            LaunchSpec::parse(r#"{"flags": {"umask": null}}"#),
+            Err("`flags.umask` must be a boolean, string, number or array".to_string())
+        );
+    }
+
+    #[test]
+    fn command_line_arguments_follow_the_spec() {
+        let spec = LaunchSpec {
+            args: os_strings(&["--umask", "077"]),
+            ..LaunchSpec::default()
+        };
+
+        assert_eq!(
+            spec.expand_args(os_strings(&[
+                "codex-linux-sandbox",
+                "--umask",
+                "022",
+                "--",
+                "true"
+            ])),
+            os_strings(&[
+                "codex-linux-sandbox",
+                "--umask",
+                "077",
+                "--umask",
+                "022",
+                "--",
+                "true"
+            ])
+        );
+    }
+
+    #[test]
+    fn spec_path_is_found_among_launcher_flags() {
+        assert_eq!(
+            spec_path(&os_strings(&[
+                "sandbox",
+                "--spec",
+                "launch.json",
+                "--",
+                "true"
+            ])),
+            Some(PathBuf::from("launch.json"))
+        );
+        assert_eq!(
+            spec_path(&os_strings(&["sandbox", "--spec=launch.json"])),
+            Some(PathBuf::from("launch.json"))
+        );
+        assert_eq!(
+            spec_path(&os_strings(&["sandbox", "--", "tool", "--spec", "x"])),
+            None
+        );
+    }
+
+    #[test]
+    fn spec_fd_is_read_from_fd_paths_only() {
+        assert_eq!(spec_fd(Path::new("/dev/fd/3")), Some(3));
+        assert_eq!(spec_fd(Path::new("/proc/self/fd/7")), Some(7));
+        assert_eq!(spec_fd(Path::new("/dev/fd/0")), None);
+        assert_eq!(spec_fd(Path::new("/dev/fd/3/x")), None);
+        assert_eq!(spec_fd(Path::new("/work/3")), None);
+    }
+}
@@ -1,7 +1,89 @@
 #[cfg(target_os = "linux")]
+mod backend;
+#[cfg(target_os = "linux")]
+mod bwrap;
+#[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(target_os = "linux")]
+mod command_env;
+#[cfg(target_os = "linux")]
+mod compliance;
+#[cfg(target_os = "linux")]
+mod enforcement;
+#[cfg(target_os = "linux")]
+mod env_filter;
+#[cfg(target_os = "linux")]
+mod events;
+#[cfg(target_os = "linux")]
+mod filter_dump;
+#[cfg(target_os = "linux")]
+mod forward_output;
+#[cfg(target_os = "linux")]
+mod groups;
+#[cfg(target_os = "linux")]
+mod ioctl_filter;
+#[cfg(target_os = "linux")]
+mod json;
+#[cfg(target_os = "linux")]
 mod landlock;
 #[cfg(target_os = "linux")]
+mod launch_spec;
+#[cfg(target_os = "linux")]
 mod linux_run_main;
+#[cfg(target_os = "linux")]
+mod logging;
+#[cfg(target_os = "linux")]
+mod manifest;
+#[cfg(target_os = "linux")]
+mod mountns;
+#[cfg(target_os = "linux")]
+mod netns;
+#[cfg(target_os = "linux")]
+mod policy_validator;
+#[cfg(target_os = "linux")]
+mod preflight;
+#[cfg(target_os = "linux")]
+mod priority;
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(target_os = "linux")]
+mod profile_syscalls;
+#[cfg(target_os = "linux")]
+mod redirect;
+#[cfg(target_os = "linux")]
+mod self_access;
+#[cfg(target_os = "linux")]
+mod self_harden;
+#[cfg(target_os = "linux")]
+mod self_test;
+#[cfg(target_os = "linux")]
+mod syscall_allowlist;
+#[cfg(target_os = "linux")]
+mod syscalls;
+#[cfg(target_os = "linux")]
+mod telemetry;
+#[cfg(target_os = "linux")]
+mod verify;
+#[cfg(target_os = "linux")]
+mod version;
+#[cfg(target_os = "linux")]
+mod wait;
+#[cfg(target_os = "linux")]
+mod writable_roots;
+
+#[cfg(target_os = "linux")]
+pub use enforcement::Degradation;
+#[cfg(target_os = "linux")]
+pub use enforcement::LandlockEnforcement;
+#[cfg(target_os = "linux")]
+pub use enforcement::NetworkEnforcement;
+#[cfg(target_os = "linux")]
+pub use enforcement::SandboxEnforcement;
+#[cfg(target_os = "linux")]
+pub use landlock::install_sandbox_on_current_thread;
+#[cfg(target_os = "linux")]
+#[doc(hidden)]
+pub use landlock::time_network_filter_cache;
 
 #[cfg(target_os = "linux")]
 pub fn run_main() -> ! {
@@ -1,10 +1,103 @@
 use clap::Parser;
+use clap::ValueEnum;
 use std::ffi::CString;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::Write as _;
+use std::os::fd::RawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
 
-use crate::landlock::apply_sandbox_policy_to_current_thread;
+use codex_core::error::CodexErr;
+use codex_core::error::SandboxErr;
+use tracing::debug;
+use tracing::warn;
 
+use crate::backend::Backend;
+use crate::backend::HostCapabilities;
+use crate::backend::SelectedBackend;
+use crate::backend::select_backend;
+use crate::bwrap::BWRAP_VERSION;
+use crate::bwrap::NIX_STORE;
+use crate::bwrap::build_bwrap_command;
+use crate::bwrap::unsupported_bwrap_features;
+use crate::bwrap::with_nix_store;
+use crate::cgroup::CgroupStart;
+use crate::command_env::derived_env;
+use crate::command_env::parse_setenv;
+use crate::command_env::set_vars;
+use crate::compliance::ComplianceLog;
+use crate::compliance::ComplianceRecord;
+use crate::enforcement::EnforcementMode;
+use crate::enforcement::EnforcementVerdict;
+use crate::enforcement::LandlockEnforcement;
+use crate::enforcement::NetworkEnforcement;
+use crate::env_filter::EnvFilter;
+use crate::events::EventStream;
+use crate::filter_dump::write_filter_dump;
+use crate::forward_output::OutputPipes;
+use crate::groups::set_supplementary_groups;
+use crate::ioctl_filter::install_ioctl_filter_on_current_thread;
+use crate::json::json_string;
+use crate::json::json_string_array;
+use crate::landlock::DEFAULT_MAX_ROOTS;
+use crate::landlock::NetworkMode;
+use crate::landlock::SandboxOptions;
+use crate::landlock::install_sandbox_with_options_on_current_thread;
+use crate::landlock::seccomp_available;
+use crate::launch_spec::LaunchSpec;
+use crate::launch_spec::spec_fd;
+use crate::launch_spec::spec_path;
+use crate::logging::Verbosity;
+use crate::logging::init_logging;
+use crate::manifest::ManifestFile;
+use crate::manifest::PrintRoots;
+use crate::manifest::RunManifest;
+use crate::mountns::enter_private_mount_namespace;
+use crate::policy_validator::DEFAULT_VALIDATOR_TIMEOUT_SECS;
+use crate::policy_validator::validate_policy;
+use crate::preflight::run_preflight_main;
+use crate::priority::NICE_RANGE;
+use crate::priority::SchedPolicy;
+use crate::priority::set_niceness;
+use crate::priority::set_sched_policy;
+use crate::profile_syscalls::run_profile_syscalls_main;
+use crate::redirect::OutputRedirect;
+use crate::redirect::Redirections;
+use crate::self_access::launcher_files;
+use crate::self_access::writable_root_of;
+use crate::self_access::writable_roots_for_exposure;
+use crate::self_harden::LAUNCHER_CAPABILITIES;
+use crate::self_harden::keep_only;
+use crate::self_harden::needed_capabilities;
+use crate::self_harden::self_harden_requested;
+use crate::self_test::SelfTest;
+use crate::self_test::VerdictPipe;
+use crate::syscall_allowlist::SyscallAllowlist;
+use crate::syscall_allowlist::SyscallDenyAction;
+use crate::syscall_allowlist::SyscallProfile;
+use crate::syscall_allowlist::install_syscall_allowlist_on_current_thread;
+use crate::telemetry::AttributeValue;
+use crate::telemetry::Telemetry;
+use crate::verify::DEFAULT_PROBE_TIMEOUT_MILLIS;
+use crate::verify::VerifyPlan;
+use crate::version::run_version_main;
+use crate::wait::set_cloexec;
+use crate::wait::wait_for_child_and_exit;
+use crate::writable_roots::add_writable_roots;
+use crate::writable_roots::create_missing_writable_roots;
+use crate::writable_roots::symlinked_writable_roots;
+
+/// Repeating a flag that takes one value keeps the last one, which lets the
+/// command line override a `--spec`.
 #[derive(Debug, Parser)]
+#[command(args_override_self = true)]
 pub struct LandlockCommand {
     /// It is possible that the cwd used in the context of the sandbox policy
     /// is different from the cwd of the process to spawn.
@@ -14,43 +107,1703 @@ pub struct LandlockCommand {
     #[arg(long = "sandbox-policy")]
     pub sandbox_policy: codex_core::protocol::SandboxPolicy,
 
+    /// Mechanism used to enforce the policy.
+    #[arg(long = "backend", value_enum, default_value_t = Backend::Auto)]
+    pub backend: Backend,
+
+    /// When the policy blocks network access, still allow IP traffic over
+    /// loopback. The command gets a private network namespace, so it can reach
+    /// servers it starts itself but not those of the host.
+    #[arg(long = "allow-loopback")]
+    pub allow_loopback: bool,
+
+    /// When the policy blocks network access, still allow UDP sockets (for
+    /// example, so the resolver can reach DNS servers) while TCP stays
+    /// blocked. Seccomp cannot filter by port, so any UDP destination is
+    /// reachable. Has no effect under bwrap, which blocks all networking.
+    #[arg(long = "allow-udp", conflicts_with = "allow_loopback")]
+    pub allow_udp: bool,
+
+    /// Give the command its own `/tmp` and point `TMPDIR` at it, so it cannot
+    /// see or interfere with other processes' temporary files.
+    #[arg(long = "private-tmp")]
+    pub private_tmp: bool,
+
+    /// Create writable roots that do not exist yet (like `mkdir -p`), with
+    /// the mode `--umask` implies, instead of skipping them. Roots are never
+    /// created directly inside system directories such as `/etc`.
+    #[arg(long = "create-roots")]
+    pub create_roots: bool,
+
+    /// Also allow writing to this directory, in addition to the writable
+    /// roots of a `workspace-write` policy. Relative paths are resolved
+    /// against `--sandbox-policy-cwd`, and system directories such as `/etc`
+    /// are rejected. Repeat for several directories.
+    #[arg(long = "writable-root", value_name = "PATH")]
+    pub writable_roots: Vec<PathBuf>,
+
+    /// Pipe the resolved policy as JSON to this program, and enforce the
+    /// policy it prints instead. The launch fails if it exits non-zero, runs
+    /// too long or prints no valid policy. It runs unsandboxed, so it must be
+    /// trusted like the launcher itself.
+    #[arg(long = "policy-validator", value_name = "PATH")]
+    pub policy_validator: Option<PathBuf>,
+
+    /// Seconds the `--policy-validator` may run before it is killed and the
+    /// launch fails.
+    #[arg(
+        long = "policy-validator-timeout",
+        value_name = "SECS",
+        default_value_t = DEFAULT_VALIDATOR_TIMEOUT_SECS,
+        value_parser = parse_validator_timeout,
+        requires = "policy_validator"
+    )]
+    pub policy_validator_timeout: u64,
+
+    /// With the bwrap backend, mount only the host's system directories
+    /// (`/usr`, `/etc`, `/lib`, ...) and the writable roots instead of all
+    /// of `/`.
+    #[arg(long = "minimal-root")]
+    pub minimal_root: bool,
+
+    /// With `--minimal-root`, also mount this host path (e.g.
+    /// `/opt/toolchain`) read-only. Repeat for several paths.
+    #[arg(
+        long = "keep-mount",
+        value_name = "PATH",
+        value_parser = parse_keep_mount,
+        requires = "minimal_root"
+    )]
+    pub keep_mounts: Vec<PathBuf>,
+
+    /// With `--minimal-root`, do not mount `/nix/store`, which is otherwise
+    /// kept whenever it exists so programs on NixOS can run.
+    #[arg(long = "no-nix-store", requires = "minimal_root")]
+    pub no_nix_store: bool,
+
+    /// With the bwrap backend, hide this path (e.g. `~/.ssh`) even though the
+    /// policy lets the command read it: a file reads as empty and a
+    /// directory as an empty, read-only directory. Repeat for several paths.
+    #[arg(long = "read-deny", value_name = "PATH", value_parser = parse_read_deny)]
+    pub read_deny: Vec<PathBuf>,
+
+    /// Let the command read and write the launcher's own executable, its
+    /// `--spec` and its `--policy-validator`, which the bwrap backend hides
+    /// from the command by default. The Landlock backend refuses to run with
+    /// the executable in a writable root unless this is given. Meant for
+    /// testing.
+    #[arg(long = "allow-self-access")]
+    pub allow_self_access: bool,
+
+    /// Fail before installing Landlock if the policy has more writable paths
+    /// (roots, the private tmp and devices) than this, which would otherwise
+    /// fail deep in the kernel or make a slow ruleset.
+    #[arg(long = "max-roots", value_name = "N", default_value_t = DEFAULT_MAX_ROOTS)]
+    pub max_roots: usize,
+
+    /// Also give the command read-write access to this device node (e.g.
+    /// `/dev/kvm`), or to a directory of them such as `/dev/dri`. Under bwrap
+    /// it is bound into the otherwise minimal `/dev`. Repeat for several
+    /// devices.
+    #[arg(long = "allow-device", value_name = "PATH", value_parser = parse_device)]
+    pub allow_devices: Vec<PathBuf>,
+
+    /// `best-effort` installs whatever the kernel supports and runs the
+    /// command anyway; `strict` fails instead of skipping rules the kernel
+    /// does not support or falling back to a different mechanism (such as
+    /// bwrap when seccomp is unavailable).
+    #[arg(long = "enforcement", value_enum, default_value_t = EnforcementMode::BestEffort)]
+    pub enforcement: EnforcementMode,
+
+    /// Shorthand for `--enforcement strict`, which it overrides.
+    #[arg(long = "require-enforcement")]
+    pub require_enforcement: bool,
+
+    /// Only permit the syscalls listed in this file (one name or number per
+    /// line, `#` starts a comment); every other syscall is denied.
+    #[arg(long = "syscall-allowlist", value_name = "FILE")]
+    pub syscall_allowlist: Option<PathBuf>,
+
+    /// Curated syscall allowlist preset. Merged with `--syscall-allowlist`
+    /// when both are given.
+    #[arg(long = "profile", value_enum)]
+    pub profile: Option<SyscallProfile>,
+
+    /// What happens to syscalls outside the allowlist.
+    #[arg(long = "syscall-deny-action", value_enum, default_value_t = SyscallDenyAction::Errno)]
+    pub syscall_deny_action: SyscallDenyAction,
+
+    /// Only let `ioctl` through for common terminal and stream requests
+    /// (`TCGETS`, `TIOCGWINSZ`, `FIONREAD`, ...); any other request fails
+    /// with `EPERM`.
+    #[arg(long = "restrict-ioctls")]
+    pub restrict_ioctls: bool,
+
+    /// Whether seccomp filters are loaded with `SECCOMP_FILTER_FLAG_TSYNC`,
+    /// which puts them on every thread of the launcher rather than only the
+    /// one that execs the command. `off` is meant for diagnosing hosts where
+    /// syncing fails.
+    #[arg(long = "seccomp-tsync", value_enum, default_value_t = SeccompTsync::On)]
+    pub seccomp_tsync: SeccompTsync,
+
+    /// Drop the launcher's own capabilities, except the few the backend and
+    /// the other flags need, before the policy is parsed. Only takes effect
+    /// that early when given on the command line rather than in `--spec`.
+    #[arg(long = "self-harden")]
+    pub self_harden: bool,
+
+    /// File mode creation mask (in octal, e.g. `077`) for the command. The
+    /// inherited umask is kept when this is not given.
+    #[arg(long = "umask", value_name = "OCTAL", value_parser = parse_umask)]
+    pub umask: Option<libc::mode_t>,
+
+    /// Run the command with exactly these supplementary groups, as
+    /// comma-separated gids. Needs `CAP_SETGID` unless they are the
+    /// launcher's own groups. Not supported by the bwrap backend.
+    #[arg(
+        long = "groups",
+        value_name = "GID,...",
+        value_delimiter = ',',
+        value_parser = parse_gid
+    )]
+    pub groups: Option<Vec<libc::gid_t>>,
+
+    /// Niceness for the command, from -20 (highest priority) to 19
+    /// (lowest). Values below the launcher's own need `CAP_SYS_NICE` or a
+    /// high enough `RLIMIT_NICE`.
+    #[arg(
+        long = "nice",
+        value_name = "N",
+        allow_negative_numbers = true,
+        value_parser = parse_nice
+    )]
+    pub nice: Option<i32>,
+
+    /// Scheduling policy for the command.
+    #[arg(long = "sched", value_enum)]
+    pub sched: Option<SchedPolicy>,
+
+    /// SIGPIPE disposition for the command.
+    #[arg(long = "sigpipe", value_enum, default_value_t = SigpipeDisposition::Default)]
+    pub sigpipe: SigpipeDisposition,
+
+    /// Read the command's stdin from this file, which `--read-deny` must not
+    /// hide.
+    #[arg(long = "stdin", value_name = "PATH")]
+    pub stdin: Option<PathBuf>,
+
+    /// Write the command's stdout to this file, which must be writable under
+    /// the policy. It is created if missing and truncated unless
+    /// `--stdout-append` is given.
+    #[arg(long = "stdout", value_name = "PATH")]
+    pub stdout: Option<PathBuf>,
+
+    /// Append to the `--stdout` file instead of truncating it.
+    #[arg(long = "stdout-append", requires = "stdout")]
+    pub stdout_append: bool,
+
+    /// Like `--stdout`, for the command's stderr.
+    #[arg(long = "stderr", value_name = "PATH")]
+    pub stderr: Option<PathBuf>,
+
+    /// Append to the `--stderr` file instead of truncating it.
+    #[arg(long = "stderr-append", requires = "stderr")]
+    pub stderr_append: bool,
+
+    /// Start the command in this directory instead of `--sandbox-policy-cwd`,
+    /// under every backend. Relative paths are resolved against
+    /// `--sandbox-policy-cwd`. The launch fails if the directory cannot be
+    /// entered inside the sandbox.
+    #[arg(long = "chdir", value_name = "DIR")]
+    pub chdir: Option<PathBuf>,
+
+    /// Pass this as `argv[0]` instead of the program name, for programs that
+    /// branch on it (multi-call binaries, login shells expecting a leading
+    /// `-`). The program is still looked up from the first command argument.
+    #[arg(long = "argv0", value_name = "VALUE", value_parser = parse_argv0)]
+    pub argv0: Option<String>,
+
+    /// Run the command through this program (e.g. `nice`, `timeout`), which
+    /// is exec'd inside the sandbox with `--exec-wrapper-arg`s and then the
+    /// command as its arguments. It is expected to exec the command itself,
+    /// so it cannot be combined with `--argv0`.
+    #[arg(long = "exec-wrapper", value_name = "PATH", conflicts_with = "argv0")]
+    pub exec_wrapper: Option<PathBuf>,
+
+    /// Argument passed to the `--exec-wrapper` before the command. Repeat for
+    /// several arguments.
+    #[arg(
+        long = "exec-wrapper-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        requires = "exec_wrapper"
+    )]
+    pub exec_wrapper_args: Vec<OsString>,
+
+    /// Pass only environment variables matching this name pattern (`*` and
+    /// `?` are wildcards) to the command. Repeat for several patterns.
+    #[arg(long = "env-allow", value_name = "NAME|GLOB")]
+    pub env_allow: Vec<String>,
+
+    /// Remove environment variables matching this name pattern, even if an
+    /// `--env-allow` pattern matches them too. Repeat for several patterns.
+    #[arg(long = "env-deny", value_name = "NAME|GLOB")]
+    pub env_deny: Vec<String>,
+
+    /// Do not add the built-in set of credential variables (such as
+    /// `GITHUB_TOKEN`) to the `--env-deny` patterns.
+    #[arg(long = "no-default-env-deny")]
+    pub no_default_env_deny: bool,
+
+    /// Set this variable in the command's environment, over the variables
+    /// the launcher derives from the sandbox (such as `TMPDIR`) and past the
+    /// `--env-allow` and `--env-deny` filters. Repeat for several variables.
+    #[arg(long = "setenv", value_name = "NAME=VALUE", value_parser = parse_setenv)]
+    pub setenv: Vec<(String, OsString)>,
+
+    /// Fork and wait for the command instead of exec'ing it in place. The
+    /// sandbox is installed only in the child, and the launcher exits with the
+    /// command's status (`128 + signal` if it was killed by a signal).
+    #[arg(long = "wait")]
+    pub wait: bool,
+
+    /// In `--wait` mode, write a JSON report of how the command exited and
+    /// its resource usage to this already-open file descriptor.
+    #[arg(long = "report-fd", value_name = "FD", requires = "wait")]
+    pub report_fd: Option<RawFd>,
+
+    /// In `--wait` mode, start the command in a frozen cgroup v2 and thaw it
+    /// only once a byte (or end of file) can be read from this already-open
+    /// file descriptor, after the launcher has finished its own setup.
+    #[arg(long = "freeze-until-ready", value_name = "FD", requires = "wait")]
+    pub freeze_until_ready: Option<RawFd>,
+
+    /// In `--wait` mode, run the command in its own cgroup v2 so its peak
+    /// memory usage can be added to the report as `memory_peak`.
+    #[arg(long = "memory-report", requires = "wait")]
+    pub memory_report: bool,
+
+    /// In `--wait` mode, give the command pipes as its stdout and stderr and
+    /// copy them to the launcher's a line at a time, so lines from the two
+    /// streams never interleave. An unfinished line, such as progress output,
+    /// is copied after a short delay.
+    #[arg(long = "forward-output", requires = "wait")]
+    pub forward_output: bool,
+
+    /// Write the seccomp programs (disassembled) and the Landlock ruleset to
+    /// this file before installing them.
+    #[arg(long = "dump-filter", value_name = "PATH")]
+    pub dump_filter: Option<PathBuf>,
+
+    /// Write a JSON description of the run (host, backend, resolved roots,
+    /// network and environment decisions, final argv) to this file before
+    /// exec'ing the command. In `--wait` mode the command's exit report is
+    /// added once it has exited.
+    #[arg(long = "manifest", value_name = "PATH")]
+    pub manifest: Option<PathBuf>,
+
+    /// Append one JSON object describing the paths and network access the
+    /// sandbox applied, and its verdict, to this file, and sync it to disk
+    /// before exec'ing the command. The command is not run if the record
+    /// cannot be written.
+    #[arg(
+        long = "compliance-log",
+        value_name = "PATH",
+        conflicts_with_all = ["dry_run", "verify"]
+    )]
+    pub compliance_log: Option<PathBuf>,
+
+    /// Resolve the policy and print the manifest of the run to stdout
+    /// instead of running the command. Nothing is installed, created or
+    /// opened besides the `--manifest` and `--dump-filter` files.
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// With `--dry-run`, print only the writable or readable paths, each
+    /// followed by a NUL byte, for `xargs -0`.
+    #[arg(long = "print-roots", value_enum, requires = "dry_run")]
+    pub print_roots: Option<PrintRoots>,
+
+    /// Install the sandbox in a forked probe child, try a few operations the
+    /// policy forbids, and print whether each was denied instead of running
+    /// the command. Exits non-zero unless every probe came out as the policy
+    /// says.
+    #[arg(long = "verify", conflicts_with = "dry_run")]
+    pub verify: bool,
+
+    /// Milliseconds each `--verify` probe may take. A connect that gets no
+    /// answer in time counts as denied, any other probe as inconclusive.
+    #[arg(
+        long = "probe-timeout",
+        value_name = "MILLIS",
+        default_value_t = DEFAULT_PROBE_TIMEOUT_MILLIS,
+        value_parser = parse_probe_timeout,
+        requires = "verify"
+    )]
+    pub probe_timeout: u64,
+
+    /// Write newline-delimited JSON events to this already-open file
+    /// descriptor as each step of the sandbox setup completes, ending with
+    /// the argv about to be exec'd.
+    #[arg(long = "events-fd", value_name = "FD")]
+    pub events_fd: Option<RawFd>,
+
+    /// Only print errors, not the launcher's warnings.
+    #[arg(long = "quiet", conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also print how the sandbox was set up, such as the selected backend
+    /// and restrictions the host could not enforce.
+    #[arg(long = "verbose")]
+    pub verbose: bool,
+
+    /// Read the policy, command, environment and other flags from this JSON
+    /// launch spec. Flags given on the command line override it.
+    #[arg(long = "spec", value_name = "PATH")]
+    pub spec: Option<PathBuf>,
+
     /// Full command args to run under landlock.
     #[arg(trailing_var_arg = true)]
-    pub command: Vec<String>,
+    pub command: Vec<OsString>,
 }
 
 pub fn run_main() -> ! {
+    // Running a command needs `--sandbox-policy-cwd` and `--sandbox-policy`
+    // before it, so a first argument of `preflight`, `profile-syscalls` or
+    // `version` can only be the subcommand.
+    let subcommand_args = || {
+        std::env::args_os()
+            .take(1)
+            .chain(std::env::args_os().skip(2))
+    };
+    match std::env::args_os().nth(1) {
+        Some(arg) if arg == "preflight" => run_preflight_main(subcommand_args()),
+        Some(arg) if arg == "profile-syscalls" => run_profile_syscalls_main(subcommand_args()),
+        Some(arg) if arg == "version" => run_version_main(subcommand_args()),
+        _ => {}
+    }
+
+    let args: Vec<OsString> = std::env::args_os().collect();
+    // Before anything the caller passed is parsed, the spec and the policy
+    // included.
+    if self_harden_requested(&args)
+        && let Err(e) = keep_only(LAUNCHER_CAPABILITIES)
+    {
+        panic!("--self-harden could not drop capabilities: {e}");
+    }
+    // Read ahead of clap, since the spec may supply required arguments.
+    let spec_source = spec_path(&args);
+    let launch_spec = match &spec_source {
+        Some(path) => match LaunchSpec::load(path) {
+            Ok(launch_spec) => launch_spec,
+            Err(e) => panic!("error reading --spec: {e}"),
+        },
+        None => LaunchSpec::default(),
+    };
+    // Otherwise the command inherits the fd and could read the spec again
+    // through it, even where no path leads to it.
+    if let Some(fd) = spec_source.as_deref().and_then(spec_fd)
+        && let Err(e) = set_cloexec(fd)
+    {
+        panic!("--spec fd {fd} is not usable: {e}");
+    }
+    let args = launch_spec.expand_args(args);
+
     let LandlockCommand {
         sandbox_policy_cwd,
         sandbox_policy,
+        backend,
+        allow_loopback,
+        allow_udp,
+        private_tmp,
+        create_roots,
+        writable_roots,
+        policy_validator,
+        policy_validator_timeout,
+        minimal_root,
+        keep_mounts,
+        no_nix_store,
+        read_deny,
+        allow_self_access,
+        max_roots,
+        allow_devices,
+        enforcement,
+        require_enforcement,
+        syscall_allowlist,
+        profile,
+        syscall_deny_action,
+        restrict_ioctls,
+        seccomp_tsync,
+        self_harden,
+        umask,
+        groups,
+        nice,
+        sched,
+        sigpipe,
+        stdin,
+        stdout,
+        stdout_append,
+        stderr,
+        stderr_append,
+        chdir,
+        argv0,
+        exec_wrapper,
+        exec_wrapper_args,
+        env_allow,
+        env_deny,
+        no_default_env_deny,
+        setenv,
+        wait,
+        report_fd,
+        freeze_until_ready,
+        memory_report,
+        forward_output,
+        dump_filter,
+        manifest,
+        compliance_log,
+        dry_run,
+        print_roots,
+        verify,
+        probe_timeout,
+        events_fd,
+        quiet,
+        verbose,
+        spec,
         command,
-    } = LandlockCommand::parse();
-
-    if let Err(e) = apply_sandbox_policy_to_current_thread(&sandbox_policy, &sandbox_policy_cwd) {
-        panic!("error running landlock: {e:?}");
+    } = LandlockCommand::parse_from(args);
+    init_logging(Verbosity::from_flags(quiet, verbose));
+    if let Some(spec) = &spec {
+        debug!("read the launch spec {}", spec.display());
+    }
+    // Before anything reads the environment, as if the launcher had been
+    // started with these variables.
+    for (name, value) in &launch_spec.env {
+        // Safe because the launcher is still single-threaded.
+        unsafe {
+            std::env::set_var(name, value);
+        }
     }
+    let command = if command.is_empty() {
+        launch_spec.command
+    } else {
+        command
+    };
+    let mut events = match events_fd {
+        Some(events_fd) => match EventStream::from_fd(events_fd) {
+            Ok(events) => events,
+            Err(e) => panic!("--events-fd {events_fd} is not usable: {e}"),
+        },
+        None => EventStream::default(),
+    };
+    let started = SystemTime::now();
+    // Read before the environment is filtered, which may drop `OTEL_*`.
+    let mut telemetry = Telemetry::from_env();
 
     if command.is_empty() {
         panic!("No command specified to execute.");
     }
+    let sandbox_policy =
+        match add_writable_roots(sandbox_policy, &writable_roots, &sandbox_policy_cwd) {
+            Ok(sandbox_policy) => sandbox_policy,
+            Err(e) => panic!("error adding writable roots: {e}"),
+        };
+    // Before anything is derived from the policy, so what the validator
+    // returns is exactly what gets enforced.
+    let sandbox_policy = match &policy_validator {
+        Some(policy_validator) => match validate_policy(
+            policy_validator,
+            &sandbox_policy,
+            Duration::from_secs(policy_validator_timeout),
+        ) {
+            Ok(validated) => {
+                if validated != sandbox_policy {
+                    debug!("--policy-validator changed the policy to {validated:?}");
+                }
+                validated
+            }
+            Err(e) => panic!("--policy-validator failed, refusing to run the command: {e}"),
+        },
+        None => sandbox_policy,
+    };
+    for (link, target) in symlinked_writable_roots(&sandbox_policy, &sandbox_policy_cwd) {
+        warn!(
+            "writable root {} is a symlink; writes are allowed beneath its target {} instead",
+            link.display(),
+            target.display()
+        );
+    }
+    // The same under every backend, rather than whatever directory the
+    // launcher or bwrap happens to be in.
+    let command_cwd = match &chdir {
+        Some(chdir) => sandbox_policy_cwd.join(chdir),
+        None => sandbox_policy_cwd.clone(),
+    };
+    let mut command = command;
+    match resolve_program(&command[0], &command_cwd) {
+        Ok(program) => command[0] = program,
+        Err(e) => panic!(
+            "command {} cannot be executed from {}: {e}",
+            command[0].to_string_lossy(),
+            command_cwd.display()
+        ),
+    }
+    // A bare name is looked up on `PATH` now, rather than failing in
+    // `execvp` once the sandbox is installed. An exec wrapper may run the
+    // command with a `PATH` of its own, so it is left to the wrapper then.
+    if exec_wrapper.is_none()
+        && !command[0].as_bytes().contains(&b'/')
+        && let Err(e) = check_executable(Path::new(&command[0]))
+    {
+        panic!(
+            "command {} cannot be executed: {e}",
+            command[0].to_string_lossy()
+        );
+    }
+    let command = match &exec_wrapper {
+        Some(exec_wrapper) => std::iter::once(exec_wrapper.clone().into_os_string())
+            .chain(exec_wrapper_args)
+            .chain(command)
+            .collect(),
+        None => command,
+    };
+
+    let syscall_allowlist = match SyscallAllowlist::load(syscall_allowlist.as_deref(), profile) {
+        Ok(syscall_allowlist) => syscall_allowlist,
+        Err(e) => panic!("error loading syscall allowlist: {e}"),
+    };
+    if syscall_allowlist.is_some() {
+        if !seccomp_available() {
+            panic!("a syscall allowlist was requested, but seccomp is not available");
+        }
+        warn!(
+            "syscall allowlists are fragile; the syscalls a program makes vary with its libc version and build"
+        );
+    }
 
-    #[expect(clippy::expect_used)]
-    let c_command =
-        CString::new(command[0].as_str()).expect("Failed to convert command to CString");
-    #[expect(clippy::expect_used)]
-    let c_args: Vec<CString> = command
-        .iter()
-        .map(|arg| CString::new(arg.as_str()).expect("Failed to convert arg to CString"))
-        .collect();
+    if restrict_ioctls && !seccomp_available() {
+        panic!("--restrict-ioctls needs seccomp, which is not available");
+    }
+
+    let enforcement = EnforcementMode::from_flags(enforcement, require_enforcement);
+    let host = HostCapabilities::detect();
+    let selected_backend = match select_backend(backend, &sandbox_policy, &host, enforcement) {
+        Ok(selected_backend) => selected_backend,
+        Err(e) => panic!("error selecting the sandbox backend: {e}"),
+    };
+    debug!("selected the {} backend", selected_backend.name());
+    if minimal_root && !matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        panic!(
+            "--minimal-root is only supported by the bwrap backend, not {}",
+            selected_backend.name()
+        );
+    }
+    // Landlock can only grant access, so it cannot carve paths out of full
+    // read access.
+    if !read_deny.is_empty() && !matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        panic!(
+            "--read-deny is only supported by the bwrap backend, not {}",
+            selected_backend.name()
+        );
+    }
+    let mut read_deny = read_deny;
+    if !allow_self_access {
+        let files = launcher_files(spec.as_deref(), policy_validator.as_deref());
+        match &selected_backend {
+            SelectedBackend::Bwrap { .. } => read_deny.extend(files),
+            // Landlock cannot carve the executable out of a writable root, so
+            // the launcher fails closed rather than let the command replace
+            // it for later launches. Writing everywhere is what the policy
+            // asked for.
+            SelectedBackend::Landlock => {
+                if let Ok(exe) = std::env::current_exe().and_then(|exe| exe.canonicalize())
+                    && let Some(writable_roots) =
+                        writable_roots_for_exposure(&sandbox_policy, &sandbox_policy_cwd)
+                    && let Some(root) = writable_root_of(&exe, &writable_roots)
+                {
+                    panic!(
+                        "refusing to run the command: {} is inside the writable root {}, and the Landlock backend cannot keep the command from overwriting it; use --backend bwrap to hide the launcher's files, or --allow-self-access",
+                        exe.display(),
+                        root.display()
+                    );
+                }
+            }
+            SelectedBackend::None => {}
+        }
+    }
+    // The probes run in-process, and bwrap sets up its sandbox in another
+    // process.
+    if verify && matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        panic!("--verify is not supported by the bwrap backend");
+    }
+    // Seccomp filters would be installed on the launcher, which then execs
+    // bwrap, so they would restrict bwrap's own setup rather than only the
+    // command.
+    if matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        if syscall_allowlist.is_some() {
+            panic!("--syscall-allowlist and --profile are not supported by the bwrap backend");
+        }
+        if restrict_ioctls {
+            panic!("--restrict-ioctls is not supported by the bwrap backend");
+        }
+    }
+    // An unprivileged user namespace cannot change supplementary groups.
+    if groups.is_some() && matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        panic!("--groups is not supported by the bwrap backend");
+    }
+    if !host.proc_mounted && matches!(selected_backend, SelectedBackend::Bwrap { .. }) {
+        warn!(
+            "/proc is not mounted, so bwrap runs without checking that user namespaces are enabled"
+        );
+    }
+
+    // After the host was probed, since finding bwrap needs PATH, and before
+    // TMPDIR is pointed at a private tmp so that it is never filtered out.
+    let env_filter = EnvFilter::new(env_allow, env_deny, !no_default_env_deny);
+    if let Some(env_filter) = &env_filter {
+        // Safe because the launcher is still single-threaded.
+        unsafe {
+            env_filter.apply_to_process_env();
+        }
+    }
+
+    let private_tmp = if private_tmp {
+        let dir = match &selected_backend {
+            // bwrap mounts a fresh tmpfs over /tmp itself.
+            SelectedBackend::Bwrap { .. } => PathBuf::from("/tmp"),
+            // Removed again before a dry run exits.
+            SelectedBackend::Landlock | SelectedBackend::None => match create_private_tmp_dir() {
+                Ok(dir) => dir,
+                Err(e) => panic!("error creating private tmp directory: {e}"),
+            },
+        };
+        Some(dir)
+    } else {
+        None
+    };
+    let options = SandboxOptions {
+        network: NetworkMode::for_policy(&sandbox_policy, allow_loopback, allow_udp),
+        private_tmp,
+        devices: allow_devices,
+        minimal_root: minimal_root.then(|| {
+            if no_nix_store {
+                keep_mounts
+            } else {
+                with_nix_store(keep_mounts, Path::new(NIX_STORE))
+            }
+        }),
+        read_deny,
+        max_roots,
+        restrict_ioctls,
+        seccomp_tsync: seccomp_tsync == SeccompTsync::On,
+    };
+    // Set before the writable roots are computed from the policy, which
+    // include TMPDIR. Safe because the launcher is still single-threaded.
+    unsafe {
+        set_vars(&derived_env(&options));
+    }
 
-    let mut c_args_ptrs: Vec<*const libc::c_char> = c_args.iter().map(|arg| arg.as_ptr()).collect();
+    if self_harden {
+        let needed = needed_capabilities(
+            &selected_backend,
+            &options,
+            groups.is_some(),
+            nice.is_some() || sched.is_some(),
+        );
+        if let Err(e) = keep_only(needed) {
+            panic!("--self-harden could not drop capabilities: {e}");
+        }
+        if needed.is_empty() {
+            debug!("--self-harden dropped every capability");
+        } else {
+            debug!("--self-harden kept {}", needed.names().join(", "));
+        }
+    }
+
+    // Before the Landlock rules or bwrap binds are built, both of which
+    // skip or fail on missing roots.
+    if create_roots
+        && !dry_run
+        && !verify
+        && let Err(e) = create_missing_writable_roots(&sandbox_policy, &sandbox_policy_cwd, umask)
+    {
+        panic!("error creating writable roots: {e:?}");
+    }
+
+    // Opened while the launcher is unrestricted, which is why the paths are
+    // checked against the policy.
+    let output_redirect =
+        |path: Option<PathBuf>, append| path.map(|path| OutputRedirect { path, append });
+    let redirections = if dry_run || verify {
+        Redirections::default()
+    } else {
+        match Redirections::open(
+            stdin.as_deref(),
+            output_redirect(stdout, stdout_append).as_ref(),
+            output_redirect(stderr, stderr_append).as_ref(),
+            &sandbox_policy,
+            &sandbox_policy_cwd,
+            &options,
+        ) {
+            Ok(redirections) => redirections,
+            Err(e) => panic!("error redirecting the command's standard streams: {e}"),
+        }
+    };
+
+    // Build the argv up front: once a syscall allowlist is installed, even
+    // allocating may no longer be possible.
+    let mut argv: Vec<CString> = match &selected_backend {
+        SelectedBackend::Bwrap { bwrap } => {
+            if backend == Backend::Auto {
+                warn!("seccomp is not available; isolating the network with bwrap instead");
+                events.emit("bwrap_fallback", &[]);
+            }
+            let version = *BWRAP_VERSION;
+            if let Some(version) = version {
+                for feature in unsupported_bwrap_features(Some(version), argv0.is_some()) {
+                    warn!(
+                        "bwrap {version} lacks {} (added in {}), so {}",
+                        feature.flag(),
+                        feature.min_version(),
+                        feature.consequence()
+                    );
+                }
+            }
+            let bwrap_command = match build_bwrap_command(
+                bwrap,
+                version,
+                &sandbox_policy,
+                &sandbox_policy_cwd,
+                &options,
+                &command_cwd,
+                argv0.as_deref(),
+                &command,
+            ) {
+                Ok(bwrap_command) => bwrap_command,
+                Err(e) => panic!("error running bwrap: {e:?}"),
+            };
+            bwrap_command
+                .iter()
+                .map(|arg| to_cstring(arg.as_os_str()))
+                .collect()
+        }
+        SelectedBackend::Landlock | SelectedBackend::None => command
+            .iter()
+            .map(|arg| to_cstring(arg.as_os_str()))
+            .collect(),
+    };
+
+    let program = argv[0].clone();
+    // bwrap applies `--argv0` itself when it execs the command.
+    if let Some(argv0) = &argv0
+        && !matches!(selected_backend, SelectedBackend::Bwrap { .. })
+    {
+        argv[0] = to_cstring(OsStr::new(argv0));
+    }
+
+    let mut c_args_ptrs: Vec<*const libc::c_char> = argv.iter().map(|arg| arg.as_ptr()).collect();
     c_args_ptrs.push(std::ptr::null());
 
+    if let Some(dump_filter) = &dump_filter
+        && let Err(e) = write_filter_dump(
+            dump_filter,
+            &sandbox_policy,
+            &options,
+            &sandbox_policy_cwd,
+            &selected_backend,
+            syscall_allowlist
+                .as_ref()
+                .map(|syscall_allowlist| (syscall_allowlist, syscall_deny_action)),
+            &command_cwd,
+            &command,
+        )
+    {
+        panic!("error writing filter dump: {e:?}");
+    }
+
+    // Kept open for the self-test verdict and, in `--wait` mode, the exit
+    // report.
+    let mut manifest = manifest.map(|path| {
+        let run_manifest = match RunManifest::new(
+            &host,
+            &selected_backend,
+            &sandbox_policy,
+            &sandbox_policy_cwd,
+            &options,
+            env_filter.as_ref(),
+            &argv,
+        ) {
+            Ok(run_manifest) => run_manifest,
+            Err(e) => panic!("error writing manifest: {e:?}"),
+        };
+        let mut file = match ManifestFile::create(path.clone()) {
+            Ok(file) => file,
+            Err(e) => panic!("error writing manifest to {}: {e}", path.display()),
+        };
+        if let Err(e) = run_manifest.write(&mut file, None, None) {
+            panic!("error writing manifest to {file}: {e}");
+        }
+        (file, run_manifest)
+    });
+
+    // Opened now for the same reason as the manifest, but only written once
+    // the verdict is known.
+    let mut compliance_log = compliance_log.map(|path| {
+        let record = match ComplianceRecord::new(
+            &selected_backend,
+            &sandbox_policy,
+            &sandbox_policy_cwd,
+            &options,
+            host.landlock_abi,
+            &command_cwd,
+            &argv,
+        ) {
+            Ok(record) => record,
+            Err(e) => panic!("error resolving the compliance record: {e:?}"),
+        };
+        match ComplianceLog::open(path.clone()) {
+            Ok(log) => (log, record),
+            Err(e) => panic!("error opening --compliance-log {}: {e}", path.display()),
+        }
+    });
+
+    if dry_run {
+        let run_manifest = match RunManifest::new(
+            &host,
+            &selected_backend,
+            &sandbox_policy,
+            &sandbox_policy_cwd,
+            &options,
+            env_filter.as_ref(),
+            &argv,
+        ) {
+            Ok(run_manifest) => run_manifest,
+            Err(e) => panic!("error resolving the run: {e:?}"),
+        };
+        let output = match print_roots {
+            Some(roots) => run_manifest.roots_nul_separated(roots),
+            None => format!("{}\n", run_manifest.to_json(None, None)).into_bytes(),
+        };
+        if let Some(private_tmp) = &options.private_tmp
+            && !matches!(selected_backend, SelectedBackend::Bwrap { .. })
+        {
+            let _ = std::fs::remove_dir(private_tmp);
+        }
+        if let Err(e) = std::io::stdout().write_all(&output) {
+            panic!("error writing the dry run to stdout: {e}");
+        }
+        std::process::exit(0);
+    }
+
+    if verify {
+        let plan = VerifyPlan::prepare(
+            &sandbox_policy,
+            &options,
+            &sandbox_policy_cwd,
+            host.landlock_abi,
+            Duration::from_millis(probe_timeout),
+        );
+        let report = match plan.run_in_child(|| match selected_backend {
+            SelectedBackend::Landlock => {
+                install_sandbox_with_options_on_current_thread(
+                    &sandbox_policy,
+                    &options,
+                    &sandbox_policy_cwd,
+                    enforcement,
+                )
+                .map_err(|e| e.to_string())?;
+                if options.restrict_ioctls {
+                    install_ioctl_filter_on_current_thread(options.seccomp_tsync)
+                        .map_err(|e| e.to_string())?;
+                }
+                Ok(())
+            }
+            SelectedBackend::Bwrap { .. } | SelectedBackend::None => Ok(()),
+        }) {
+            Ok(report) => report,
+            Err(e) => panic!("error running the --verify probes: {e}"),
+        };
+        if let Some(private_tmp) = &options.private_tmp {
+            let _ = std::fs::remove_dir(private_tmp);
+        }
+        if let Err(e) = writeln!(
+            std::io::stdout(),
+            "{}",
+            report.to_json(selected_backend.name())
+        ) {
+            panic!("error writing the --verify report to stdout: {e}");
+        }
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
+
+    // Inherited across exec, including by the command bwrap runs.
+    if let Some(umask) = umask {
+        unsafe {
+            libc::umask(umask);
+        }
+    }
+
+    telemetry.record(
+        "policy_resolution",
+        started,
+        vec![
+            ("policy", AttributeValue::Text(sandbox_policy.to_string())),
+            (
+                "backend",
+                AttributeValue::Text(selected_backend.name().to_string()),
+            ),
+            (
+                "network",
+                AttributeValue::Text(options.network.name().to_string()),
+            ),
+        ],
+    );
+
+    // Set in the child in `--wait` mode, which reports the self-test to the
+    // launcher instead of writing the manifest itself.
+    let mut verdict_sender = None;
+    // Likewise set in the child, which attaches the pipes right before the
+    // exec.
+    let mut output_pipes = None;
+    if wait {
+        if let Some(report_fd) = report_fd
+            && let Err(e) = set_cloexec(report_fd)
+        {
+            panic!("--report-fd {report_fd} is not usable: {e}");
+        }
+        let cgroup_start = match freeze_until_ready {
+            Some(ready_fd) => {
+                if let Err(e) = set_cloexec(ready_fd) {
+                    panic!("--freeze-until-ready {ready_fd} is not usable: {e}");
+                }
+                match CgroupStart::prepare(Some(ready_fd)) {
+                    Ok(cgroup_start) => Some(cgroup_start),
+                    Err(e) => panic!("--freeze-until-ready needs the cgroup v2 freezer: {e}"),
+                }
+            }
+            // The report just leaves the peak out without a cgroup.
+            None if memory_report => match CgroupStart::prepare(None) {
+                Ok(cgroup_start) => Some(cgroup_start),
+                Err(e) => {
+                    warn!("--memory-report needs a cgroup v2 for the command: {e}");
+                    None
+                }
+            },
+            None => None,
+        };
+        let verdict_pipe = match VerdictPipe::new() {
+            Ok(verdict_pipe) => verdict_pipe,
+            Err(e) => panic!("error creating the self-test pipe: {e}"),
+        };
+        let forwarded = forward_output.then(|| match OutputPipes::new() {
+            Ok(output_pipes) => output_pipes,
+            Err(e) => panic!("error creating the --forward-output pipes: {e}"),
+        });
+        telemetry.prepare_fork();
+        match unsafe { libc::fork() } {
+            -1 => panic!("fork failed: {}", std::io::Error::last_os_error()),
+            0 => {
+                telemetry.into_child();
+                verdict_sender = Some(verdict_pipe.into_sender());
+                output_pipes = forwarded;
+                if let Some(cgroup_start) = cgroup_start {
+                    cgroup_start.wait_until_released();
+                }
+            }
+            pid => {
+                let cgroup = cgroup_start.map(|cgroup_start| match cgroup_start.release(pid) {
+                    Ok(cgroup) => cgroup,
+                    Err(e) => panic!("could not start the command in its cgroup: {e}"),
+                });
+                // bwrap's private tmp is a tmpfs that goes away by itself.
+                let private_tmp = options
+                    .private_tmp
+                    .as_deref()
+                    .filter(|_| !matches!(selected_backend, SelectedBackend::Bwrap { .. }));
+                wait_for_child_and_exit(
+                    pid,
+                    report_fd,
+                    cgroup,
+                    private_tmp,
+                    manifest,
+                    verdict_pipe,
+                    forwarded,
+                    telemetry,
+                )
+            }
+        }
+    } else {
+        // The last chance to reach the network before the sandbox is
+        // installed.
+        telemetry.export();
+    }
+
+    // The Rust runtime ignores SIGPIPE, and an ignored signal stays ignored
+    // across exec, so without this the command would inherit SIG_IGN. Only
+    // in the child in `--wait` mode: the launcher keeps ignoring it, so that
+    // it still reports the command's status once its own stdout is gone.
+    let sigpipe_handler = match sigpipe {
+        SigpipeDisposition::Default => libc::SIG_DFL,
+        SigpipeDisposition::Ignore => libc::SIG_IGN,
+    };
     unsafe {
-        libc::execvp(c_command.as_ptr(), c_args_ptrs.as_ptr());
+        libc::signal(libc::SIGPIPE, sigpipe_handler);
+    }
+
+    // Only in the child in `--wait` mode, and before the sandbox, which may
+    // deny setgroups once a syscall allowlist is installed.
+    if let Some(groups) = &groups
+        && let Err(e) = set_supplementary_groups(groups)
+    {
+        panic!("error setting --groups: {e}");
+    }
+
+    // Like the groups, before a syscall allowlist may deny the calls. The
+    // policy comes first because leaving SCHED_IDLE can depend on the
+    // niceness still being the launcher's.
+    if let Some(sched) = sched
+        && let Err(e) = set_sched_policy(sched)
+    {
+        panic!("error setting --sched: {e}");
+    }
+    if let Some(nice) = nice
+        && let Err(e) = set_niceness(nice)
+    {
+        panic!("error setting --nice: {e}");
+    }
+
+    let self_test = SelfTest::prepare(
+        &sandbox_policy,
+        &options,
+        &sandbox_policy_cwd,
+        host.landlock_abi,
+    );
+    let verdict = match selected_backend {
+        SelectedBackend::Landlock => {
+            // In strict mode this fails before installing anything if some
+            // restriction cannot be enforced.
+            match install_sandbox_with_options_on_current_thread(
+                &sandbox_policy,
+                &options,
+                &sandbox_policy_cwd,
+                enforcement,
+            ) {
+                Ok(installed) => {
+                    for degradation in &installed.degradations {
+                        debug!("not enforced: {degradation}");
+                    }
+                    if installed.network != NetworkEnforcement::Unrestricted {
+                        events.emit(
+                            "seccomp_installed",
+                            &[("network", json_string(options.network.name()))],
+                        );
+                    }
+                    if let Some(landlock) = installed.landlock {
+                        events.emit(
+                            "landlock_installed",
+                            &[
+                                ("abi", host.landlock_abi.to_string()),
+                                ("full", (landlock == LandlockEnforcement::Full).to_string()),
+                            ],
+                        );
+                    }
+                    let self_test = self_test.run(&installed);
+                    if let Err(e) = &self_test {
+                        if enforcement == EnforcementMode::Strict {
+                            panic!("refusing to run the command: the self-test failed: {e}");
+                        }
+                        warn!(
+                            "the self-test failed, so the sandbox is reported as not enforced: {e}"
+                        );
+                    }
+                    EnforcementVerdict::for_landlock(&installed, self_test)
+                }
+                // Never exec after a failure, which may leave some
+                // restrictions in place but not others.
+                Err(CodexErr::Sandbox(e @ SandboxErr::PartiallyInstalled(_))) => {
+                    panic!("refusing to run the command: {e}")
+                }
+                Err(e) => panic!("error running landlock: {e:?}"),
+            }
+        }
+        // bwrap sets up the whole sandbox itself, but would let mounts the
+        // host adds later propagate into it.
+        SelectedBackend::Bwrap { .. } => {
+            match enter_private_mount_namespace() {
+                Ok(true) => {}
+                Ok(false) => warn!(
+                    "could not create a private mount namespace for bwrap, so mounts the host adds under a writable root later show up inside the sandbox"
+                ),
+                Err(e) => panic!("error setting up a private mount namespace for bwrap: {e}"),
+            }
+            EnforcementVerdict::unverified_bwrap()
+        }
+        SelectedBackend::None => {
+            warn!("--backend none runs the command without a sandbox");
+            EnforcementVerdict::unsandboxed()
+        }
+    };
+    events.emit(
+        "enforcement",
+        &[
+            ("enforced", verdict.enforced.to_string()),
+            ("reason", json_string(&verdict.reason)),
+        ],
+    );
+    match verdict_sender {
+        Some(verdict_sender) => {
+            if let Err(e) = verdict_sender.send(&verdict) {
+                warn!("could not send the self-test verdict to the launcher: {e}");
+            }
+        }
+        None => {
+            if let Some((file, run_manifest)) = &mut manifest
+                && let Err(e) = run_manifest.write(file, Some(&verdict), None)
+            {
+                warn!("could not add the self-test verdict to {file}: {e}");
+            }
+        }
+    }
+
+    // Once the sandbox is in place, like bwrap, which enters the directory
+    // inside it.
+    if !matches!(selected_backend, SelectedBackend::Bwrap { .. })
+        && let Err(e) = std::env::set_current_dir(&command_cwd)
+    {
+        panic!("cannot start the command in {}: {e}", command_cwd.display());
+    }
+
+    // Checked once the sandbox is in place, so a wrapper the read rules hide
+    // fails here with a clear message rather than as a failed exec.
+    if let Some(exec_wrapper) = &exec_wrapper
+        && !matches!(selected_backend, SelectedBackend::Bwrap { .. })
+        && let Err(e) = check_executable(exec_wrapper)
+    {
+        panic!(
+            "--exec-wrapper {} cannot be executed inside the sandbox: {e}",
+            exec_wrapper.display()
+        );
+    }
+
+    // Only now, so the variables reach the command without changing the
+    // writable roots computed from the environment. Safe because the
+    // launcher, or in `--wait` mode its child, is single-threaded.
+    unsafe {
+        set_vars(&setenv);
+    }
+
+    // Without the record, the run would leave no trace for an audit.
+    if let Some((log, record)) = &mut compliance_log
+        && let Err(e) = log.append(record, &verdict)
+    {
+        panic!("refusing to run the command: could not append to --compliance-log {log}: {e}");
+    }
+
+    // Before the syscall allowlist, which may deny the write.
+    events.emit(
+        "exec",
+        &[(
+            "argv",
+            json_string_array(argv.iter().map(|arg| arg.to_string_lossy())),
+        )],
+    );
+
+    // As late as possible, so the launcher's own messages still reach its
+    // stderr, but before the syscall allowlist, which may deny dup2. The
+    // pipes come first, so a stream redirected to a file goes there instead.
+    if let Some(output_pipes) = output_pipes
+        && let Err(e) = output_pipes.attach()
+    {
+        panic!("error attaching the --forward-output pipes: {e}");
+    }
+    if let Err(e) = redirections.apply() {
+        panic!("error redirecting the command's standard streams: {e}");
+    }
+
+    // Before the syscall allowlist, which may deny installing it.
+    if options.restrict_ioctls
+        && let Err(e) = install_ioctl_filter_on_current_thread(options.seccomp_tsync)
+    {
+        panic!("error installing the ioctl filter: {e:?}");
+    }
+
+    // Installed last because it likely denies the syscalls the other
+    // sandboxing steps rely on.
+    if let Some(syscall_allowlist) = &syscall_allowlist
+        && let Err(e) = install_syscall_allowlist_on_current_thread(
+            syscall_allowlist,
+            syscall_deny_action,
+            options.seccomp_tsync,
+        )
+    {
+        panic!("error installing syscall allowlist: {e:?}");
+    }
+
+    unsafe {
+        libc::execvp(program.as_ptr(), c_args_ptrs.as_ptr());
     }
 
     // If execvp returns, there was an error.
     let err = std::io::Error::last_os_error();
-    panic!("Failed to execvp {}: {err}", command[0].as_str());
+    panic!("Failed to execvp {}: {err}", program.to_string_lossy());
+}
+
+/// What the command does when it writes to a pipe with no reader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SigpipeDisposition {
+    /// Terminate, like commands started from a shell.
+    Default,
+    /// Ignore the signal, so the write fails with `EPIPE` instead.
+    Ignore,
+}
+
+/// Whether seccomp filters cover the whole thread group, for `--seccomp-tsync`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SeccompTsync {
+    /// Load each filter on every thread of the process.
+    On,
+    /// Load each filter on the calling thread only.
+    Off,
+}
+
+/// Creates a fresh, owner-only directory under the host's `/tmp`. Only the
+/// directory is writable under Landlock; `/tmp` itself is not.
+fn create_private_tmp_dir() -> std::io::Result<PathBuf> {
+    let mut template = b"/tmp/codex-private-tmp.XXXXXX\0".to_vec();
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(std::io::Error::last_os_error());
+    }
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+#[expect(clippy::expect_used)]
+fn to_cstring(arg: &OsStr) -> CString {
+    CString::new(arg.as_bytes()).expect("Failed to convert arg to CString")
+}
+
+/// Why a program cannot be exec'd.
+#[derive(Debug)]
+enum ProgramError {
+    /// A bare name that no directory on `PATH` has.
+    NotOnPath,
+    IsADirectory,
+    /// Neither a regular file nor a directory, such as a socket.
+    NotARegularFile,
+    /// A regular file without execute permission for the effective user.
+    NotExecutable,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramError::NotOnPath => write!(f, "not found on PATH"),
+            ProgramError::IsADirectory => write!(f, "is a directory"),
+            ProgramError::NotARegularFile => write!(f, "is not a regular file"),
+            ProgramError::NotExecutable => {
+                write!(f, "is not executable by the effective user")
+            }
+            ProgramError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Resolves a relative `program` with a `/`, such as `./foo` or `../bin/foo`,
+/// against `command_cwd`, where the command starts, rather than against the
+/// launcher's own working directory. The result is canonicalized so the
+/// command runs the same file under every backend. Absolute paths are checked
+/// with [`check_executable`] and returned unchanged. Bare names are returned
+/// unchanged too, for `execvp` to look up on `PATH`.
+fn resolve_program(
+    program: &OsStr,
+    command_cwd: &Path,
+) -> std::result::Result<OsString, ProgramError> {
+    let path = Path::new(program);
+    if !program.as_bytes().contains(&b'/') {
+        return Ok(program.to_os_string());
+    }
+    if path.is_absolute() {
+        check_executable(path)?;
+        return Ok(program.to_os_string());
+    }
+    let resolved = command_cwd
+        .join(path)
+        .canonicalize()
+        .map_err(ProgramError::Io)?;
+    check_executable(&resolved)?;
+    Ok(resolved.into_os_string())
+}
+
+/// Checks that `program` can be exec'd: that it is, or is a symlink to, a
+/// regular file the effective user may execute. Without a `/` it is looked
+/// up on `PATH` the way `execvp` would, which skips candidates it cannot
+/// execute; the first of those is reported if none can be.
+fn check_executable(program: &Path) -> std::result::Result<(), ProgramError> {
+    if program.as_os_str().as_bytes().contains(&b'/') {
+        return check_executable_file(program);
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut first_error = None;
+    for dir in std::env::split_paths(&path) {
+        match check_executable_file(&dir.join(program)) {
+            Ok(()) => return Ok(()),
+            Err(ProgramError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or(ProgramError::NotOnPath))
+}
+
+fn check_executable_file(path: &Path) -> std::result::Result<(), ProgramError> {
+    let metadata = std::fs::metadata(path).map_err(ProgramError::Io)?;
+    if metadata.is_dir() {
+        return Err(ProgramError::IsADirectory);
+    }
+    if !metadata.is_file() {
+        return Err(ProgramError::NotARegularFile);
+    }
+    let path = to_cstring(path.as_os_str());
+    // `AT_EACCESS` checks for the effective rather than the real user, as
+    // exec does.
+    if unsafe { libc::faccessat(libc::AT_FDCWD, path.as_ptr(), libc::X_OK, libc::AT_EACCESS) } == 0
+    {
+        return Ok(());
+    }
+    let e = std::io::Error::last_os_error();
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => Err(ProgramError::NotExecutable),
+        _ => Err(ProgramError::Io(e)),
+    }
+}
+
+/// Accepts a device node, or a directory of them under `/dev` such as
+/// `/dev/dri`.
+fn parse_device(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    let metadata = std::fs::metadata(&path).map_err(|err| format!("{value}: {err}"))?;
+    let file_type = metadata.file_type();
+    let is_device = file_type.is_char_device() || file_type.is_block_device();
+    let is_device_dir = file_type.is_dir() && path.starts_with("/dev") && path != Path::new("/dev");
+    if is_device || is_device_dir {
+        Ok(path)
+    } else {
+        Err(format!("{value} is not a device node"))
+    }
+}
+
+/// Accepts an existing absolute path, which bwrap can bind at the same place.
+fn parse_keep_mount(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    if !path.is_absolute() {
+        return Err(format!("{value} is not an absolute path"));
+    }
+    std::fs::metadata(&path).map_err(|err| format!("{value}: {err}"))?;
+    Ok(path)
+}
+
+/// Accepts an absolute path, which need not exist.
+fn parse_read_deny(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(format!("{value} is not an absolute path"))
+    }
+}
+
+/// Rejects values that cannot be passed to exec.
+fn parse_argv0(value: &str) -> Result<String, String> {
+    if value.contains('\0') {
+        return Err("argv0 must not contain NUL bytes".to_string());
+    }
+    Ok(value.to_string())
+}
+
+/// Accepts a numeric gid other than `-1`, which `setgroups` cannot use.
+fn parse_gid(value: &str) -> Result<libc::gid_t, String> {
+    match value.trim().parse::<libc::gid_t>() {
+        Ok(libc::gid_t::MAX) => Err(format!("gid `{value}` is not a valid group")),
+        Ok(gid) => Ok(gid),
+        Err(_) => Err(format!("gid `{value}` is not a number")),
+    }
+}
+
+fn parse_validator_timeout(value: &str) -> Result<u64, String> {
+    match value.trim().parse::<u64>() {
+        Ok(0) => Err("the --policy-validator timeout must be at least 1 second".to_string()),
+        Ok(secs) => Ok(secs),
+        Err(_) => Err(format!(
+            "timeout `{value}` is not a whole number of seconds"
+        )),
+    }
+}
+
+fn parse_probe_timeout(value: &str) -> Result<u64, String> {
+    match value.trim().parse::<u64>() {
+        Ok(0) => Err("the --probe-timeout must be at least 1 millisecond".to_string()),
+        Ok(millis) => Ok(millis),
+        Err(_) => Err(format!(
+            "timeout `{value}` is not a whole number of milliseconds"
+        )),
+    }
+}
+
+fn parse_nice(value: &str) -> Result<i32, String> {
+    match value.trim().parse::<i32>() {
+        Ok(nice) if NICE_RANGE.contains(&nice) => Ok(nice),
+        Ok(_) => Err(format!(
+            "nice value `{value}` is outside {}..={}",
+            NICE_RANGE.start(),
+            NICE_RANGE.end()
+        )),
+        Err(_) => Err(format!("nice value `{value}` is not a number")),
+    }
+}
+
+/// Parses an octal umask such as `022`, `0o077` or `7`.
+fn parse_umask(value: &str) -> Result<libc::mode_t, String> {
+    let digits = value.strip_prefix("0o").unwrap_or(value);
+    match libc::mode_t::from_str_radix(digits, 8) {
+        Ok(umask) if umask <= 0o777 => Ok(umask),
+        Ok(_) => Err(format!("umask `{value}` is larger than 0777")),
+        Err(_) => Err(format!("umask `{value}` is not an octal number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn parse_device_accepts_only_device_nodes() {
+        assert_eq!(parse_device("/dev/null"), Ok(PathBuf::from("/dev/null")));
+        assert_eq!(
+            parse_device("/dev"),
+            Err("/dev is not a device node".to_string())
+        );
+        assert_eq!(
+            parse_device("/tmp"),
+            Err("/tmp is not a device node".to_string())
+        );
+        assert_eq!(
+            parse_device("/bin/sh"),
+            Err("/bin/sh is not a device node".to_string())
+        );
+        assert!(parse_device("/dev/codex-no-such-device").is_err());
+    }
+
+    #[test]
+    fn parse_keep_mount_accepts_existing_absolute_paths() {
+        assert_eq!(parse_keep_mount("/usr"), Ok(PathBuf::from("/usr")));
+        assert_eq!(
+            parse_keep_mount("usr"),
+            Err("usr is not an absolute path".to_string())
+        );
+        assert!(parse_keep_mount("/codex-no-such-mount").is_err());
+    }
+
+    #[test]
+    fn check_executable_resolves_bare_names_on_path() {
+        assert!(check_executable(Path::new("/bin/sh")).is_ok());
+        assert!(check_executable(Path::new("sh")).is_ok());
+        assert!(matches!(
+            check_executable(Path::new("/nonexistent/wrapper")),
+            Err(ProgramError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+        assert_eq!(
+            check_executable(Path::new("codex-no-such-wrapper"))
+                .expect_err("missing wrapper")
+                .to_string(),
+            "not found on PATH"
+        );
+    }
+
+    #[test]
+    fn check_executable_rejects_directories_and_non_executable_files() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let data = tmpdir.path().join("data");
+        let script = tmpdir.path().join("script");
+        let link = tmpdir.path().join("link");
+        std::fs::write(&data, "").expect("write data");
+        std::fs::write(&script, "#!/bin/sh\n").expect("write script");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+            .expect("make script executable");
+        std::os::unix::fs::symlink(&script, &link).expect("symlink script");
+
+        assert!(matches!(
+            check_executable(tmpdir.path()),
+            Err(ProgramError::IsADirectory)
+        ));
+        assert!(matches!(
+            check_executable(Path::new("/dev/null")),
+            Err(ProgramError::NotARegularFile)
+        ));
+        assert!(check_executable(&script).is_ok());
+        assert!(check_executable(&link).is_ok());
+        // Not even root may execute a file without any execute bit.
+        assert!(matches!(
+            check_executable(&data),
+            Err(ProgramError::NotExecutable)
+        ));
+    }
+
+    #[test]
+    fn parse_read_deny_accepts_absolute_paths() {
+        assert_eq!(
+            parse_read_deny("/codex-no-such-secret"),
+            Ok(PathBuf::from("/codex-no-such-secret"))
+        );
+        assert_eq!(
+            parse_read_deny(".ssh"),
+            Err(".ssh is not an absolute path".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_program_uses_the_command_cwd_for_relative_paths() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let root = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+        let work = root.join("work");
+        let bin = root.join("bin");
+        std::fs::create_dir_all(&work).expect("create work");
+        std::fs::create_dir_all(&bin).expect("create bin");
+        for script in [work.join("foo"), bin.join("foo")] {
+            std::fs::write(&script, "#!/bin/sh\n").expect("write script");
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+                .expect("make script executable");
+        }
+
+        assert_eq!(
+            resolve_program(OsStr::new("./foo"), &work).expect("resolve ./foo"),
+            work.join("foo").into_os_string()
+        );
+        assert_eq!(
+            resolve_program(OsStr::new("../bin/foo"), &work).expect("resolve ../bin/foo"),
+            bin.join("foo").into_os_string()
+        );
+        assert_eq!(
+            resolve_program(OsStr::new("foo"), &work).expect("resolve foo"),
+            OsString::from("foo")
+        );
+        assert_eq!(
+            resolve_program(OsStr::new("/bin/sh"), &work).expect("resolve /bin/sh"),
+            OsString::from("/bin/sh")
+        );
+    }
+
+    #[test]
+    fn resolve_program_rejects_missing_and_non_executable_files() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(tmpdir.path().join("data"), "").expect("write data");
+
+        assert!(matches!(
+            resolve_program(OsStr::new("./missing"), tmpdir.path()),
+            Err(ProgramError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+        assert!(matches!(
+            resolve_program(OsStr::new("./data"), tmpdir.path()),
+            Err(ProgramError::NotExecutable)
+        ));
+        assert!(matches!(
+            resolve_program(OsStr::new("./"), tmpdir.path()),
+            Err(ProgramError::IsADirectory)
+        ));
+        assert!(matches!(
+            resolve_program(tmpdir.path().join("data").as_os_str(), Path::new("/")),
+            Err(ProgramError::NotExecutable)
+        ));
+    }
+
+    #[test]
+    fn parse_argv0_rejects_nul_bytes() {
+        assert_eq!(parse_argv0("-bash"), Ok("-bash".to_string()));
+        assert_eq!(
+            parse_argv0("a\0b"),
+            Err("argv0 must not contain NUL bytes".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_gid_accepts_numeric_gids() {
+        assert_eq!(parse_gid("0"), Ok(0));
+        assert_eq!(parse_gid("1000"), Ok(1000));
+        assert_eq!(
+            parse_gid("wheel"),
+            Err("gid `wheel` is not a number".to_string())
+        );
+        assert_eq!(parse_gid("-1"), Err("gid `-1` is not a number".to_string()));
+        assert_eq!(
+            parse_gid("4294967295"),
+            Err("gid `4294967295` is not a valid group".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_probe_timeout_needs_whole_milliseconds() {
+        assert_eq!(parse_probe_timeout("250"), Ok(250));
+        assert_eq!(
+            parse_probe_timeout("0"),
+            Err("the --probe-timeout must be at least 1 millisecond".to_string())
+        );
+        assert_eq!(
+            parse_probe_timeout("0.5"),
+            Err("timeout `0.5` is not a whole number of milliseconds".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_validator_timeout_needs_whole_seconds() {
+        assert_eq!(parse_validator_timeout("30"), Ok(30));
+        assert_eq!(
+            parse_validator_timeout("0"),
+            Err("the --policy-validator timeout must be at least 1 second".to_string())
+        );
+        assert_eq!(
+            parse_validator_timeout("1.5"),
+            Err("timeout `1.5` is not a whole number of seconds".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_nice_checks_the_range() {
+        assert_eq!(parse_nice("-20"), Ok(-20));
+        assert_eq!(parse_nice("19"), Ok(19));
+        assert_eq!(
+            parse_nice("20"),
+            Err("nice value `20` is outside -20..=19".to_string())
+        );
+        assert_eq!(
+            parse_nice("low"),
+            Err("nice value `low` is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_umask_accepts_octal() {
+        assert_eq!(parse_umask("022"), Ok(0o022));
+        assert_eq!(parse_umask("0o077"), Ok(0o077));
+        assert_eq!(parse_umask("7"), Ok(0o007));
+        assert_eq!(parse_umask("0777"), Ok(0o777));
+    }
+
+    #[test]
+    fn parse_umask_rejects_invalid_values() {
+        assert_eq!(
+            parse_umask("089"),
+            Err("umask `089` is not an octal number".to_string())
+        );
+        assert_eq!(
+            parse_umask("1000"),
+            Err("umask `1000` is larger than 0777".to_string())
+        );
+        assert_eq!(
+            parse_umask(""),
+            Err("umask `` is not an octal number".to_string())
+        );
+    }
 }
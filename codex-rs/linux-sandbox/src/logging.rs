@@ -0,0 +1,72 @@
+//! The launcher's own diagnostics, logged with `tracing` to stderr so the
+//! command's stdout is left alone.
+
+use tracing::level_filters::LevelFilter;
+
+/// How much the launcher logs, set with `--quiet` and `--verbose`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Verbosity {
+    /// Only errors.
+    Quiet,
+    /// Errors and warnings, such as running without a sandbox.
+    #[default]
+    Default,
+    /// Also how the sandbox was set up: the backend and any restriction the
+    /// host could not enforce.
+    Verbose,
+}
+
+impl Verbosity {
+    pub(crate) fn from_flags(quiet: bool, verbose: bool) -> Self {
+        if quiet {
+            Self::Quiet
+        } else if verbose {
+            Self::Verbose
+        } else {
+            Self::Default
+        }
+    }
+
+    fn level(self) -> LevelFilter {
+        match self {
+            Self::Quiet => LevelFilter::ERROR,
+            Self::Default => LevelFilter::WARN,
+            Self::Verbose => LevelFilter::DEBUG,
+        }
+    }
+}
+
+/// Installs the stderr subscriber. Panics, which report every fatal error,
+/// go through the panic hook and print at every verbosity.
+pub(crate) fn init_logging(verbosity: Verbosity) {
+    // Only fails if a subscriber is already installed, whose settings then
+    // win.
+    let _ = tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(verbosity.level())
+        .with_target(false)
+        .without_time()
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn verbosity_levels() {
+        assert_eq!(
+            Verbosity::from_flags(true, false).level(),
+            LevelFilter::ERROR
+        );
+        assert_eq!(
+            Verbosity::from_flags(false, false).level(),
+            LevelFilter::WARN
+        );
+        assert_eq!(
+            Verbosity::from_flags(false, true).level(),
+            LevelFilter::DEBUG
+        );
+    }
+}
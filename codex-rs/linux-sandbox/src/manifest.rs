@@ -0,0 +1,376 @@
+//! `--manifest`: a JSON description of the run, written before the command
+//! is exec'd, for attaching to bug reports. It is rewritten once the
+//! sandboxed process has run its self-test, and in `--wait` mode once more
+//! when the command has exited.
+//!
+//! Fields:
+//!
+//! - `kernel`: kernel release, as in `uname -r`.
+//! - `landlock_abi`: Landlock ABI version, 0 when unavailable.
+//! - `seccomp`: whether seccomp filters are available.
+//! - `backend`: the mechanism enforcing the policy (`landlock`, `bwrap` or
+//!   `none`).
+//! - `readable_roots`: paths the command may read, besides its writable
+//!   roots.
+//! - `read_deny`: paths passed with `--read-deny`, and under bwrap the
+//!   launcher's own files, hidden despite being readable.
+//! - `writable_roots`: paths the command may write, each with its read-only
+//!   subpaths, after resolving relative roots.
+//! - `private_tmp`: the directory replacing `/tmp`, or `null`.
+//! - `devices`: device nodes passed with `--allow-device`.
+//! - `network`: `full`, `blocked`, `loopback-only` or `udp`.
+//! - `env`: the `allow` and `deny` patterns applied to the environment, or
+//!   `null` when it was passed through.
+//! - `argv`: the argv that is exec'd, including any bwrap or exec wrapper.
+//! - `enforced`: whether the command runs sandboxed, true only when the
+//!   self-test (see [`crate::self_test`]) passed with nothing left
+//!   unenforced; `null` until the sandboxed process has reported.
+//! - `enforcement_reason`: why `enforced` has its value, or `null`.
+//! - `exit`: `null`, or in `--wait` mode the `--report-fd` report once the
+//!   command has exited.
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt;
+use std::fs::File;
+use std::io::Seek as _;
+use std::io::Write as _;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use codex_core::error::Result;
+use codex_core::protocol::SandboxPolicy;
+use codex_core::protocol::WritableRoot;
+
+use crate::backend::HostCapabilities;
+use crate::backend::SelectedBackend;
+use crate::bwrap::MINIMAL_ROOT_DIRS;
+use crate::enforcement::EnforcementVerdict;
+use crate::env_filter::EnvFilter;
+use crate::json::json_string;
+use crate::json::json_string_array;
+use crate::landlock::NetworkMode;
+use crate::landlock::SandboxOptions;
+use crate::wait::ChildReport;
+use crate::writable_roots::writable_roots_with_cwd;
+
+/// Which paths `--print-roots` prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrintRoots {
+    /// Paths the command may write.
+    Writable,
+    /// Paths the command may read, besides the writable ones.
+    Readable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RunManifest {
+    kernel: String,
+    landlock_abi: i32,
+    seccomp: bool,
+    backend: &'static str,
+    readable_roots: Vec<PathBuf>,
+    read_deny: Vec<PathBuf>,
+    writable_roots: Vec<WritableRoot>,
+    private_tmp: Option<PathBuf>,
+    devices: Vec<PathBuf>,
+    network: NetworkMode,
+    env_filter: Option<EnvFilter>,
+    argv: Vec<String>,
+}
+
+impl RunManifest {
+    pub(crate) fn new(
+        host: &HostCapabilities,
+        selected_backend: &SelectedBackend,
+        sandbox_policy: &SandboxPolicy,
+        sandbox_policy_cwd: &Path,
+        options: &SandboxOptions,
+        env_filter: Option<&EnvFilter>,
+        argv: &[CString],
+    ) -> Result<Self> {
+        Ok(Self {
+            kernel: kernel_release(),
+            landlock_abi: host.landlock_abi,
+            seccomp: host.seccomp,
+            backend: selected_backend.name(),
+            // Every policy the launcher enforces allows reading the whole
+            // filesystem, unless bwrap only mounts part of it.
+            readable_roots: match &options.minimal_root {
+                None => vec![PathBuf::from("/")],
+                Some(keep_mounts) => MINIMAL_ROOT_DIRS
+                    .iter()
+                    .map(PathBuf::from)
+                    .filter(|dir| dir.exists())
+                    .chain(keep_mounts.iter().cloned())
+                    .collect(),
+            },
+            read_deny: options.read_deny.clone(),
+            writable_roots: if sandbox_policy.has_full_disk_write_access() {
+                vec![WritableRoot {
+                    root: PathBuf::from("/"),
+                    read_only_subpaths: Vec::new(),
+                }]
+            } else {
+                writable_roots_with_cwd(sandbox_policy, sandbox_policy_cwd)?
+            },
+            private_tmp: options.private_tmp.clone(),
+            devices: options.devices.clone(),
+            network: options.network,
+            env_filter: env_filter.cloned(),
+            argv: argv
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        })
+    }
+
+    /// Single-line JSON object, with `enforced` and `exit` filled in from
+    /// `verdict` and `exit` if given.
+    pub(crate) fn to_json(
+        &self,
+        verdict: Option<&EnforcementVerdict>,
+        exit: Option<&ChildReport>,
+    ) -> String {
+        let path_array =
+            |paths: &[PathBuf]| json_string_array(paths.iter().map(|path| path.to_string_lossy()));
+        let writable_roots = self
+            .writable_roots
+            .iter()
+            .map(|writable_root| {
+                format!(
+                    r#"{{"root":{},"read_only_subpaths":{}}}"#,
+                    json_string(&writable_root.root.to_string_lossy()),
+                    path_array(&writable_root.read_only_subpaths),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let private_tmp = self.private_tmp.as_ref().map_or_else(
+            || "null".to_string(),
+            |private_tmp| json_string(&private_tmp.to_string_lossy()),
+        );
+        let env = self.env_filter.as_ref().map_or_else(
+            || "null".to_string(),
+            |env_filter| {
+                format!(
+                    r#"{{"allow":{},"deny":{}}}"#,
+                    json_string_array(env_filter.allow()),
+                    json_string_array(env_filter.deny()),
+                )
+            },
+        );
+        let (enforced, enforcement_reason) = verdict.map_or_else(
+            || ("null".to_string(), "null".to_string()),
+            |verdict| (verdict.enforced.to_string(), json_string(&verdict.reason)),
+        );
+        let exit = exit.map_or_else(|| "null".to_string(), ChildReport::to_json);
+        format!(
+            r#"{{"kernel":{},"landlock_abi":{},"seccomp":{},"backend":{},"readable_roots":{},"read_deny":{},"writable_roots":[{writable_roots}],"private_tmp":{private_tmp},"devices":{},"network":{},"env":{env},"argv":{},"enforced":{enforced},"enforcement_reason":{enforcement_reason},"exit":{exit}}}"#,
+            json_string(&self.kernel),
+            self.landlock_abi,
+            self.seccomp,
+            json_string(self.backend),
+            path_array(&self.readable_roots),
+            path_array(&self.read_deny),
+            path_array(&self.devices),
+            json_string(self.network.name()),
+            json_string_array(&self.argv),
+        )
+    }
+
+    /// The readable or writable paths, each followed by a NUL byte, for
+    /// `--print-roots`. The writable paths are the roots, the private tmp
+    /// and the devices, in that order. Paths that exist are canonicalized.
+    pub(crate) fn roots_nul_separated(&self, roots: PrintRoots) -> Vec<u8> {
+        let paths: Vec<&Path> = match roots {
+            PrintRoots::Readable => self.readable_roots.iter().map(PathBuf::as_path).collect(),
+            PrintRoots::Writable => self
+                .writable_roots
+                .iter()
+                .map(|writable_root| writable_root.root.as_path())
+                .chain(self.private_tmp.as_deref())
+                .chain(self.devices.iter().map(PathBuf::as_path))
+                .collect(),
+        };
+        let mut output = Vec::new();
+        for path in paths {
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            output.extend_from_slice(path.as_os_str().as_bytes());
+            output.push(0);
+        }
+        output
+    }
+
+    /// Replaces the contents of `file`.
+    pub(crate) fn write(
+        &self,
+        file: &mut ManifestFile,
+        verdict: Option<&EnforcementVerdict>,
+        exit: Option<&ChildReport>,
+    ) -> std::io::Result<()> {
+        file.file.set_len(0)?;
+        file.file.rewind()?;
+        file.file
+            .write_all(format!("{}\n", self.to_json(verdict, exit)).as_bytes())
+    }
+}
+
+/// The `--manifest` file, opened once before the sandbox is installed: the
+/// sandboxed process may no longer be allowed to open it, but can still
+/// write through the open file.
+#[derive(Debug)]
+pub(crate) struct ManifestFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl ManifestFile {
+    pub(crate) fn create(path: PathBuf) -> std::io::Result<Self> {
+        let file = File::create(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl fmt::Display for ManifestFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())
+    }
+}
+
+fn kernel_release() -> String {
+    let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } != 0 {
+        return "unknown".to_string();
+    }
+    unsafe { CStr::from_ptr(uts.release.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::landlock::DEFAULT_MAX_ROOTS;
+    use crate::wait::ChildRusage;
+    use pretty_assertions::assert_eq;
+
+    fn manifest() -> RunManifest {
+        RunManifest {
+            kernel: "6.8.0".to_string(),
+            landlock_abi: 5,
+            seccomp: true,
+            backend: "landlock",
+            readable_roots: vec![PathBuf::from("/")],
+            read_deny: vec![PathBuf::from("/etc/shadow")],
+            writable_roots: vec![WritableRoot {
+                root: PathBuf::from("/work"),
+                read_only_subpaths: vec![PathBuf::from("/work/.git")],
+            }],
+            private_tmp: Some(PathBuf::from("/tmp/codex-tmp-1")),
+            devices: vec![PathBuf::from("/dev/kvm")],
+            network: NetworkMode::Blocked,
+            env_filter: EnvFilter::new(vec!["PATH".to_string()], Vec::new(), false),
+            argv: vec!["echo".to_string(), "hi".to_string()],
+        }
+    }
+
+    #[test]
+    fn roots_are_printed_nul_terminated() {
+        let manifest = manifest();
+
+        assert_eq!(
+            manifest.roots_nul_separated(PrintRoots::Writable),
+            b"/work\0/tmp/codex-tmp-1\0/dev/kvm\0"
+        );
+        assert_eq!(manifest.roots_nul_separated(PrintRoots::Readable), b"/\0");
+    }
+
+    #[test]
+    fn manifest_has_every_documented_field() {
+        assert_eq!(
+            manifest().to_json(None, None),
+            r#"{"kernel":"6.8.0","landlock_abi":5,"seccomp":true,"backend":"landlock","readable_roots":["/"],"read_deny":["/etc/shadow"],"writable_roots":[{"root":"/work","read_only_subpaths":["/work/.git"]}],"private_tmp":"/tmp/codex-tmp-1","devices":["/dev/kvm"],"network":"blocked","env":{"allow":["PATH"],"deny":[]},"argv":["echo","hi"],"enforced":null,"enforcement_reason":null,"exit":null}"#
+        );
+    }
+
+    #[test]
+    fn wait_mode_appends_the_exit_report() {
+        let report = ChildReport {
+            exit_code: Some(0),
+            signal: None,
+            rusage: ChildRusage::default(),
+            memory_peak: None,
+            enforcement: EnforcementVerdict::unsandboxed(),
+        };
+
+        assert!(
+            manifest()
+                .to_json(Some(&report.enforcement), Some(&report))
+                .ends_with(
+                    r#""enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox","exit":{"exit_code":0,"signal":null,"rusage":{"utime":0,"stime":0,"maxrss":0,"minflt":0,"majflt":0},"enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox"}}"#
+                )
+        );
+    }
+
+    #[test]
+    fn rewrites_replace_the_previous_contents() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let path = tmpdir.path().join("manifest.json");
+        let mut file = ManifestFile::create(path.clone()).expect("create manifest");
+        let verdict = EnforcementVerdict::unverified_bwrap();
+
+        manifest()
+            .write(&mut file, None, None)
+            .expect("write manifest");
+        manifest()
+            .write(&mut file, Some(&verdict), None)
+            .expect("rewrite manifest");
+
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read manifest"),
+            format!("{}\n", manifest().to_json(Some(&verdict), None))
+        );
+    }
+
+    #[test]
+    fn full_access_writes_everywhere() {
+        let manifest = RunManifest::new(
+            &HostCapabilities {
+                seccomp: true,
+                landlock_abi: 5,
+                bwrap: None,
+                user_namespaces: true,
+                proc_mounted: true,
+            },
+            &SelectedBackend::Landlock,
+            &SandboxPolicy::DangerFullAccess,
+            Path::new("/work"),
+            &SandboxOptions {
+                network: NetworkMode::Full,
+                private_tmp: None,
+                devices: Vec::new(),
+                minimal_root: None,
+                read_deny: Vec::new(),
+                max_roots: DEFAULT_MAX_ROOTS,
+                restrict_ioctls: false,
+                seccomp_tsync: false,
+            },
+            None,
+            &[CString::from(c"true")],
+        )
+        .expect("build manifest");
+
+        assert_eq!(
+            manifest.writable_roots,
+            vec![WritableRoot {
+                root: PathBuf::from("/"),
+                read_only_subpaths: Vec::new(),
+            }]
+        );
+        assert_eq!(manifest.env_filter, None);
+        assert_eq!(manifest.argv, vec!["true".to_string()]);
+    }
+}
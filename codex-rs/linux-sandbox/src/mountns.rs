@@ -0,0 +1,74 @@
+//! Private mount namespace that bwrap is started in, so mounts the host adds
+//! under a writable root after the sandbox is set up stay out of it.
+//!
+//! bwrap makes the mounts it is started with recursive slaves of the ones
+//! they were copied from, and has no option to change that. Entering a mount
+//! namespace of our own whose mounts are all private first leaves bwrap
+//! nothing to be a slave of.
+
+use crate::procfs::write_proc_file;
+
+/// Moves the current process into a fresh mount namespace and makes every
+/// mount in it private. Without the privileges to create a mount namespace
+/// directly, a user namespace that maps only our own uid/gid is created along
+/// with it. Returns `Ok(false)`, having changed nothing, when that does not
+/// work on this host; an error means the process may be left in a
+/// half-set-up namespace and must not go on.
+pub(crate) fn enter_private_mount_namespace() -> std::io::Result<bool> {
+    // There is no way back out of a user namespace, so the steps are tried
+    // in a throwaway child first. Hosts may allow creating one but then deny
+    // the mount in it, as AppArmor does for unconfined programs on Ubuntu.
+    match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()),
+        0 => {
+            let status = if unshare_private_mount_namespace().is_ok() {
+                0
+            } else {
+                1
+            };
+            unsafe { libc::_exit(status) };
+        }
+        pid => {
+            let mut status: libc::c_int = 0;
+            if unsafe { libc::waitpid(pid, &mut status, 0) } != pid {
+                return Err(std::io::Error::last_os_error());
+            }
+            if !libc::WIFEXITED(status) || libc::WEXITSTATUS(status) != 0 {
+                return Ok(false);
+            }
+        }
+    }
+    unshare_private_mount_namespace()?;
+    Ok(true)
+}
+
+fn unshare_private_mount_namespace() -> std::io::Result<()> {
+    let uid = unsafe { libc::geteuid() };
+    let gid = unsafe { libc::getegid() };
+
+    if unsafe { libc::unshare(libc::CLONE_NEWNS) } != 0 {
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // `setgroups` must be denied before an unprivileged process may write
+        // its gid_map.
+        write_proc_file("/proc/self/setgroups", "deny")?;
+        write_proc_file("/proc/self/uid_map", &format!("{uid} {uid} 1"))?;
+        write_proc_file("/proc/self/gid_map", &format!("{gid} {gid} 1"))?;
+    }
+
+    let root = c"/";
+    if unsafe {
+        libc::mount(
+            std::ptr::null(),
+            root.as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    } != 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Private network namespace whose only interface is loopback, used to give
+//! the command loopback networking without any route to the outside.
+
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+
+use codex_core::error::CodexErr;
+use codex_core::error::Result;
+use codex_core::error::SandboxErr;
+
+use crate::procfs::write_proc_file;
+
+/// Moves the current process into a fresh network namespace and brings up
+/// its `lo` interface. Without the privileges to create a network namespace
+/// directly, a user namespace that maps only our own uid/gid is created along
+/// with it.
+pub(crate) fn enter_loopback_only_network_namespace() -> Result<()> {
+    let uid = unsafe { libc::geteuid() };
+    let gid = unsafe { libc::getegid() };
+
+    if unsafe { libc::unshare(libc::CLONE_NEWNET) } != 0 {
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+            let err = std::io::Error::last_os_error();
+            return Err(network_namespace_error(format!(
+                "unshare failed: {err}; unprivileged user namespaces may be disabled on this host"
+            )));
+        }
+        // `setgroups` must be denied before an unprivileged process may write
+        // its gid_map.
+        write_proc_self("setgroups", "deny")?;
+        write_proc_self("uid_map", &format!("{uid} {uid} 1"))?;
+        write_proc_self("gid_map", &format!("{gid} {gid} 1"))?;
+    }
+
+    bring_up_loopback()
+        .map_err(|err| network_namespace_error(format!("could not bring up lo: {err}")))
+}
+
+fn write_proc_self(file: &str, contents: &str) -> Result<()> {
+    let path = format!("/proc/self/{file}");
+    write_proc_file(&path, contents)
+        .map_err(|err| network_namespace_error(format!("writing {path}: {err}")))
+}
+
+/// A new network namespace starts with `lo` down.
+fn bring_up_loopback() -> std::io::Result<()> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut ifreq: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifreq.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut ifreq) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    unsafe {
+        ifreq.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+    }
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS as _, &ifreq) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn network_namespace_error(reason: String) -> CodexErr {
+    CodexErr::Sandbox(SandboxErr::NetworkNamespace(reason))
+}
@@ -0,0 +1,163 @@
+//! `--policy-validator`: an external program every launch's policy goes
+//! through before it is enforced, for central checks or mandatory changes.
+//!
+//! The validator gets the resolved policy (after `--writable-root`s are
+//! added) as JSON on stdin, and prints the policy to enforce, changed or
+//! not, as JSON on stdout. The launch fails closed: a validator that cannot
+//! be started, exits non-zero, outlives `--policy-validator-timeout` or
+//! prints anything but a policy stops the launch before anything runs.
+//!
+//! The validator runs outside the sandbox with the launcher's privileges
+//! and environment, and may loosen the policy as freely as tighten it, so it
+//! must be as trusted as the launcher itself.
+
+use std::io::Read as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::Child;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::time::Instant;
+
+use codex_core::protocol::SandboxPolicy;
+
+/// Default for `--policy-validator-timeout`, in seconds.
+pub(crate) const DEFAULT_VALIDATOR_TIMEOUT_SECS: u64 = 10;
+
+/// How often a running validator is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs `validator` on `sandbox_policy` and returns the policy it printed.
+pub(crate) fn validate_policy(
+    validator: &Path,
+    sandbox_policy: &SandboxPolicy,
+    timeout: Duration,
+) -> Result<SandboxPolicy, String> {
+    let name = validator.display();
+    let input = serde_json::to_string(sandbox_policy).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    let mut child = Command::new(validator)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not start {name}: {e}"))?;
+
+    // Read on another thread, so a validator that prints before reading all
+    // of its input cannot deadlock against the launcher.
+    let (sender, receiver) = mpsc::channel();
+    if let Some(mut stdout) = child.stdout.take() {
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = sender.send(stdout.read_to_string(&mut output).map(|_| output));
+        });
+    }
+    if let Some(mut stdin) = child.stdin.take() {
+        // A validator may exit without reading its input; its exit status
+        // decides.
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let status = wait_until(&mut child, deadline)
+        .map_err(|e| format!("error waiting for {name}: {e}"))?
+        .ok_or_else(|| format!("{name} did not finish within {}s", timeout.as_secs_f64()))?;
+    if !status.success() {
+        return Err(format!("{name} rejected the policy ({status})"));
+    }
+    // Bounded too: a process the validator left behind may hold its stdout.
+    let output = receiver
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|_| format!("{name} did not close its stdout within the timeout"))?
+        .map_err(|e| format!("error reading the output of {name}: {e}"))?;
+    output
+        .trim()
+        .parse()
+        .map_err(|e| format!("{name} printed an invalid policy: {e}"))
+}
+
+/// The child's status, or `None` once `deadline` passed, in which case it is
+/// killed.
+fn wait_until(child: &mut Child, deadline: Instant) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    fn validator(dir: &Path, script: &str) -> PathBuf {
+        let path = dir.join("validator");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).expect("write validator");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("make validator executable");
+        path
+    }
+
+    #[test]
+    fn validator_output_replaces_the_policy() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let validator = validator(
+            tmpdir.path(),
+            r#"cat > /dev/null; echo '{"mode":"read-only"}'"#,
+        );
+
+        assert_eq!(
+            validate_policy(
+                &validator,
+                &SandboxPolicy::DangerFullAccess,
+                Duration::from_secs(10)
+            ),
+            Ok(SandboxPolicy::ReadOnly)
+        );
+    }
+
+    #[test]
+    fn failing_validators_reject_the_launch() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let rejecting = validator(tmpdir.path(), "exit 3");
+        let name = rejecting.display().to_string();
+
+        assert_eq!(
+            validate_policy(
+                &rejecting,
+                &SandboxPolicy::ReadOnly,
+                Duration::from_secs(10)
+            ),
+            Err(format!("{name} rejected the policy (exit status: 3)"))
+        );
+
+        let silent = validator(tmpdir.path(), "cat > /dev/null");
+        let err = validate_policy(&silent, &SandboxPolicy::ReadOnly, Duration::from_secs(10))
+            .expect_err("no policy was printed");
+        assert!(
+            err.starts_with(&format!("{name} printed an invalid policy: ")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn slow_validators_are_killed() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let slow = validator(tmpdir.path(), "exec sleep 10");
+
+        assert_eq!(
+            validate_policy(&slow, &SandboxPolicy::ReadOnly, Duration::from_millis(100)),
+            Err(format!("{} did not finish within 0.1s", slow.display()))
+        );
+    }
+}
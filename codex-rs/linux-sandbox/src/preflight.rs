@@ -0,0 +1,475 @@
+//! `codex-linux-sandbox preflight`: reports whether this host can enforce a
+//! policy, without running anything under it.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+
+use clap::Parser;
+use codex_core::protocol::SandboxPolicy;
+
+use crate::backend::Backend;
+use crate::backend::HostCapabilities;
+use crate::backend::SelectedBackend;
+use crate::backend::bwrap_is_setuid;
+use crate::backend::select_backend;
+use crate::enforcement::EnforcementMode;
+use crate::json::json_string;
+use crate::landlock::LANDLOCK_FULL_FILESYSTEM_ABI;
+use crate::landlock::LANDLOCK_NETWORK_ABI;
+
+#[derive(Debug, Parser)]
+#[command(name = "codex-linux-sandbox preflight")]
+pub struct PreflightCommand {
+    #[arg(long = "sandbox-policy")]
+    pub sandbox_policy: SandboxPolicy,
+
+    /// Mechanism that would be used to enforce the policy.
+    #[arg(long = "backend", value_enum, default_value_t = Backend::Auto)]
+    pub backend: Backend,
+
+    /// Check as if the command were run with this `--enforcement` mode. In
+    /// `strict` mode, restrictions that would be skipped count as failures.
+    #[arg(long = "enforcement", value_enum, default_value_t = EnforcementMode::BestEffort)]
+    pub enforcement: EnforcementMode,
+
+    /// Shorthand for `--enforcement strict`, which it overrides.
+    #[arg(long = "require-enforcement")]
+    pub require_enforcement: bool,
+
+    /// Print the report as a single JSON object.
+    #[arg(long = "json")]
+    pub json: bool,
+}
+
+/// Parses `args` (program name first, without the `preflight` subcommand),
+/// prints the report and exits with 0 when the policy is enforceable and 1
+/// otherwise.
+pub(crate) fn run_preflight_main(args: impl IntoIterator<Item = OsString>) -> ! {
+    let PreflightCommand {
+        sandbox_policy,
+        backend,
+        enforcement,
+        require_enforcement,
+        json,
+    } = PreflightCommand::parse_from(args);
+
+    let report = preflight(
+        &sandbox_policy,
+        backend,
+        EnforcementMode::from_flags(enforcement, require_enforcement),
+        &HostCapabilities::detect(),
+    );
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.render());
+    }
+    std::process::exit(if report.enforceable() { 0 } else { 1 });
+}
+
+/// One requirement of the policy and whether the host meets it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Check {
+    pub(crate) name: &'static str,
+    /// Whether the policy cannot be enforced when this check fails. Other
+    /// checks only describe restrictions that would be skipped.
+    pub(crate) required: bool,
+    pub(crate) passed: bool,
+    pub(crate) detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, required: bool, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            required,
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PreflightReport {
+    /// Mechanism that would enforce the policy, `None` when none can.
+    pub(crate) backend: Option<SelectedBackend>,
+    pub(crate) checks: Vec<Check>,
+}
+
+/// Evaluates `sandbox_policy` against `host` for `backend`, using the same
+/// backend selection as a real run.
+pub(crate) fn preflight(
+    sandbox_policy: &SandboxPolicy,
+    backend: Backend,
+    enforcement: EnforcementMode,
+    host: &HostCapabilities,
+) -> PreflightReport {
+    let strict = enforcement == EnforcementMode::Strict;
+    let restrict_writes = !sandbox_policy.has_full_disk_write_access();
+    let restrict_network = !sandbox_policy.has_full_network_access();
+    let abi = host.landlock_abi;
+    let mut checks = Vec::new();
+
+    let selected = match select_backend(backend, sandbox_policy, host, enforcement) {
+        Ok(selected) => selected,
+        Err(e) => {
+            checks.push(Check::new("backend", true, false, e.to_string()));
+            return PreflightReport {
+                backend: None,
+                checks,
+            };
+        }
+    };
+
+    match &selected {
+        SelectedBackend::Landlock => {
+            if restrict_writes {
+                checks.push(if abi > 0 {
+                    Check::new("landlock", true, true, format!("Landlock ABI {abi}"))
+                } else {
+                    Check::new("landlock", true, false, "not supported by this kernel")
+                });
+                checks.push(Check::new(
+                    "landlock-filesystem-rights",
+                    strict,
+                    abi >= LANDLOCK_FULL_FILESYSTEM_ABI,
+                    format!("ABI {LANDLOCK_FULL_FILESYSTEM_ABI} restricts every filesystem right"),
+                ));
+            }
+            if restrict_network {
+                checks.push(Check::new(
+                    "seccomp",
+                    true,
+                    host.seccomp,
+                    "blocks network syscalls",
+                ));
+                // Landlock network rules are only part of a ruleset that also
+                // restricts writes.
+                if restrict_writes {
+                    checks.push(Check::new(
+                        "landlock-network",
+                        strict,
+                        abi >= LANDLOCK_NETWORK_ABI,
+                        format!("ABI {LANDLOCK_NETWORK_ABI} also blocks TCP bind/connect"),
+                    ));
+                }
+            }
+        }
+        SelectedBackend::Bwrap { bwrap } => {
+            if restrict_network {
+                checks.push(Check::new(
+                    "seccomp",
+                    false,
+                    host.seccomp,
+                    "not available, falling back to bwrap",
+                ));
+            }
+            checks.push(Check::new("bwrap", true, true, bwrap.display().to_string()));
+            let setuid = bwrap_is_setuid(bwrap);
+            checks.push(if !host.proc_mounted {
+                Check::new(
+                    "user-namespaces",
+                    false,
+                    false,
+                    "/proc is not mounted, so this could not be checked",
+                )
+            } else if setuid {
+                Check::new(
+                    "user-namespaces",
+                    false,
+                    host.user_namespaces,
+                    "bwrap is setuid",
+                )
+            } else {
+                Check::new(
+                    "user-namespaces",
+                    true,
+                    host.user_namespaces,
+                    "needed by bwrap to set up its mounts",
+                )
+            });
+        }
+        SelectedBackend::None => {
+            checks.push(Check::new(
+                "sandbox",
+                true,
+                !restrict_writes && !restrict_network,
+                "--backend none does not enforce the policy",
+            ));
+        }
+    }
+
+    PreflightReport {
+        backend: Some(selected),
+        checks,
+    }
+}
+
+impl PreflightReport {
+    pub(crate) fn enforceable(&self) -> bool {
+        self.backend.is_some()
+            && self
+                .checks
+                .iter()
+                .all(|check| check.passed || !check.required)
+    }
+
+    fn backend_name(&self) -> Option<&'static str> {
+        self.backend.as_ref().map(SelectedBackend::name)
+    }
+
+    /// One line per check, then the verdict.
+    pub(crate) fn render(&self) -> String {
+        let mut out = String::new();
+        for check in &self.checks {
+            let status = match (check.passed, check.required) {
+                (true, _) => "pass",
+                (false, true) => "fail",
+                (false, false) => "warn",
+            };
+            let _ = writeln!(out, "{status} {}: {}", check.name, check.detail);
+        }
+        match (self.enforceable(), self.backend_name()) {
+            (true, Some(backend)) => {
+                let _ = writeln!(out, "verdict: enforceable with the {backend} backend");
+            }
+            _ => out.push_str("verdict: not enforceable\n"),
+        }
+        out
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let checks = self
+            .checks
+            .iter()
+            .map(|check| {
+                format!(
+                    r#"{{"name":{},"required":{},"passed":{},"detail":{}}}"#,
+                    json_string(check.name),
+                    check.required,
+                    check.passed,
+                    json_string(&check.detail),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let backend = self
+            .backend_name()
+            .map_or_else(|| "null".to_string(), json_string);
+        format!(
+            r#"{{"enforceable":{},"backend":{backend},"checks":[{checks}]}}"#,
+            self.enforceable(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn host(seccomp: bool, landlock_abi: i32, bwrap: Option<&str>) -> HostCapabilities {
+        HostCapabilities {
+            seccomp,
+            landlock_abi,
+            bwrap: bwrap.map(PathBuf::from),
+            user_namespaces: true,
+            proc_mounted: true,
+        }
+    }
+
+    fn workspace_write() -> SandboxPolicy {
+        SandboxPolicy::new_workspace_write_policy()
+    }
+
+    #[test]
+    fn read_only_is_enforceable_on_a_modern_kernel() {
+        let report = preflight(
+            &SandboxPolicy::ReadOnly,
+            Backend::Auto,
+            EnforcementMode::BestEffort,
+            &host(true, 5, None),
+        );
+
+        assert_eq!(
+            report,
+            PreflightReport {
+                backend: Some(SelectedBackend::Landlock),
+                checks: vec![
+                    Check::new("landlock", true, true, "Landlock ABI 5"),
+                    Check::new(
+                        "landlock-filesystem-rights",
+                        false,
+                        true,
+                        "ABI 5 restricts every filesystem right"
+                    ),
+                    Check::new("seccomp", true, true, "blocks network syscalls"),
+                    Check::new(
+                        "landlock-network",
+                        false,
+                        true,
+                        "ABI 4 also blocks TCP bind/connect"
+                    ),
+                ],
+            }
+        );
+        assert!(report.enforceable());
+        assert_eq!(
+            report.render(),
+            "pass landlock: Landlock ABI 5\n\
+             pass landlock-filesystem-rights: ABI 5 restricts every filesystem right\n\
+             pass seccomp: blocks network syscalls\n\
+             pass landlock-network: ABI 4 also blocks TCP bind/connect\n\
+             verdict: enforceable with the landlock backend\n"
+        );
+    }
+
+    #[test]
+    fn old_landlock_degrades_unless_enforcement_is_required() {
+        let host = host(true, 3, None);
+
+        let report = preflight(
+            &workspace_write(),
+            Backend::Auto,
+            EnforcementMode::BestEffort,
+            &host,
+        );
+        assert!(report.enforceable());
+        assert!(report.render().contains("warn landlock-network"));
+
+        let report = preflight(
+            &workspace_write(),
+            Backend::Auto,
+            EnforcementMode::Strict,
+            &host,
+        );
+        assert!(!report.enforceable());
+        assert!(report.render().contains("fail landlock-network"));
+    }
+
+    #[test]
+    fn writes_cannot_be_restricted_without_landlock() {
+        let report = preflight(
+            &workspace_write(),
+            Backend::Auto,
+            EnforcementMode::BestEffort,
+            &host(true, 0, None),
+        );
+
+        assert!(!report.enforceable());
+        assert_eq!(
+            report.checks[0],
+            Check::new("landlock", true, false, "not supported by this kernel")
+        );
+    }
+
+    #[test]
+    fn missing_seccomp_falls_back_to_bwrap() {
+        let report = preflight(
+            &SandboxPolicy::ReadOnly,
+            Backend::Auto,
+            EnforcementMode::BestEffort,
+            &host(false, 5, Some("/usr/bin/bwrap")),
+        );
+
+        assert_eq!(
+            report,
+            PreflightReport {
+                backend: Some(SelectedBackend::Bwrap {
+                    bwrap: PathBuf::from("/usr/bin/bwrap"),
+                }),
+                checks: vec![
+                    Check::new(
+                        "seccomp",
+                        false,
+                        false,
+                        "not available, falling back to bwrap"
+                    ),
+                    Check::new("bwrap", true, true, "/usr/bin/bwrap"),
+                    Check::new(
+                        "user-namespaces",
+                        true,
+                        true,
+                        "needed by bwrap to set up its mounts"
+                    ),
+                ],
+            }
+        );
+        assert!(report.enforceable());
+    }
+
+    #[test]
+    fn bwrap_without_user_namespaces_is_not_enforceable() {
+        let host = HostCapabilities {
+            user_namespaces: false,
+            ..host(false, 5, Some("/nonexistent/bwrap"))
+        };
+
+        let report = preflight(
+            &SandboxPolicy::ReadOnly,
+            Backend::Bwrap,
+            EnforcementMode::BestEffort,
+            &host,
+        );
+        assert!(!report.enforceable());
+    }
+
+    #[test]
+    fn user_namespaces_are_a_warning_without_proc() {
+        let host = HostCapabilities {
+            proc_mounted: false,
+            ..host(true, 5, Some("/nonexistent/bwrap"))
+        };
+
+        let report = preflight(
+            &SandboxPolicy::ReadOnly,
+            Backend::Bwrap,
+            EnforcementMode::BestEffort,
+            &host,
+        );
+        assert_eq!(
+            report.checks.last(),
+            Some(&Check::new(
+                "user-namespaces",
+                false,
+                false,
+                "/proc is not mounted, so this could not be checked",
+            ))
+        );
+        assert!(report.enforceable());
+    }
+
+    #[test]
+    fn no_mechanism_at_all_is_not_enforceable() {
+        let report = preflight(
+            &SandboxPolicy::ReadOnly,
+            Backend::Auto,
+            EnforcementMode::BestEffort,
+            &host(false, 5, None),
+        );
+
+        assert_eq!(report.backend, None);
+        assert!(!report.enforceable());
+        assert_eq!(
+            report.to_json(),
+            r#"{"enforceable":false,"backend":null,"checks":[{"name":"backend","required":true,"passed":false,"detail":"sandbox error: seccomp is not available, so network access cannot be blocked: bwrap was not found on PATH to isolate the network instead"}]}"#
+        );
+    }
+
+    #[test]
+    fn full_access_needs_nothing() {
+        let report = preflight(
+            &SandboxPolicy::DangerFullAccess,
+            Backend::Auto,
+            EnforcementMode::Strict,
+            &host(false, 0, None),
+        );
+
+        assert!(report.enforceable());
+        assert_eq!(
+            report.to_json(),
+            r#"{"enforceable":true,"backend":"landlock","checks":[]}"#
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! `--nice` and `--sched`: the scheduling priority the command runs with, so
+//! background tasks can yield the CPU to interactive work.
+//!
+//! Both are inherited across exec, including by the command bwrap runs.
+//! Lowering the priority needs no privileges; raising it above the current
+//! one needs `CAP_SYS_NICE` or a high enough `RLIMIT_NICE`.
+
+use std::io::Error;
+use std::io::ErrorKind;
+
+use clap::ValueEnum;
+
+/// Lowest and highest niceness Linux accepts.
+pub(crate) const NICE_RANGE: std::ops::RangeInclusive<i32> = -20..=19;
+
+/// Scheduling policy for the command, set with `--sched`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchedPolicy {
+    /// `SCHED_OTHER`, the default time-sharing policy.
+    Normal,
+    /// `SCHED_BATCH`: CPU-bound work that gives up some interactivity for
+    /// throughput.
+    Batch,
+    /// `SCHED_IDLE`: only runs when nothing else wants the CPU.
+    Idle,
+}
+
+impl SchedPolicy {
+    fn policy(self) -> libc::c_int {
+        match self {
+            SchedPolicy::Normal => libc::SCHED_OTHER,
+            SchedPolicy::Batch => libc::SCHED_BATCH,
+            SchedPolicy::Idle => libc::SCHED_IDLE,
+        }
+    }
+}
+
+/// The calling thread's niceness.
+pub(crate) fn current_niceness() -> std::io::Result<i32> {
+    // -1 is a valid niceness, so errors are told apart through errno.
+    unsafe {
+        *libc::__errno_location() = 0;
+    }
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+    let err = Error::last_os_error();
+    if nice == -1 && err.raw_os_error() != Some(0) {
+        return Err(err);
+    }
+    Ok(nice)
+}
+
+/// Sets the niceness of the calling thread, which the exec'd command
+/// inherits. Fails with an error saying what is missing rather than a bare
+/// `EACCES` when `nice` is a higher priority than the current one.
+pub(crate) fn set_niceness(nice: i32) -> std::io::Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+        let err = Error::last_os_error();
+        if matches!(err.raw_os_error(), Some(libc::EACCES | libc::EPERM)) {
+            let current = current_niceness()?;
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "raising the priority from nice {current} to {nice} needs CAP_SYS_NICE or a \
+                     higher RLIMIT_NICE; without them, only {current} to 19 can be requested"
+                ),
+            ));
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Sets the scheduling policy of the calling thread, keeping its niceness.
+pub(crate) fn set_sched_policy(sched: SchedPolicy) -> std::io::Result<()> {
+    // The static priority must be 0 for every non-realtime policy.
+    let param = libc::sched_param { sched_priority: 0 };
+    if unsafe { libc::sched_setscheduler(0, sched.policy(), &param) } != 0 {
+        let err = Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EPERM) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "leaving SCHED_IDLE needs CAP_SYS_NICE or a high enough RLIMIT_NICE",
+            ));
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn lowering_the_priority_needs_no_privileges() {
+        // Niceness is per thread, so this leaves the other tests alone.
+        let (lower, after, policy) = std::thread::spawn(|| {
+            let current = current_niceness().expect("current niceness");
+            let lower = (current + 1).min(*NICE_RANGE.end());
+            set_niceness(lower).expect("lower the priority");
+            set_sched_policy(SchedPolicy::Batch).expect("switch to SCHED_BATCH");
+            (
+                lower,
+                current_niceness().expect("current niceness"),
+                unsafe { libc::sched_getscheduler(0) },
+            )
+        })
+        .join()
+        .expect("priority thread panicked");
+
+        assert_eq!(after, lower);
+        assert_eq!(policy, libc::SCHED_BATCH);
+    }
+}
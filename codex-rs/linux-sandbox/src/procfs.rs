@@ -0,0 +1,63 @@
+//! Access to `/proc`, which minimal containers do not always mount.
+//!
+//! Features that read or write `/proc` go through here, so that without it
+//! they fail with an error that names `/proc` (or, where a safe default
+//! exists, fall back to it) instead of surfacing a bare `ENOENT`.
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::LazyLock;
+
+const PROC: &str = "/proc";
+
+static PROC_MOUNTED: LazyLock<bool> = LazyLock::new(|| proc_mounted_at(Path::new(PROC)));
+
+/// Whether procfs is mounted on `/proc`. Probed once per process.
+pub(crate) fn proc_mounted() -> bool {
+    *PROC_MOUNTED
+}
+
+/// An empty directory, or another filesystem mounted over `/proc`, has no
+/// `self/stat`.
+fn proc_mounted_at(root: &Path) -> bool {
+    root.join("self/stat").is_file()
+}
+
+/// Reads `path`, a file under `/proc`.
+pub(crate) fn read_proc_file(path: &str) -> std::io::Result<String> {
+    ensure_proc_mounted(path)?;
+    std::fs::read_to_string(path)
+}
+
+/// Writes `contents` to `path`, a file under `/proc`.
+pub(crate) fn write_proc_file(path: &str, contents: &str) -> std::io::Result<()> {
+    ensure_proc_mounted(path)?;
+    std::fs::write(path, contents)
+}
+
+fn ensure_proc_mounted(path: &str) -> std::io::Result<()> {
+    if proc_mounted() {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            ErrorKind::NotFound,
+            format!("{path} is unavailable because {PROC} is not mounted"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_whether_procfs_is_mounted() {
+        assert!(proc_mounted_at(Path::new(PROC)));
+
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        assert!(!proc_mounted_at(tmpdir.path()));
+        // Not fooled by a directory that merely has the same layout.
+        std::fs::create_dir_all(tmpdir.path().join("self/stat")).expect("create dirs");
+        assert!(!proc_mounted_at(tmpdir.path()));
+    }
+}
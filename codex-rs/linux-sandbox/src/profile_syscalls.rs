@@ -0,0 +1,257 @@
+//! `codex-linux-sandbox profile-syscalls`: runs a command under `ptrace` and
+//! lists the syscalls it and its descendants made, in the
+//! `--syscall-allowlist` file format, to seed an allowlist.
+//!
+//! The command runs without a sandbox. A single run only sees the code paths
+//! it took, so a list seeded this way usually needs the syscalls of error
+//! paths and other inputs added by hand.
+
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::syscalls::syscall_name;
+
+#[derive(Debug, Parser)]
+#[command(name = "codex-linux-sandbox profile-syscalls")]
+pub struct ProfileSyscallsCommand {
+    /// Write the list to this file instead of stdout, where it would follow
+    /// the command's own output.
+    #[arg(long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Command to trace.
+    #[arg(trailing_var_arg = true, required = true)]
+    pub command: Vec<OsString>,
+}
+
+/// Parses `args` (program name first, without the `profile-syscalls`
+/// subcommand), traces the command, writes the list and exits with the
+/// command's status.
+pub(crate) fn run_profile_syscalls_main(args: impl IntoIterator<Item = OsString>) -> ! {
+    let ProfileSyscallsCommand { output, command } = ProfileSyscallsCommand::parse_from(args);
+
+    let argv = match command
+        .iter()
+        .map(|arg| CString::new(arg.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(argv) => argv,
+        Err(e) => panic!("error tracing {}: {e}", command[0].to_string_lossy()),
+    };
+    let (syscalls, exit_code) = match trace_syscalls(&argv) {
+        Ok(traced) => traced,
+        Err(e) => panic!("error tracing {}: {e}", command[0].to_string_lossy()),
+    };
+
+    let list = render_syscall_list(&syscalls);
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, list) {
+                panic!("error writing {}: {e}", path.display());
+            }
+        }
+        None => print!("{list}"),
+    }
+    std::process::exit(exit_code);
+}
+
+/// One syscall per line, named ones sorted by name and then the ones missing
+/// from the name table by number.
+pub(crate) fn render_syscall_list(syscalls: &BTreeSet<i64>) -> String {
+    let mut names: Vec<&str> = syscalls.iter().filter_map(|nr| syscall_name(*nr)).collect();
+    names.sort_unstable();
+    let mut list: String = names.into_iter().map(|name| format!("{name}\n")).collect();
+    for nr in syscalls.iter().filter(|nr| syscall_name(**nr).is_none()) {
+        list.push_str(&format!("{nr}  # not in the name table\n"));
+    }
+    list
+}
+
+/// For the unused `addr` and `data` arguments of `ptrace`, which are
+/// pointer-sized.
+const NULL: *mut libc::c_void = std::ptr::null_mut();
+
+const PTRACE_GET_SYSCALL_INFO: libc::c_uint = 0x420e;
+const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+
+/// The prefix of `struct ptrace_syscall_info` up to the entry's arguments.
+#[repr(C)]
+#[derive(Default)]
+struct SyscallInfo {
+    op: u8,
+    _pad: [u8; 3],
+    _arch: u32,
+    _instruction_pointer: u64,
+    _stack_pointer: u64,
+    nr: u64,
+    _args: [u64; 6],
+}
+
+/// Runs `argv` as a traced child, following its forks and clones, and
+/// returns the syscall numbers entered by any of them together with the exit
+/// code for the launcher.
+///
+/// Waits for any child, so the caller must have no other children.
+fn trace_syscalls(argv: &[CString]) -> std::io::Result<(BTreeSet<i64>, i32)> {
+    let mut argv_ptrs: Vec<*const libc::c_char> = argv.iter().map(|arg| arg.as_ptr()).collect();
+    argv_ptrs.push(std::ptr::null());
+
+    let pid = match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()),
+        0 => unsafe {
+            // Stop so the launcher can set the ptrace options before the
+            // command starts.
+            if libc::ptrace(libc::PTRACE_TRACEME, 0, NULL, NULL) == 0
+                && libc::raise(libc::SIGSTOP) == 0
+            {
+                libc::execvp(argv_ptrs[0], argv_ptrs.as_ptr());
+            }
+            libc::_exit(127);
+        },
+        pid => pid,
+    };
+
+    let (tid, status) = wait_any()?;
+    if tid != pid || !libc::WIFSTOPPED(status) {
+        return Err(std::io::Error::other(
+            "the command did not stop for tracing",
+        ));
+    }
+    let options = libc::PTRACE_O_TRACESYSGOOD
+        | libc::PTRACE_O_TRACEFORK
+        | libc::PTRACE_O_TRACEVFORK
+        | libc::PTRACE_O_TRACECLONE
+        | libc::PTRACE_O_TRACEEXEC
+        | libc::PTRACE_O_EXITKILL;
+    if unsafe { libc::ptrace(libc::PTRACE_SETOPTIONS, pid, NULL, options as usize) } != 0 {
+        let err = std::io::Error::last_os_error();
+        unsafe {
+            libc::kill(pid, libc::SIGKILL);
+        }
+        return Err(err);
+    }
+    resume(pid, 0);
+
+    let mut syscalls = BTreeSet::new();
+    let mut seen = HashSet::from([pid]);
+    let mut exit_code = 1;
+    loop {
+        let (tid, status) = match wait_any() {
+            Ok(stopped) => stopped,
+            Err(err) if err.raw_os_error() == Some(libc::ECHILD) => break,
+            Err(err) => return Err(err),
+        };
+        if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+            if tid == pid {
+                exit_code = if libc::WIFEXITED(status) {
+                    libc::WEXITSTATUS(status)
+                } else {
+                    128 + libc::WTERMSIG(status)
+                };
+            }
+            continue;
+        }
+        if !libc::WIFSTOPPED(status) {
+            continue;
+        }
+
+        let signal = libc::WSTOPSIG(status);
+        let inject = if signal == libc::SIGTRAP | 0x80 {
+            if let Some(nr) = syscall_entry(tid) {
+                syscalls.insert(nr);
+            }
+            0
+        } else if signal == libc::SIGTRAP && status >> 16 != 0 {
+            // A fork, clone or exec event, not a signal.
+            0
+        } else if signal == libc::SIGSTOP && seen.insert(tid) {
+            // The initial stop of a newly traced process.
+            0
+        } else if is_group_stop(tid) {
+            0
+        } else {
+            signal
+        };
+        resume(tid, inject);
+    }
+    Ok((syscalls, exit_code))
+}
+
+fn wait_any() -> std::io::Result<(libc::pid_t, libc::c_int)> {
+    let mut status = 0;
+    loop {
+        let tid = unsafe { libc::waitpid(-1, &mut status, libc::__WALL) };
+        if tid >= 0 {
+            return Ok((tid, status));
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Resumes `tid` until its next syscall stop. It may have been killed in the
+/// meantime, which its exit reports.
+fn resume(tid: libc::pid_t, signal: libc::c_int) {
+    unsafe {
+        libc::ptrace(libc::PTRACE_SYSCALL, tid, NULL, signal as usize);
+    }
+}
+
+/// The syscall `tid` is entering, or `None` at a syscall exit.
+fn syscall_entry(tid: libc::pid_t) -> Option<i64> {
+    let mut info = SyscallInfo::default();
+    let size = std::mem::size_of::<SyscallInfo>();
+    let written = unsafe {
+        libc::ptrace(
+            PTRACE_GET_SYSCALL_INFO,
+            tid,
+            size,
+            &mut info as *mut SyscallInfo,
+        )
+    };
+    (written > 0 && info.op == PTRACE_SYSCALL_INFO_ENTRY).then_some(info.nr as i64)
+}
+
+/// Whether a stop of `tid` is a group-stop, which has no signal to deliver,
+/// rather than a signal-delivery-stop.
+fn is_group_stop(tid: libc::pid_t) -> bool {
+    let mut siginfo: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::ptrace(
+            libc::PTRACE_GETSIGINFO,
+            tid,
+            NULL,
+            &mut siginfo as *mut libc::siginfo_t,
+        ) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syscalls::syscall_number;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn list_sorts_names_and_appends_unknown_numbers() {
+        let syscalls = ["write", "execve", "read"]
+            .into_iter()
+            .filter_map(syscall_number)
+            .chain([99_999])
+            .collect();
+
+        assert_eq!(
+            render_syscall_list(&syscalls),
+            "execve\nread\nwrite\n99999  # not in the name table\n"
+        );
+    }
+}
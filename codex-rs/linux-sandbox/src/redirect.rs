@@ -0,0 +1,240 @@
+//! `--stdin`, `--stdout` and `--stderr`: files the command's standard streams
+//! are redirected to, for callers that would otherwise need a shell.
+//!
+//! The launcher opens the files before installing the sandbox and `dup2`s
+//! them onto fds 0-2 right before the exec. Since the sandbox does not check
+//! an already-open file, the paths are checked against the policy instead:
+//! `--stdout` and `--stderr` must be writable under it, and `--stdin` must not
+//! be hidden by `--read-deny`.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::protocol::SandboxPolicy;
+
+use crate::landlock::SandboxOptions;
+use crate::writable_roots::writable_roots_with_cwd;
+
+/// Written by every policy, like the Landlock ruleset and bwrap allow.
+const ALWAYS_WRITABLE: &[&str] = &["/dev/null"];
+
+/// An output file and whether to append to it rather than truncate it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OutputRedirect {
+    pub(crate) path: PathBuf,
+    pub(crate) append: bool,
+}
+
+/// The opened files, until [`Redirections::apply`] moves them onto the
+/// standard fds.
+#[derive(Debug, Default)]
+pub(crate) struct Redirections {
+    stdin: Option<File>,
+    stdout: Option<File>,
+    stderr: Option<File>,
+}
+
+impl Redirections {
+    /// Checks each path against the policy, then opens it. Output files are
+    /// created if missing.
+    pub(crate) fn open(
+        stdin: Option<&Path>,
+        stdout: Option<&OutputRedirect>,
+        stderr: Option<&OutputRedirect>,
+        sandbox_policy: &SandboxPolicy,
+        sandbox_policy_cwd: &Path,
+        options: &SandboxOptions,
+    ) -> Result<Self, String> {
+        let stdin = stdin
+            .map(|path| {
+                let resolved =
+                    resolve(path).map_err(|e| format!("--stdin {}: {e}", path.display()))?;
+                if options
+                    .read_deny
+                    .iter()
+                    .any(|deny| resolved.starts_with(deny))
+                {
+                    return Err(format!(
+                        "--stdin {} is hidden by --read-deny",
+                        path.display()
+                    ));
+                }
+                File::open(path).map_err(|e| format!("--stdin {}: {e}", path.display()))
+            })
+            .transpose()?;
+        let output = |flag: &str, redirect: &OutputRedirect| {
+            let path = &redirect.path;
+            let resolved = resolve(path).map_err(|e| format!("{flag} {}: {e}", path.display()))?;
+            if !is_writable(&resolved, sandbox_policy, sandbox_policy_cwd, options)
+                .map_err(|e| format!("{flag} {}: {e}", path.display()))?
+            {
+                return Err(format!(
+                    "{flag} {} is outside the writable roots",
+                    path.display()
+                ));
+            }
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(redirect.append)
+                .truncate(!redirect.append)
+                .open(path)
+                .map_err(|e| format!("{flag} {}: {e}", path.display()))
+        };
+        Ok(Self {
+            stdin,
+            stdout: stdout
+                .map(|redirect| output("--stdout", redirect))
+                .transpose()?,
+            stderr: stderr
+                .map(|redirect| output("--stderr", redirect))
+                .transpose()?,
+        })
+    }
+
+    /// Replaces fds 0-2 with the opened files. The duplicates are not
+    /// close-on-exec, unlike the originals.
+    pub(crate) fn apply(self) -> std::io::Result<()> {
+        for (file, target) in [
+            (self.stdin, libc::STDIN_FILENO),
+            (self.stdout, libc::STDOUT_FILENO),
+            (self.stderr, libc::STDERR_FILENO),
+        ] {
+            if let Some(file) = file
+                && unsafe { libc::dup2(file.as_raw_fd(), target) } < 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `path` made absolute with symlinks and `..` resolved. The file itself may
+/// not exist yet, but its directory must.
+fn resolve(path: &Path) -> std::io::Result<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Ok(resolved);
+    }
+    let Some(name) = path.file_name() else {
+        return path.canonicalize();
+    };
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    Ok(parent.canonicalize()?.join(name))
+}
+
+fn is_writable(
+    resolved: &Path,
+    sandbox_policy: &SandboxPolicy,
+    sandbox_policy_cwd: &Path,
+    options: &SandboxOptions,
+) -> codex_core::error::Result<bool> {
+    if sandbox_policy.has_full_disk_write_access()
+        || ALWAYS_WRITABLE
+            .iter()
+            .any(|path| resolved == Path::new(path))
+        || options.devices.iter().any(|device| resolved == device)
+    {
+        return Ok(true);
+    }
+    if let Some(private_tmp) = &options.private_tmp
+        && resolved.starts_with(private_tmp)
+    {
+        return Ok(true);
+    }
+    Ok(writable_roots_with_cwd(sandbox_policy, sandbox_policy_cwd)?
+        .iter()
+        // With a private tmp, the host's /tmp is not writable.
+        .filter(|writable_root| {
+            options.private_tmp.is_none() || writable_root.root != Path::new("/tmp")
+        })
+        .any(|writable_root| writable_root.is_path_writable(resolved)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn workspace(root: &Path) -> SandboxPolicy {
+        SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![root.to_path_buf()],
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        }
+    }
+
+    #[test]
+    fn output_outside_the_writable_roots_is_rejected() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let writable = tmpdir.path().join("writable");
+        let other = tmpdir.path().join("other");
+        std::fs::create_dir(&writable).expect("create writable dir");
+        std::fs::create_dir(&other).expect("create other dir");
+        let policy = workspace(&writable.canonicalize().expect("canonicalize"));
+        let options = SandboxOptions::for_policy(&policy);
+        let redirect = |path: PathBuf| OutputRedirect {
+            path,
+            append: false,
+        };
+
+        let outside = other.join("out.txt");
+        let err = Redirections::open(
+            None,
+            Some(&redirect(outside.clone())),
+            None,
+            &policy,
+            &writable,
+            &options,
+        )
+        .expect_err("other/ is not writable");
+        assert_eq!(
+            err,
+            format!(
+                "--stdout {} is outside the writable roots",
+                outside.display()
+            )
+        );
+        assert!(!outside.exists());
+
+        Redirections::open(
+            None,
+            Some(&redirect(writable.join("out.txt"))),
+            Some(&redirect(PathBuf::from("/dev/null"))),
+            &policy,
+            &writable,
+            &options,
+        )
+        .expect("writable root and /dev/null are writable");
+    }
+
+    #[test]
+    fn hidden_stdin_is_rejected() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let secret = tmpdir.path().join("secret");
+        std::fs::write(&secret, "").expect("write secret");
+        let mut options = SandboxOptions::for_policy(&SandboxPolicy::ReadOnly);
+        options.read_deny = vec![tmpdir.path().canonicalize().expect("canonicalize")];
+
+        let err = Redirections::open(
+            Some(&secret),
+            None,
+            None,
+            &SandboxPolicy::ReadOnly,
+            tmpdir.path(),
+            &options,
+        )
+        .expect_err("stdin is hidden");
+        assert_eq!(
+            err,
+            format!("--stdin {} is hidden by --read-deny", secret.display())
+        );
+    }
+}
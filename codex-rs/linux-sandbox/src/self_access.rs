@@ -0,0 +1,110 @@
+//! The launcher's own files: its executable, the `--spec` it read and the
+//! `--policy-validator` it ran. A command that could overwrite them could
+//! change how later launches are sandboxed, and the spec may hold more than
+//! the command needs to see, so the bwrap backend hides them from the
+//! command unless `--allow-self-access` is given.
+//!
+//! Landlock can only grant access, not carve exceptions out of it, so it
+//! can neither hide these files nor protect them inside a writable root.
+//! The spec needs no protection there: the launcher reads it before the
+//! sandbox is installed and passes nothing of it on, and a spec given on an
+//! inherited fd is kept from the command. Under that backend the launcher
+//! only refuses to run when its executable is inside a writable root.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::protocol::SandboxPolicy;
+
+use crate::writable_roots::writable_roots_with_cwd;
+
+/// The launcher's own files that exist, canonicalized and without
+/// duplicates.
+pub(crate) fn launcher_files(spec: Option<&Path>, policy_validator: Option<&Path>) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::env::current_exe()
+        .ok()
+        .into_iter()
+        .chain(spec.map(Path::to_path_buf))
+        .chain(policy_validator.map(Path::to_path_buf))
+        .filter_map(|file| file.canonicalize().ok())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// The writable roots of `sandbox_policy`, canonicalized like
+/// [`launcher_files`], or `None` when the policy allows writing everywhere.
+pub(crate) fn writable_roots_for_exposure(
+    sandbox_policy: &SandboxPolicy,
+    sandbox_policy_cwd: &Path,
+) -> Option<Vec<PathBuf>> {
+    if sandbox_policy.has_full_disk_write_access() {
+        return None;
+    }
+    let roots = writable_roots_with_cwd(sandbox_policy, sandbox_policy_cwd).unwrap_or_default();
+    Some(
+        roots
+            .into_iter()
+            .map(|root| root.root.canonicalize().unwrap_or(root.root))
+            .collect(),
+    )
+}
+
+/// The one of `writable_roots` that `exe` is inside, which the Landlock
+/// backend would let the command overwrite it in.
+pub(crate) fn writable_root_of<'a>(exe: &Path, writable_roots: &'a [PathBuf]) -> Option<&'a Path> {
+    writable_roots
+        .iter()
+        .find(|root| exe.starts_with(root))
+        .map(PathBuf::as_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn launcher_files_are_canonical_and_unique() {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let root = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+        let spec = root.join("spec.json");
+        std::fs::write(&spec, "{}").expect("write spec");
+        std::os::unix::fs::symlink(&spec, root.join("link.json")).expect("symlink");
+        let exe = std::env::current_exe()
+            .and_then(|exe| exe.canonicalize())
+            .expect("current exe");
+
+        let mut expected = vec![exe, spec.clone()];
+        expected.sort();
+        assert_eq!(
+            launcher_files(Some(&root.join("link.json")), Some(&spec)),
+            expected
+        );
+        // Files that do not exist have nothing to protect.
+        assert_eq!(
+            launcher_files(Some(&root.join("missing.json")), None).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn only_an_executable_under_a_writable_root_is_exposed() {
+        let roots = vec![PathBuf::from("/work"), PathBuf::from("/tmp/build")];
+
+        assert_eq!(
+            writable_root_of(Path::new("/work/bin/codex-linux-sandbox"), &roots),
+            Some(Path::new("/work"))
+        );
+        assert_eq!(
+            writable_root_of(Path::new("/usr/bin/codex-linux-sandbox"), &roots),
+            None
+        );
+        // A sibling that only shares a prefix is not inside the root.
+        assert_eq!(
+            writable_root_of(Path::new("/workshop/codex-linux-sandbox"), &roots),
+            None
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! `--self-harden`: the launcher gives up the capabilities it does not need
+//! itself, so a bug it hits while parsing its input or setting up the sandbox
+//! can do less with them.
+//!
+//! It happens in two steps. Before the spec or the policy is parsed, the
+//! launcher keeps only [`LAUNCHER_CAPABILITIES`], everything one of its
+//! features may use. Once the flags are parsed and the backend is chosen, it
+//! keeps only what that run needs, see [`needed_capabilities`].
+//!
+//! Only the effective, permitted and inheritable sets are reduced, and the
+//! ambient set is cleared. The bounding set is left alone, so a setuid bwrap
+//! still gets the privileges it needs, and what the command may regain by
+//! exec is up to the sandbox, not to this flag.
+
+use std::ffi::OsString;
+use std::io::Error;
+
+use crate::backend::SelectedBackend;
+use crate::landlock::NetworkMode;
+use crate::landlock::SandboxOptions;
+
+const CAP_SETGID: u32 = 6;
+const CAP_NET_ADMIN: u32 = 12;
+const CAP_SYS_ADMIN: u32 = 21;
+const CAP_SYS_NICE: u32 = 23;
+
+/// Names of the capabilities the launcher may keep, for messages.
+const CAPABILITY_NAMES: &[(u32, &str)] = &[
+    (CAP_SETGID, "CAP_SETGID"),
+    (CAP_NET_ADMIN, "CAP_NET_ADMIN"),
+    (CAP_SYS_ADMIN, "CAP_SYS_ADMIN"),
+    (CAP_SYS_NICE, "CAP_SYS_NICE"),
+];
+
+/// `_LINUX_CAPABILITY_VERSION_3`: 64-bit sets, split over two `CapData`s.
+const CAPABILITY_VERSION: u32 = 0x2008_0522;
+
+/// A set of capabilities, one bit per capability number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CapabilitySet(u64);
+
+impl CapabilitySet {
+    const fn of(capabilities: &[u32]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < capabilities.len() {
+            bits |= 1 << capabilities[i];
+            i += 1;
+        }
+        Self(bits)
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub(crate) fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Names of the capabilities in the set, or their numbers for those the
+    /// launcher never keeps.
+    pub(crate) fn names(self) -> Vec<String> {
+        (0..64)
+            .filter(|capability| self.0 & (1 << capability) != 0)
+            .map(|capability| {
+                CAPABILITY_NAMES
+                    .iter()
+                    .find(|&&(number, _)| number == capability)
+                    .map_or_else(|| capability.to_string(), |&(_, name)| name.to_string())
+            })
+            .collect()
+    }
+}
+
+/// What any launcher feature may need: `CAP_SYS_ADMIN` for bwrap and for the
+/// private network namespace, `CAP_NET_ADMIN` to bring up its `lo`,
+/// `CAP_SETGID` for `--groups` and `CAP_SYS_NICE` for `--nice` and `--sched`.
+pub(crate) const LAUNCHER_CAPABILITIES: CapabilitySet =
+    CapabilitySet::of(&[CAP_SETGID, CAP_NET_ADMIN, CAP_SYS_ADMIN, CAP_SYS_NICE]);
+
+/// What this run needs, given the chosen backend and whether `--groups` and
+/// `--nice` or `--sched` were given. The Landlock backend and
+/// `--backend none` need nothing of their own.
+pub(crate) fn needed_capabilities(
+    selected_backend: &SelectedBackend,
+    options: &SandboxOptions,
+    groups: bool,
+    priority: bool,
+) -> CapabilitySet {
+    let mut needed = CapabilitySet::default();
+    match selected_backend {
+        SelectedBackend::Bwrap { .. } => needed = needed.union(CapabilitySet::of(&[CAP_SYS_ADMIN])),
+        SelectedBackend::Landlock if options.network == NetworkMode::LoopbackOnly => {
+            needed = needed.union(CapabilitySet::of(&[CAP_SYS_ADMIN, CAP_NET_ADMIN]));
+        }
+        SelectedBackend::Landlock | SelectedBackend::None => {}
+    }
+    if groups {
+        needed = needed.union(CapabilitySet::of(&[CAP_SETGID]));
+    }
+    if priority {
+        needed = needed.union(CapabilitySet::of(&[CAP_SYS_NICE]));
+    }
+    needed
+}
+
+/// Whether `--self-harden` is among the launcher flags in `args`, checked
+/// before clap parses them so the first step happens before the policy is
+/// parsed. Only the command line is searched, not a `--spec`.
+pub(crate) fn self_harden_requested(args: &[OsString]) -> bool {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--self-harden")
+}
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+fn capget() -> std::io::Result<[CapData; 2]> {
+    let mut header = CapHeader {
+        version: CAPABILITY_VERSION,
+        pid: 0,
+    };
+    let mut data = [CapData::default(); 2];
+    if unsafe { libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(data)
+}
+
+/// Drops every capability not in `keep` from the effective, permitted and
+/// inheritable sets, and clears the ambient set. Capabilities in `keep` the
+/// launcher does not have are not gained.
+pub(crate) fn keep_only(keep: CapabilitySet) -> std::io::Result<()> {
+    let mut data = capget()?;
+    for (index, data) in data.iter_mut().enumerate() {
+        let keep = (keep.0 >> (32 * index)) as u32;
+        data.effective &= keep;
+        data.permitted &= keep;
+        data.inheritable &= keep;
+    }
+    // The ambient set may only hold permitted and inheritable capabilities,
+    // so it goes first. Kernels before 4.3 have none to clear.
+    if unsafe {
+        libc::prctl(
+            libc::PR_CAP_AMBIENT,
+            libc::PR_CAP_AMBIENT_CLEAR_ALL,
+            0,
+            0,
+            0,
+        )
+    } != 0
+    {
+        let err = Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EINVAL) {
+            return Err(err);
+        }
+    }
+    let mut header = CapHeader {
+        version: CAPABILITY_VERSION,
+        pid: 0,
+    };
+    if unsafe { libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    fn effective_capabilities() -> std::io::Result<CapabilitySet> {
+        let data = capget()?;
+        Ok(CapabilitySet(
+            u64::from(data[0].effective) | (u64::from(data[1].effective) << 32),
+        ))
+    }
+
+    #[test]
+    fn runs_need_only_what_their_backend_and_flags_use() {
+        let policy = codex_core::protocol::SandboxPolicy::ReadOnly;
+        let mut options = SandboxOptions::for_policy(&policy);
+        let bwrap = SelectedBackend::Bwrap {
+            bwrap: PathBuf::from("/usr/bin/bwrap"),
+        };
+
+        assert_eq!(
+            needed_capabilities(&SelectedBackend::Landlock, &options, false, false),
+            CapabilitySet::default()
+        );
+        assert_eq!(
+            needed_capabilities(&bwrap, &options, false, false).names(),
+            vec!["CAP_SYS_ADMIN"]
+        );
+        assert_eq!(
+            needed_capabilities(&SelectedBackend::None, &options, true, true).names(),
+            vec!["CAP_SETGID", "CAP_SYS_NICE"]
+        );
+
+        options.network = NetworkMode::LoopbackOnly;
+        assert_eq!(
+            needed_capabilities(&SelectedBackend::Landlock, &options, false, false).names(),
+            vec!["CAP_NET_ADMIN", "CAP_SYS_ADMIN"]
+        );
+        // bwrap sets up the loopback namespace itself.
+        assert_eq!(
+            needed_capabilities(&bwrap, &options, false, false).names(),
+            vec!["CAP_SYS_ADMIN"]
+        );
+    }
+
+    #[test]
+    fn only_launcher_flags_request_hardening() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert!(self_harden_requested(&args(&[
+            "sandbox",
+            "--self-harden",
+            "--",
+            "true"
+        ])));
+        assert!(!self_harden_requested(&args(&[
+            "sandbox",
+            "--",
+            "tool",
+            "--self-harden"
+        ])));
+    }
+
+    #[test]
+    fn dropped_capabilities_stay_dropped() {
+        // Capabilities are per thread, so this leaves the other tests alone.
+        let effective = std::thread::spawn(|| {
+            keep_only(CapabilitySet::default()).expect("drop capabilities");
+            effective_capabilities().expect("read capabilities")
+        })
+        .join()
+        .expect("capability thread panicked");
+
+        assert_eq!(effective, CapabilitySet::default());
+    }
+}
@@ -0,0 +1,264 @@
+//! The self-test the sandboxed process runs once the Landlock backend has
+//! installed the sandbox, before the command is exec'd. Each probe tries
+//! something the sandbox must deny, so a restriction reported as installed
+//! that does not actually hold keeps the run from being reported as
+//! enforced.
+//!
+//! The probes leave no trace:
+//!
+//! - `PR_GET_NO_NEW_PRIVS` must report the flag as set.
+//! - With a seccomp network filter, opening an `AF_NETLINK` socket, which
+//!   every network mode denies, must fail with `EPERM`.
+//! - With a Landlock ruleset, opening a device outside the writable paths
+//!   (`/dev/full`, or else `/dev/zero`) for writing must fail with `EACCES`.
+//!   Nothing is written to it.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::os::fd::FromRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::protocol::SandboxPolicy;
+
+use crate::enforcement::EnforcementVerdict;
+use crate::enforcement::NetworkEnforcement;
+use crate::enforcement::SandboxEnforcement;
+use crate::landlock::LandlockRules;
+use crate::landlock::SandboxOptions;
+
+/// World-writable devices no policy makes writable by default.
+const LANDLOCK_PROBES: &[&str] = &["/dev/full", "/dev/zero"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SelfTest {
+    /// `None` when every probe is missing or writable under the policy.
+    landlock_probe: Option<PathBuf>,
+}
+
+impl SelfTest {
+    /// Picks the probes while the launcher can still inspect every path,
+    /// before the sandbox is installed.
+    pub(crate) fn prepare(
+        sandbox_policy: &SandboxPolicy,
+        options: &SandboxOptions,
+        cwd: &Path,
+        abi: i32,
+    ) -> Self {
+        // An invalid policy fails the install with the same error, so there
+        // is nothing to probe then.
+        let writable_roots = LandlockRules::for_policy(
+            sandbox_policy,
+            options,
+            cwd,
+            abi,
+            &mut SandboxEnforcement::default(),
+        )
+        .ok()
+        .flatten()
+        .map(|rules| rules.writable_roots)
+        .unwrap_or_default();
+        Self::with_writable_roots(&writable_roots)
+    }
+
+    fn with_writable_roots(writable_roots: &[PathBuf]) -> Self {
+        let landlock_probe = LANDLOCK_PROBES.iter().map(PathBuf::from).find(|probe| {
+            probe.exists() && !writable_roots.iter().any(|root| probe.starts_with(root))
+        });
+        Self { landlock_probe }
+    }
+
+    /// Runs the probes for the restrictions `enforcement` says are installed
+    /// on the current thread.
+    pub(crate) fn run(&self, enforcement: &SandboxEnforcement) -> Result<(), String> {
+        if enforcement.no_new_privs
+            && unsafe { libc::prctl(libc::PR_GET_NO_NEW_PRIVS, 0, 0, 0, 0) } != 1
+        {
+            return Err("no_new_privs is not set".to_string());
+        }
+        if enforcement.network != NetworkEnforcement::Unrestricted {
+            probe_network_filter()?;
+        }
+        if enforcement.landlock.is_some() {
+            let probe = self
+                .landlock_probe
+                .as_deref()
+                .ok_or("no device outside the writable paths to probe Landlock with")?;
+            probe_landlock(probe)?;
+        }
+        Ok(())
+    }
+}
+
+fn probe_network_filter() -> Result<(), String> {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd >= 0 {
+        unsafe {
+            libc::close(fd);
+        }
+        return Err("the seccomp filter let a netlink socket be opened".to_string());
+    }
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EPERM) => Ok(()),
+        _ => Err(format!(
+            "opening a netlink socket failed with {err} rather than EPERM"
+        )),
+    }
+}
+
+fn probe_landlock(probe: &Path) -> Result<(), String> {
+    match OpenOptions::new().write(true).open(probe) {
+        Ok(_) => Err(format!(
+            "Landlock let {} be opened for writing",
+            probe.display()
+        )),
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => Ok(()),
+        Err(e) => Err(format!(
+            "opening {} for writing failed with {e} rather than EACCES",
+            probe.display()
+        )),
+    }
+}
+
+/// In `--wait` mode, carries the child's verdict to the launcher. Both ends
+/// are close-on-exec, so the launcher reads until the child has exec'd the
+/// command or exited.
+#[derive(Debug)]
+pub(crate) struct VerdictPipe {
+    read: File,
+    write: File,
+}
+
+impl VerdictPipe {
+    pub(crate) fn new() -> std::io::Result<Self> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(unsafe {
+            Self {
+                read: File::from_raw_fd(fds[0]),
+                write: File::from_raw_fd(fds[1]),
+            }
+        })
+    }
+
+    /// Child side of the fork: the end the verdict is sent on.
+    pub(crate) fn into_sender(self) -> VerdictSender {
+        VerdictSender(self.write)
+    }
+
+    /// Launcher side of the fork: waits for the child's verdict.
+    pub(crate) fn receive(self) -> EnforcementVerdict {
+        let Self { mut read, write } = self;
+        drop(write);
+        let mut message = String::new();
+        if read.read_to_string(&mut message).is_err() {
+            return EnforcementVerdict::never_reported();
+        }
+        decode_verdict(&message).unwrap_or_else(EnforcementVerdict::never_reported)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct VerdictSender(File);
+
+impl VerdictSender {
+    pub(crate) fn send(mut self, verdict: &EnforcementVerdict) -> std::io::Result<()> {
+        self.0.write_all(encode_verdict(verdict).as_bytes())
+    }
+}
+
+fn encode_verdict(verdict: &EnforcementVerdict) -> String {
+    format!("{}\n{}", verdict.enforced, verdict.reason)
+}
+
+fn decode_verdict(message: &str) -> Option<EnforcementVerdict> {
+    let (enforced, reason) = message.split_once('\n')?;
+    Some(EnforcementVerdict {
+        enforced: enforced.parse().ok()?,
+        reason: reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enforcement::LandlockEnforcement;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn writable_devices_are_not_probed() {
+        assert_eq!(
+            SelfTest::with_writable_roots(&[PathBuf::from("/work")]),
+            SelfTest {
+                landlock_probe: Some(PathBuf::from("/dev/full")),
+            }
+        );
+        assert_eq!(
+            SelfTest::with_writable_roots(&[PathBuf::from("/dev/full")]),
+            SelfTest {
+                landlock_probe: Some(PathBuf::from("/dev/zero")),
+            }
+        );
+        assert_eq!(
+            SelfTest::with_writable_roots(&[PathBuf::from("/dev")]),
+            SelfTest {
+                landlock_probe: None,
+            }
+        );
+    }
+
+    #[test]
+    fn probes_fail_without_the_sandbox() {
+        let self_test = SelfTest::with_writable_roots(&[]);
+        assert_eq!(self_test.run(&SandboxEnforcement::default()), Ok(()));
+
+        let network = SandboxEnforcement {
+            network: NetworkEnforcement::Seccomp,
+            ..SandboxEnforcement::default()
+        };
+        assert_eq!(
+            self_test.run(&network),
+            Err("the seccomp filter let a netlink socket be opened".to_string())
+        );
+
+        let landlock = SandboxEnforcement {
+            landlock: Some(LandlockEnforcement::Full),
+            ..SandboxEnforcement::default()
+        };
+        assert_eq!(
+            self_test.run(&landlock),
+            Err("Landlock let /dev/full be opened for writing".to_string())
+        );
+        assert_eq!(
+            SelfTest::with_writable_roots(&[PathBuf::from("/dev")]).run(&landlock),
+            Err("no device outside the writable paths to probe Landlock with".to_string())
+        );
+    }
+
+    #[test]
+    fn verdicts_survive_the_pipe() {
+        let verdict = EnforcementVerdict {
+            enforced: true,
+            reason: "multi\nline".to_string(),
+        };
+        let pipe = VerdictPipe::new().expect("pipe");
+        let sender = VerdictSender(pipe.write.try_clone().expect("clone write end"));
+        sender.send(&verdict).expect("send verdict");
+
+        assert_eq!(pipe.receive(), verdict);
+    }
+
+    #[test]
+    fn a_silent_child_was_never_verified() {
+        let pipe = VerdictPipe::new().expect("pipe");
+
+        assert_eq!(pipe.receive(), EnforcementVerdict::never_reported());
+    }
+}
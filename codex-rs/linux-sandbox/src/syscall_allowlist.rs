@@ -0,0 +1,336 @@
+//! Deny-by-default seccomp filters built from an explicit list of permitted
+//! syscalls.
+//!
+//! Allowlists are fragile: the exact syscalls a program makes depend on its
+//! libc version, build flags and runtime, so a list tuned on one host may
+//! break the same program on another.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use clap::ValueEnum;
+use codex_core::error::SandboxErr;
+use seccompiler::BpfProgram;
+use seccompiler::SeccompAction;
+use seccompiler::SeccompFilter;
+use seccompiler::SeccompRule;
+
+use crate::landlock::apply_seccomp_program;
+use crate::landlock::seccomp_target_arch;
+use crate::syscalls::syscall_number;
+
+/// Syscalls the launcher itself needs once the allowlist is installed so it
+/// can hand off to the command, or report why it could not.
+const LAUNCHER_SYSCALLS: &[&str] = &["execve", "write", "exit", "exit_group"];
+
+/// Dynamic loader, libc startup, plain file I/O and process bookkeeping.
+const RUNTIME_SYSCALLS: &[&str] = &[
+    "read",
+    "write",
+    "openat",
+    "close",
+    "fstat",
+    "newfstatat",
+    "statx",
+    "lseek",
+    "pread64",
+    "readv",
+    "writev",
+    "mmap",
+    "mprotect",
+    "munmap",
+    "mremap",
+    "madvise",
+    "brk",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "sigaltstack",
+    "ioctl",
+    "fcntl",
+    "dup",
+    "dup3",
+    "pipe2",
+    "getdents64",
+    "getcwd",
+    "chdir",
+    "readlinkat",
+    "faccessat",
+    "faccessat2",
+    "umask",
+    "getpid",
+    "getppid",
+    "gettid",
+    "getuid",
+    "geteuid",
+    "getgid",
+    "getegid",
+    "uname",
+    "sysinfo",
+    "prlimit64",
+    "getrlimit",
+    "getrandom",
+    "clock_gettime",
+    "clock_getres",
+    "clock_nanosleep",
+    "gettimeofday",
+    "nanosleep",
+    "futex",
+    "set_tid_address",
+    "set_robust_list",
+    "rseq",
+    "sched_getaffinity",
+    "sched_yield",
+    "wait4",
+    "exit",
+    "exit_group",
+];
+
+#[cfg(target_arch = "x86_64")]
+const RUNTIME_ARCH_SYSCALLS: &[&str] = &[
+    "arch_prctl",
+    "access",
+    "open",
+    "stat",
+    "lstat",
+    "readlink",
+    "dup2",
+    "pipe",
+];
+
+#[cfg(not(target_arch = "x86_64"))]
+const RUNTIME_ARCH_SYSCALLS: &[&str] = &[];
+
+/// Extra syscalls CPython makes on startup and while importing modules.
+const PYTHON_SYSCALLS: &[&str] = &["getdents64", "fstatfs", "pselect6", "ppoll"];
+
+#[cfg(target_arch = "x86_64")]
+const PYTHON_ARCH_SYSCALLS: &[&str] = &["select", "poll"];
+
+#[cfg(not(target_arch = "x86_64"))]
+const PYTHON_ARCH_SYSCALLS: &[&str] = &[];
+
+/// Extra syscalls Node.js makes for its event loop and libuv thread pool.
+const NODE_SYSCALLS: &[&str] = &[
+    "clone",
+    "clone3",
+    "epoll_create1",
+    "epoll_ctl",
+    "epoll_pwait",
+    "eventfd2",
+    "prctl",
+    "capget",
+    "memfd_create",
+    "ppoll",
+];
+
+#[cfg(target_arch = "x86_64")]
+const NODE_ARCH_SYSCALLS: &[&str] = &["epoll_wait", "poll"];
+
+#[cfg(not(target_arch = "x86_64"))]
+const NODE_ARCH_SYSCALLS: &[&str] = &[];
+
+/// Curated allowlist presets for common interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SyscallProfile {
+    /// Enough to start CPython and run a script that only works with files.
+    MinimalPython,
+    /// Enough to start Node.js and run a script that only works with files.
+    MinimalNode,
+}
+
+impl SyscallProfile {
+    fn syscalls(self) -> impl Iterator<Item = &'static str> {
+        let (extra, extra_arch) = match self {
+            SyscallProfile::MinimalPython => (PYTHON_SYSCALLS, PYTHON_ARCH_SYSCALLS),
+            SyscallProfile::MinimalNode => (NODE_SYSCALLS, NODE_ARCH_SYSCALLS),
+        };
+        RUNTIME_SYSCALLS
+            .iter()
+            .chain(RUNTIME_ARCH_SYSCALLS)
+            .chain(extra)
+            .chain(extra_arch)
+            .copied()
+    }
+}
+
+/// What happens to a syscall that is not on the allowlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SyscallDenyAction {
+    /// Fail the syscall with `EPERM`.
+    Errno,
+    /// Kill the process.
+    Kill,
+}
+
+/// Set of syscall numbers permitted by a deny-by-default filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SyscallAllowlist {
+    syscalls: BTreeSet<i64>,
+}
+
+impl SyscallAllowlist {
+    /// Combines the allowlist file (if any) with the preset (if any). Returns
+    /// `None` when neither was requested.
+    pub(crate) fn load(
+        path: Option<&Path>,
+        profile: Option<SyscallProfile>,
+    ) -> std::io::Result<Option<Self>> {
+        if path.is_none() && profile.is_none() {
+            return Ok(None);
+        }
+
+        let mut allowlist = match path {
+            Some(path) => Self::parse(&std::fs::read_to_string(path)?)?,
+            None => Self::default(),
+        };
+        if let Some(profile) = profile {
+            allowlist.add_profile(profile);
+        }
+        Ok(Some(allowlist))
+    }
+
+    /// Parses one syscall per line. Blank lines are ignored and `#` starts a
+    /// comment. Syscalls missing from our name table can be given by number.
+    pub(crate) fn parse(contents: &str) -> std::io::Result<Self> {
+        let mut allowlist = Self::default();
+        for (index, line) in contents.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let nr = match entry.parse::<i64>() {
+                Ok(nr) => nr,
+                Err(_) => syscall_number(entry).ok_or_else(|| {
+                    let line_number = index + 1;
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "unknown syscall `{entry}` on line {line_number}; use its number instead"
+                        ),
+                    )
+                })?,
+            };
+            allowlist.syscalls.insert(nr);
+        }
+        Ok(allowlist)
+    }
+
+    pub(crate) fn add_profile(&mut self, profile: SyscallProfile) {
+        self.syscalls
+            .extend(profile.syscalls().filter_map(syscall_number));
+    }
+}
+
+/// Assembles a filter that allows exactly the syscalls in `allowlist` (plus
+/// the few the launcher needs to exec) and applies `deny_action` to the rest.
+pub(crate) fn build_syscall_allowlist_filter(
+    allowlist: &SyscallAllowlist,
+    deny_action: SyscallDenyAction,
+) -> Result<BpfProgram, SandboxErr> {
+    let launcher_syscalls = LAUNCHER_SYSCALLS.iter().copied().filter_map(syscall_number);
+
+    // Empty rule vec = unconditional match, i.e. always allowed.
+    let rules: BTreeMap<i64, Vec<SeccompRule>> = allowlist
+        .syscalls
+        .iter()
+        .copied()
+        .chain(launcher_syscalls)
+        .map(|nr| (nr, vec![]))
+        .collect();
+
+    let mismatch_action = match deny_action {
+        SyscallDenyAction::Errno => SeccompAction::Errno(libc::EPERM as u32),
+        SyscallDenyAction::Kill => SeccompAction::KillProcess,
+    };
+
+    let filter = SeccompFilter::new(
+        rules,
+        mismatch_action,      // default – deny
+        SeccompAction::Allow, // when rule matches – allow
+        seccomp_target_arch(),
+    )?;
+
+    Ok(filter.try_into()?)
+}
+
+/// Installs the allowlist filter on the current thread, or on all threads with
+/// `all_threads`. This must be the last filter installed before exec since it
+/// likely blocks the syscalls that Landlock and the other filters need.
+pub(crate) fn install_syscall_allowlist_on_current_thread(
+    allowlist: &SyscallAllowlist,
+    deny_action: SyscallDenyAction,
+    all_threads: bool,
+) -> Result<(), SandboxErr> {
+    let prog = build_syscall_allowlist_filter(allowlist, deny_action)?;
+    apply_seccomp_program(&prog, all_threads)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_ignores_comments_and_blank_lines() {
+        let allowlist = SyscallAllowlist::parse(
+            "# header comment\n\nread\n  write  # trailing comment\n\n1000\n",
+        )
+        .expect("allowlist should parse");
+
+        assert_eq!(
+            allowlist,
+            SyscallAllowlist {
+                syscalls: BTreeSet::from([libc::SYS_read, libc::SYS_write, 1000]),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_names() {
+        let err = SyscallAllowlist::parse("read\nnot_a_syscall\n")
+            .expect_err("unknown syscall should be rejected");
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "unknown syscall `not_a_syscall` on line 2; use its number instead"
+        );
+    }
+
+    #[test]
+    fn profiles_only_reference_known_syscalls() {
+        for profile in [SyscallProfile::MinimalPython, SyscallProfile::MinimalNode] {
+            for name in profile.syscalls() {
+                assert!(
+                    syscall_number(name).is_some(),
+                    "{profile:?} references unknown syscall {name}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn load_merges_file_and_profile() {
+        let mut file = tempfile::NamedTempFile::new().expect("create allowlist file");
+        std::io::Write::write_all(&mut file, b"socket\n").expect("write allowlist file");
+
+        let allowlist =
+            SyscallAllowlist::load(Some(file.path()), Some(SyscallProfile::MinimalNode))
+                .expect("allowlist should load")
+                .expect("allowlist was requested");
+
+        let mut expected = SyscallAllowlist::default();
+        expected.add_profile(SyscallProfile::MinimalNode);
+        expected.syscalls.insert(libc::SYS_socket);
+        assert_eq!(allowlist, expected);
+    }
+
+    #[test]
+    fn load_without_file_or_profile_is_none() {
+        assert_eq!(SyscallAllowlist::load(None, None).expect("load"), None);
+    }
+}
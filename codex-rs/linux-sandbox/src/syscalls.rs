@@ -0,0 +1,319 @@
+//! Mapping between syscall names and numbers for the architectures the
+//! seccomp filters support.
+//!
+//! The table is intentionally limited to syscalls that user programs commonly
+//! make. Syscalls that are not listed here can still be referenced by number.
+
+/// Syscalls available on every supported architecture.
+const COMMON_SYSCALLS: &[(&str, i64)] = &[
+    ("read", libc::SYS_read),
+    ("write", libc::SYS_write),
+    ("openat", libc::SYS_openat),
+    ("close", libc::SYS_close),
+    ("fstat", libc::SYS_fstat),
+    ("newfstatat", libc::SYS_newfstatat),
+    ("lseek", libc::SYS_lseek),
+    ("mmap", libc::SYS_mmap),
+    ("mprotect", libc::SYS_mprotect),
+    ("munmap", libc::SYS_munmap),
+    ("brk", libc::SYS_brk),
+    ("rt_sigaction", libc::SYS_rt_sigaction),
+    ("rt_sigprocmask", libc::SYS_rt_sigprocmask),
+    ("rt_sigreturn", libc::SYS_rt_sigreturn),
+    ("ioctl", libc::SYS_ioctl),
+    ("pread64", libc::SYS_pread64),
+    ("pwrite64", libc::SYS_pwrite64),
+    ("readv", libc::SYS_readv),
+    ("writev", libc::SYS_writev),
+    ("pipe2", libc::SYS_pipe2),
+    ("sched_yield", libc::SYS_sched_yield),
+    ("mremap", libc::SYS_mremap),
+    ("msync", libc::SYS_msync),
+    ("mincore", libc::SYS_mincore),
+    ("madvise", libc::SYS_madvise),
+    ("dup", libc::SYS_dup),
+    ("dup3", libc::SYS_dup3),
+    ("nanosleep", libc::SYS_nanosleep),
+    ("getitimer", libc::SYS_getitimer),
+    ("setitimer", libc::SYS_setitimer),
+    ("getpid", libc::SYS_getpid),
+    ("sendfile", libc::SYS_sendfile),
+    ("socket", libc::SYS_socket),
+    ("connect", libc::SYS_connect),
+    ("accept", libc::SYS_accept),
+    ("accept4", libc::SYS_accept4),
+    ("sendto", libc::SYS_sendto),
+    ("recvfrom", libc::SYS_recvfrom),
+    ("sendmsg", libc::SYS_sendmsg),
+    ("recvmsg", libc::SYS_recvmsg),
+    ("sendmmsg", libc::SYS_sendmmsg),
+    ("recvmmsg", libc::SYS_recvmmsg),
+    ("shutdown", libc::SYS_shutdown),
+    ("bind", libc::SYS_bind),
+    ("listen", libc::SYS_listen),
+    ("getsockname", libc::SYS_getsockname),
+    ("getpeername", libc::SYS_getpeername),
+    ("socketpair", libc::SYS_socketpair),
+    ("setsockopt", libc::SYS_setsockopt),
+    ("getsockopt", libc::SYS_getsockopt),
+    ("clone", libc::SYS_clone),
+    ("clone3", libc::SYS_clone3),
+    ("execve", libc::SYS_execve),
+    ("execveat", libc::SYS_execveat),
+    ("exit", libc::SYS_exit),
+    ("exit_group", libc::SYS_exit_group),
+    ("wait4", libc::SYS_wait4),
+    ("waitid", libc::SYS_waitid),
+    ("kill", libc::SYS_kill),
+    ("tkill", libc::SYS_tkill),
+    ("tgkill", libc::SYS_tgkill),
+    ("uname", libc::SYS_uname),
+    ("fcntl", libc::SYS_fcntl),
+    ("flock", libc::SYS_flock),
+    ("fsync", libc::SYS_fsync),
+    ("fdatasync", libc::SYS_fdatasync),
+    ("sync", libc::SYS_sync),
+    ("syncfs", libc::SYS_syncfs),
+    ("sync_file_range", libc::SYS_sync_file_range),
+    ("truncate", libc::SYS_truncate),
+    ("ftruncate", libc::SYS_ftruncate),
+    ("fallocate", libc::SYS_fallocate),
+    ("fadvise64", libc::SYS_fadvise64),
+    ("readahead", libc::SYS_readahead),
+    ("getdents64", libc::SYS_getdents64),
+    ("getcwd", libc::SYS_getcwd),
+    ("chdir", libc::SYS_chdir),
+    ("fchdir", libc::SYS_fchdir),
+    ("chroot", libc::SYS_chroot),
+    ("fchmod", libc::SYS_fchmod),
+    ("fchmodat", libc::SYS_fchmodat),
+    ("fchown", libc::SYS_fchown),
+    ("fchownat", libc::SYS_fchownat),
+    ("umask", libc::SYS_umask),
+    ("mkdirat", libc::SYS_mkdirat),
+    ("mknodat", libc::SYS_mknodat),
+    ("unlinkat", libc::SYS_unlinkat),
+    ("renameat", libc::SYS_renameat),
+    ("renameat2", libc::SYS_renameat2),
+    ("linkat", libc::SYS_linkat),
+    ("symlinkat", libc::SYS_symlinkat),
+    ("readlinkat", libc::SYS_readlinkat),
+    ("faccessat", libc::SYS_faccessat),
+    ("faccessat2", libc::SYS_faccessat2),
+    ("utimensat", libc::SYS_utimensat),
+    ("statfs", libc::SYS_statfs),
+    ("fstatfs", libc::SYS_fstatfs),
+    ("statx", libc::SYS_statx),
+    ("openat2", libc::SYS_openat2),
+    ("close_range", libc::SYS_close_range),
+    ("gettimeofday", libc::SYS_gettimeofday),
+    ("getrlimit", libc::SYS_getrlimit),
+    ("setrlimit", libc::SYS_setrlimit),
+    ("prlimit64", libc::SYS_prlimit64),
+    ("getrusage", libc::SYS_getrusage),
+    ("sysinfo", libc::SYS_sysinfo),
+    ("times", libc::SYS_times),
+    ("ptrace", libc::SYS_ptrace),
+    ("getuid", libc::SYS_getuid),
+    ("getgid", libc::SYS_getgid),
+    ("setuid", libc::SYS_setuid),
+    ("setgid", libc::SYS_setgid),
+    ("geteuid", libc::SYS_geteuid),
+    ("getegid", libc::SYS_getegid),
+    ("setpgid", libc::SYS_setpgid),
+    ("getppid", libc::SYS_getppid),
+    ("getpgid", libc::SYS_getpgid),
+    ("getsid", libc::SYS_getsid),
+    ("setsid", libc::SYS_setsid),
+    ("setreuid", libc::SYS_setreuid),
+    ("setregid", libc::SYS_setregid),
+    ("setresuid", libc::SYS_setresuid),
+    ("getresuid", libc::SYS_getresuid),
+    ("setresgid", libc::SYS_setresgid),
+    ("getresgid", libc::SYS_getresgid),
+    ("getgroups", libc::SYS_getgroups),
+    ("setgroups", libc::SYS_setgroups),
+    ("capget", libc::SYS_capget),
+    ("capset", libc::SYS_capset),
+    ("personality", libc::SYS_personality),
+    ("prctl", libc::SYS_prctl),
+    ("getpriority", libc::SYS_getpriority),
+    ("setpriority", libc::SYS_setpriority),
+    ("sched_setparam", libc::SYS_sched_setparam),
+    ("sched_getparam", libc::SYS_sched_getparam),
+    ("sched_setscheduler", libc::SYS_sched_setscheduler),
+    ("sched_getscheduler", libc::SYS_sched_getscheduler),
+    ("sched_get_priority_max", libc::SYS_sched_get_priority_max),
+    ("sched_get_priority_min", libc::SYS_sched_get_priority_min),
+    ("sched_rr_get_interval", libc::SYS_sched_rr_get_interval),
+    ("sched_setaffinity", libc::SYS_sched_setaffinity),
+    ("sched_getaffinity", libc::SYS_sched_getaffinity),
+    ("sched_setattr", libc::SYS_sched_setattr),
+    ("sched_getattr", libc::SYS_sched_getattr),
+    ("getcpu", libc::SYS_getcpu),
+    ("rt_sigpending", libc::SYS_rt_sigpending),
+    ("rt_sigtimedwait", libc::SYS_rt_sigtimedwait),
+    ("rt_sigqueueinfo", libc::SYS_rt_sigqueueinfo),
+    ("rt_sigsuspend", libc::SYS_rt_sigsuspend),
+    ("sigaltstack", libc::SYS_sigaltstack),
+    ("mlock", libc::SYS_mlock),
+    ("munlock", libc::SYS_munlock),
+    ("mlockall", libc::SYS_mlockall),
+    ("munlockall", libc::SYS_munlockall),
+    ("mount", libc::SYS_mount),
+    ("umount2", libc::SYS_umount2),
+    ("gettid", libc::SYS_gettid),
+    ("setxattr", libc::SYS_setxattr),
+    ("getxattr", libc::SYS_getxattr),
+    ("listxattr", libc::SYS_listxattr),
+    ("removexattr", libc::SYS_removexattr),
+    ("fsetxattr", libc::SYS_fsetxattr),
+    ("fgetxattr", libc::SYS_fgetxattr),
+    ("futex", libc::SYS_futex),
+    ("set_tid_address", libc::SYS_set_tid_address),
+    ("set_robust_list", libc::SYS_set_robust_list),
+    ("get_robust_list", libc::SYS_get_robust_list),
+    ("restart_syscall", libc::SYS_restart_syscall),
+    ("rseq", libc::SYS_rseq),
+    ("timer_create", libc::SYS_timer_create),
+    ("timer_settime", libc::SYS_timer_settime),
+    ("timer_gettime", libc::SYS_timer_gettime),
+    ("timer_getoverrun", libc::SYS_timer_getoverrun),
+    ("timer_delete", libc::SYS_timer_delete),
+    ("clock_settime", libc::SYS_clock_settime),
+    ("clock_gettime", libc::SYS_clock_gettime),
+    ("clock_getres", libc::SYS_clock_getres),
+    ("clock_nanosleep", libc::SYS_clock_nanosleep),
+    ("epoll_create1", libc::SYS_epoll_create1),
+    ("epoll_ctl", libc::SYS_epoll_ctl),
+    ("epoll_pwait", libc::SYS_epoll_pwait),
+    ("eventfd2", libc::SYS_eventfd2),
+    ("signalfd4", libc::SYS_signalfd4),
+    ("timerfd_create", libc::SYS_timerfd_create),
+    ("timerfd_settime", libc::SYS_timerfd_settime),
+    ("timerfd_gettime", libc::SYS_timerfd_gettime),
+    ("inotify_init1", libc::SYS_inotify_init1),
+    ("inotify_add_watch", libc::SYS_inotify_add_watch),
+    ("inotify_rm_watch", libc::SYS_inotify_rm_watch),
+    ("pselect6", libc::SYS_pselect6),
+    ("ppoll", libc::SYS_ppoll),
+    ("splice", libc::SYS_splice),
+    ("tee", libc::SYS_tee),
+    ("vmsplice", libc::SYS_vmsplice),
+    ("preadv", libc::SYS_preadv),
+    ("pwritev", libc::SYS_pwritev),
+    ("preadv2", libc::SYS_preadv2),
+    ("pwritev2", libc::SYS_pwritev2),
+    ("copy_file_range", libc::SYS_copy_file_range),
+    ("unshare", libc::SYS_unshare),
+    ("setns", libc::SYS_setns),
+    ("seccomp", libc::SYS_seccomp),
+    ("getrandom", libc::SYS_getrandom),
+    ("memfd_create", libc::SYS_memfd_create),
+    ("membarrier", libc::SYS_membarrier),
+    ("process_vm_readv", libc::SYS_process_vm_readv),
+    ("process_vm_writev", libc::SYS_process_vm_writev),
+    ("pidfd_open", libc::SYS_pidfd_open),
+    ("pidfd_send_signal", libc::SYS_pidfd_send_signal),
+    ("landlock_create_ruleset", libc::SYS_landlock_create_ruleset),
+    ("landlock_add_rule", libc::SYS_landlock_add_rule),
+    ("landlock_restrict_self", libc::SYS_landlock_restrict_self),
+];
+
+/// Legacy syscalls that only exist on x86_64; the generic syscall table used by
+/// aarch64 only provides their `*at` replacements.
+#[cfg(target_arch = "x86_64")]
+const ARCH_SYSCALLS: &[(&str, i64)] = &[
+    ("open", libc::SYS_open),
+    ("creat", libc::SYS_creat),
+    ("stat", libc::SYS_stat),
+    ("lstat", libc::SYS_lstat),
+    ("access", libc::SYS_access),
+    ("poll", libc::SYS_poll),
+    ("select", libc::SYS_select),
+    ("pipe", libc::SYS_pipe),
+    ("dup2", libc::SYS_dup2),
+    ("pause", libc::SYS_pause),
+    ("alarm", libc::SYS_alarm),
+    ("fork", libc::SYS_fork),
+    ("vfork", libc::SYS_vfork),
+    ("getdents", libc::SYS_getdents),
+    ("rename", libc::SYS_rename),
+    ("mkdir", libc::SYS_mkdir),
+    ("rmdir", libc::SYS_rmdir),
+    ("link", libc::SYS_link),
+    ("unlink", libc::SYS_unlink),
+    ("symlink", libc::SYS_symlink),
+    ("readlink", libc::SYS_readlink),
+    ("chmod", libc::SYS_chmod),
+    ("chown", libc::SYS_chown),
+    ("lchown", libc::SYS_lchown),
+    ("mknod", libc::SYS_mknod),
+    ("utime", libc::SYS_utime),
+    ("utimes", libc::SYS_utimes),
+    ("futimesat", libc::SYS_futimesat),
+    ("getpgrp", libc::SYS_getpgrp),
+    ("arch_prctl", libc::SYS_arch_prctl),
+    ("epoll_create", libc::SYS_epoll_create),
+    ("epoll_wait", libc::SYS_epoll_wait),
+    ("eventfd", libc::SYS_eventfd),
+    ("signalfd", libc::SYS_signalfd),
+    ("inotify_init", libc::SYS_inotify_init),
+    ("time", libc::SYS_time),
+];
+
+#[cfg(not(target_arch = "x86_64"))]
+const ARCH_SYSCALLS: &[(&str, i64)] = &[];
+
+fn all_syscalls() -> impl Iterator<Item = &'static (&'static str, i64)> {
+    COMMON_SYSCALLS.iter().chain(ARCH_SYSCALLS.iter())
+}
+
+/// Looks up the number of the syscall called `name` on this architecture.
+pub(crate) fn syscall_number(name: &str) -> Option<i64> {
+    all_syscalls()
+        .find(|(candidate, _)| *candidate == name)
+        .map(|(_, nr)| *nr)
+}
+
+/// Looks up the name of syscall `nr` on this architecture.
+pub(crate) fn syscall_name(nr: i64) -> Option<&'static str> {
+    all_syscalls()
+        .find(|(_, candidate)| *candidate == nr)
+        .map(|(name, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
+
+    #[test]
+    fn table_has_no_duplicate_names() {
+        let mut seen = HashSet::new();
+        for (name, _) in all_syscalls() {
+            assert!(seen.insert(*name), "duplicate entry for {name}");
+        }
+    }
+
+    #[test]
+    fn table_has_no_duplicate_numbers() {
+        let mut seen = HashSet::new();
+        for (name, nr) in all_syscalls() {
+            assert!(seen.insert(*nr), "duplicate entry for {name} ({nr})");
+        }
+    }
+
+    #[test]
+    fn unknown_name_is_not_found() {
+        assert_eq!(syscall_number("not_a_syscall"), None);
+    }
+
+    #[test]
+    fn name_and_number_lookups_agree() {
+        assert_eq!(syscall_number("connect"), Some(libc::SYS_connect));
+        assert_eq!(syscall_name(libc::SYS_connect), Some("connect"));
+        assert_eq!(syscall_name(-1), None);
+    }
+}
@@ -0,0 +1,322 @@
+//! OpenTelemetry spans for the launcher's own work, with the `otel` feature.
+//!
+//! Spans are exported over OTLP/HTTP when `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`
+//! or `OTEL_EXPORTER_OTLP_ENDPOINT` is set, unless `OTEL_SDK_DISABLED=true` or
+//! `OTEL_TRACES_EXPORTER=none`. Once the sandbox is installed the launcher
+//! may have no network, so spans are kept in memory and exported in one go
+//! from outside the sandbox: right before it is installed, or in `--wait`
+//! mode by the launcher once the command has exited. Only `--wait` mode sees
+//! past the exec, so only it reports `filter_installation` and
+//! `command_execution` after `policy_resolution`.
+//!
+//! Without the feature nothing is recorded.
+
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::time::SystemTime;
+
+use tracing::warn;
+
+use crate::cgroup::read_one_byte;
+use crate::wait::ChildReport;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub(crate) enum AttributeValue {
+    Text(String),
+    Int(i64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "otel"), allow(dead_code))]
+pub(crate) struct SpanRecord {
+    name: &'static str,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Telemetry {
+    /// OTLP/HTTP endpoint for traces; `None` when nothing is exported.
+    endpoint: Option<String>,
+    spans: Vec<SpanRecord>,
+    /// In `--wait` mode, a close-on-exec pipe whose write end only the child
+    /// holds, so reading it returns end of file once the child has exec'd.
+    exec_pipe: Option<(OwnedFd, OwnedFd)>,
+    exec_time: Option<SystemTime>,
+}
+
+impl Telemetry {
+    pub(crate) fn from_env() -> Self {
+        let endpoint = if cfg!(feature = "otel") {
+            traces_endpoint(|name| std::env::var(name).ok())
+        } else {
+            None
+        };
+        Self {
+            endpoint,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        name: &'static str,
+        start: SystemTime,
+        attributes: Vec<(&'static str, AttributeValue)>,
+    ) {
+        if self.endpoint.is_some() {
+            self.spans.push(SpanRecord {
+                name,
+                start,
+                end: SystemTime::now(),
+                attributes,
+            });
+        }
+    }
+
+    /// Before the `--wait` fork: sets up the pipe that tells the launcher when
+    /// the child has exec'd.
+    pub(crate) fn prepare_fork(&mut self) {
+        if self.endpoint.is_none() {
+            return;
+        }
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            warn!(
+                "could not trace the command's exec: {}",
+                std::io::Error::last_os_error()
+            );
+            return;
+        }
+        self.exec_pipe =
+            Some(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) });
+    }
+
+    /// Child side of the fork: keeps the write end open until exec closes it.
+    pub(crate) fn into_child(self) {
+        if let Some((read, write)) = self.exec_pipe {
+            drop(read);
+            std::mem::forget(write);
+        }
+    }
+
+    /// Launcher side of the fork: waits until the child has installed the
+    /// sandbox and exec'd the command, and records `filter_installation`.
+    pub(crate) fn wait_for_exec(&mut self) {
+        let Some((read, write)) = self.exec_pipe.take() else {
+            return;
+        };
+        let start = SystemTime::now();
+        drop(write);
+        let _ = read_one_byte(read.as_raw_fd());
+        self.record("filter_installation", start, Vec::new());
+        self.exec_time = Some(SystemTime::now());
+    }
+
+    /// Records `command_execution`, from the exec until the command exited.
+    pub(crate) fn record_exit(&mut self, report: &ChildReport) {
+        let Some(start) = self.exec_time else {
+            return;
+        };
+        let mut attributes = Vec::new();
+        if let Some(exit_code) = report.exit_code {
+            attributes.push(("exit_code", AttributeValue::Int(exit_code.into())));
+        }
+        if let Some(signal) = report.signal {
+            attributes.push(("signal", AttributeValue::Int(signal.into())));
+        }
+        self.record("command_execution", start, attributes);
+    }
+
+    /// Exports the recorded spans, warning if that fails.
+    pub(crate) fn export(self) {
+        #[cfg(feature = "otel")]
+        if let Some(endpoint) = &self.endpoint
+            && let Err(e) = otel::export(endpoint, self.spans)
+        {
+            warn!("could not export spans to {endpoint}: {e}");
+        }
+    }
+}
+
+/// The traces endpoint configured by the standard `OTEL_*` variables, read
+/// through `var`.
+fn traces_endpoint(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let disabled = var("OTEL_SDK_DISABLED").is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        || var("OTEL_TRACES_EXPORTER").is_some_and(|value| value == "none");
+    if disabled {
+        return None;
+    }
+    let non_empty = |name| var(name).filter(|value| !value.is_empty());
+    // Unlike the traces endpoint, the general one is a base URL.
+    non_empty("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").or_else(|| {
+        non_empty("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .map(|endpoint| format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+    })
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::Context;
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::Span as _;
+    use opentelemetry::trace::TraceContextExt as _;
+    use opentelemetry::trace::Tracer as _;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig as _;
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::trace::SpanExporter;
+
+    use super::AttributeValue;
+    use super::SpanRecord;
+
+    /// Name of the service and the root span of each run.
+    pub(super) const SERVICE_NAME: &str = "codex-linux-sandbox";
+
+    pub(super) fn export(endpoint: &str, spans: Vec<SpanRecord>) -> Result<(), String> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let provider = tracer_provider(exporter);
+        emit_spans(&provider, spans);
+        provider.shutdown().map_err(|e| e.to_string())
+    }
+
+    /// A provider that exports each span as soon as it ends, since the
+    /// launcher execs or exits right after.
+    pub(super) fn tracer_provider(exporter: impl SpanExporter + 'static) -> SdkTracerProvider {
+        SdkTracerProvider::builder()
+            .with_simple_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build()
+    }
+
+    /// Emits `spans` as children of one root span covering all of them.
+    pub(super) fn emit_spans(provider: &SdkTracerProvider, spans: Vec<SpanRecord>) {
+        let (Some(start), Some(end)) = (
+            spans.iter().map(|span| span.start).min(),
+            spans.iter().map(|span| span.end).max(),
+        ) else {
+            return;
+        };
+        let tracer = provider.tracer(SERVICE_NAME);
+        let root = tracer
+            .span_builder(SERVICE_NAME)
+            .with_start_time(start)
+            .start(&tracer);
+        let cx = Context::current_with_span(root);
+        for span in spans {
+            let attributes = span.attributes.into_iter().map(|(key, value)| match value {
+                AttributeValue::Text(value) => KeyValue::new(key, value),
+                AttributeValue::Int(value) => KeyValue::new(key, value),
+            });
+            tracer
+                .span_builder(span.name)
+                .with_start_time(span.start)
+                .with_attributes(attributes)
+                .start_with_context(&tracer, &cx)
+                .end_with_timestamp(span.end);
+        }
+        cx.span().end_with_timestamp(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn endpoint(vars: &[(&str, &str)]) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect();
+        traces_endpoint(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn traces_endpoint_follows_the_otel_variables() {
+        assert_eq!(endpoint(&[]), None);
+        assert_eq!(
+            endpoint(&[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318/")]),
+            Some("http://collector:4318/v1/traces".to_string())
+        );
+        assert_eq!(
+            endpoint(&[
+                ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                (
+                    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+                    "http://traces:4318/spans"
+                ),
+            ]),
+            Some("http://traces:4318/spans".to_string())
+        );
+        assert_eq!(
+            endpoint(&[
+                ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                ("OTEL_SDK_DISABLED", "true"),
+            ]),
+            None
+        );
+        assert_eq!(
+            endpoint(&[
+                ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318"),
+                ("OTEL_TRACES_EXPORTER", "none"),
+            ]),
+            None
+        );
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn spans_share_one_trace_under_a_root_span() {
+        use opentelemetry::KeyValue;
+        use opentelemetry_sdk::trace::InMemorySpanExporter;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = otel::tracer_provider(exporter.clone());
+        let start = SystemTime::now();
+        otel::emit_spans(
+            &provider,
+            vec![
+                SpanRecord {
+                    name: "policy_resolution",
+                    start,
+                    end: start,
+                    attributes: vec![("backend", AttributeValue::Text("landlock".to_string()))],
+                },
+                SpanRecord {
+                    name: "command_execution",
+                    start,
+                    end: start,
+                    attributes: vec![("exit_code", AttributeValue::Int(3))],
+                },
+            ],
+        );
+        provider.force_flush().expect("flush spans");
+
+        let spans = exporter.get_finished_spans().expect("finished spans");
+        let names: Vec<_> = spans.iter().map(|span| span.name.to_string()).collect();
+        assert_eq!(
+            names,
+            vec!["policy_resolution", "command_execution", otel::SERVICE_NAME]
+        );
+        let root = &spans[2];
+        for child in &spans[..2] {
+            assert_eq!(child.span_context.trace_id(), root.span_context.trace_id());
+            assert_eq!(child.parent_span_id, root.span_context.span_id());
+        }
+        assert_eq!(
+            spans[0].attributes,
+            vec![KeyValue::new("backend", "landlock")]
+        );
+        assert_eq!(spans[1].attributes, vec![KeyValue::new("exit_code", 3_i64)]);
+    }
+}
@@ -0,0 +1,643 @@
+//! `--verify`: installs the sandbox in a forked probe child, checks that a
+//! few operations the policy forbids actually fail there, and reports the
+//! results without running the command.
+//!
+//! Where `--dry-run` only shows what would be installed, the probes show what
+//! the kernel enforces:
+//!
+//! - `write_outside_roots` creates a file in a fresh directory outside every
+//!   writable root, which the launcher itself may write to.
+//! - `connect_to_host` connects to a listener the launcher opens on the
+//!   host's loopback, which neither a blocked network nor a private network
+//!   namespace can reach.
+//!
+//! The syscall allowlist is not installed in the probe child, since it would
+//! deny the probes themselves whether or not the rest of the sandbox holds.
+//!
+//! Each probe runs on a thread of its own, which inherits the sandbox, and
+//! gets `--probe-timeout` to finish. A connect that gets no answer in time
+//! counts as denied: a sandbox may drop the packets rather than fail the
+//! call. Any other probe that times out is inconclusive.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Error;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::os::fd::FromRawFd;
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use codex_core::protocol::SandboxPolicy;
+
+use crate::enforcement::SandboxEnforcement;
+use crate::json::json_string;
+use crate::landlock::LandlockRules;
+use crate::landlock::NetworkMode;
+use crate::landlock::SandboxOptions;
+
+/// Default for `--probe-timeout`: far longer than a probe takes when the
+/// kernel answers at all.
+pub(crate) const DEFAULT_PROBE_TIMEOUT_MILLIS: u64 = 1000;
+
+/// Where a directory outside the writable roots is looked for, in order.
+const PROBE_DIR_BASES: &[&str] = &["/tmp", "/var/tmp"];
+
+/// One operation a probe attempts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Probe {
+    /// Create this file, and remove it again if that worked.
+    CreateFile(PathBuf),
+    /// Open a TCP connection to this address.
+    Connect(SocketAddr),
+}
+
+impl Probe {
+    /// Attempts the operation on the real kernel, giving up after
+    /// `timeout`.
+    fn attempt(&self, timeout: Duration) -> std::io::Result<()> {
+        let probe = self.clone();
+        with_timeout(timeout, move || match probe {
+            Self::CreateFile(path) => {
+                File::create_new(&path)?;
+                std::fs::remove_file(&path)
+            }
+            Self::Connect(addr) => TcpStream::connect_timeout(&addr, timeout).map(drop),
+        })
+    }
+
+    /// Errors a sandbox that denies the operation fails it with.
+    fn denial_errnos(&self) -> &'static [i32] {
+        match self {
+            // Landlock fails with `EACCES`, seccomp with `EPERM`.
+            Self::CreateFile(_) => &[libc::EACCES, libc::EPERM],
+            // The seccomp network filter fails the socket call with `EPERM`;
+            // in a private network namespace nothing listens on the address.
+            Self::Connect(_) => &[
+                libc::EPERM,
+                libc::EACCES,
+                libc::ECONNREFUSED,
+                libc::ENETUNREACH,
+            ],
+        }
+    }
+
+    fn outcome(&self, attempt: std::io::Result<()>) -> ProbeOutcome {
+        match attempt {
+            Ok(()) => ProbeOutcome::Allowed,
+            Err(e)
+                if e.raw_os_error()
+                    .is_some_and(|errno| self.denial_errnos().contains(&errno)) =>
+            {
+                ProbeOutcome::Denied
+            }
+            // A timeout is `TimedOut` with no errno, from `connect_timeout`
+            // or `with_timeout`.
+            Err(e)
+                if matches!(self, Self::Connect(_)) && e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                ProbeOutcome::Denied
+            }
+            Err(e) => ProbeOutcome::Inconclusive(e.to_string()),
+        }
+    }
+}
+
+/// Runs `attempt` on a new thread and waits up to `timeout` for it. A probe
+/// stuck in the kernel is left behind; the probe child exits without it.
+fn with_timeout(
+    timeout: Duration,
+    attempt: impl FnOnce() -> std::io::Result<()> + Send + 'static,
+) -> std::io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("verify-probe".to_string())
+        .spawn(move || {
+            let _ = sender.send(attempt());
+        })?;
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("no answer within {} ms", timeout.as_millis()),
+        ))
+    })
+}
+
+/// What a probe found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProbeOutcome {
+    /// The operation failed the way the sandbox fails it.
+    Denied,
+    /// The operation succeeded.
+    Allowed,
+    /// The operation failed for some other reason, so it shows nothing.
+    Inconclusive(String),
+    /// There was nothing to probe with on this host.
+    Skipped(String),
+}
+
+impl ProbeOutcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Denied => "denied",
+            Self::Allowed => "allowed",
+            Self::Inconclusive(_) => "inconclusive",
+            Self::Skipped(_) => "skipped",
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Self::Denied | Self::Allowed => None,
+            Self::Inconclusive(detail) | Self::Skipped(detail) => Some(detail),
+        }
+    }
+
+    fn encode(&self) -> String {
+        match self.detail() {
+            Some(detail) => format!("{}\t{}", self.name(), detail.replace('\n', " ")),
+            None => self.name().to_string(),
+        }
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let (name, detail) = line.split_once('\t').unwrap_or((line, ""));
+        match name {
+            "denied" => Some(Self::Denied),
+            "allowed" => Some(Self::Allowed),
+            "inconclusive" => Some(Self::Inconclusive(detail.to_string())),
+            "skipped" => Some(Self::Skipped(detail.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A probe the plan runs, and whether the policy forbids its operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Check {
+    pub(crate) name: &'static str,
+    pub(crate) should_deny: bool,
+    /// Why there is nothing to probe with, when there is not.
+    pub(crate) probe: Result<Probe, String>,
+}
+
+/// The probes for one run, with what they need from the host set up before
+/// the fork.
+#[derive(Debug)]
+pub(crate) struct VerifyPlan {
+    checks: Vec<Check>,
+    /// Removed with everything in it once the probes ran.
+    probe_dir: Option<PathBuf>,
+    /// Kept open until the probes ran, so the connect probe has a peer.
+    _listener: Option<TcpListener>,
+    /// How long each probe may take, for `--probe-timeout`.
+    probe_timeout: Duration,
+}
+
+impl VerifyPlan {
+    /// Prepares the probes for `sandbox_policy` while the launcher is still
+    /// unrestricted.
+    pub(crate) fn prepare(
+        sandbox_policy: &SandboxPolicy,
+        options: &SandboxOptions,
+        cwd: &Path,
+        abi: i32,
+        probe_timeout: Duration,
+    ) -> Self {
+        let writable_roots = LandlockRules::for_policy(
+            sandbox_policy,
+            options,
+            cwd,
+            abi,
+            &mut SandboxEnforcement::default(),
+        )
+        .ok()
+        .flatten()
+        .map(|rules| rules.writable_roots)
+        .unwrap_or_default();
+        let probe_dir = create_probe_dir(&writable_roots);
+        let write = Check {
+            name: "write_outside_roots",
+            should_deny: !sandbox_policy.has_full_disk_write_access(),
+            probe: match &probe_dir {
+                Ok(dir) => Ok(Probe::CreateFile(dir.join("probe"))),
+                Err(e) => Err(e.clone()),
+            },
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0");
+        let connect = Check {
+            name: "connect_to_host",
+            should_deny: options.network != NetworkMode::Full,
+            probe: match listener.as_ref().map(TcpListener::local_addr) {
+                Ok(Ok(addr)) => Ok(Probe::Connect(addr)),
+                Ok(Err(e)) => Err(format!("could not listen on the host's loopback: {e}")),
+                Err(e) => Err(format!("could not listen on the host's loopback: {e}")),
+            },
+        };
+
+        Self {
+            checks: vec![write, connect],
+            probe_dir: probe_dir.ok(),
+            _listener: listener.ok(),
+            probe_timeout,
+        }
+    }
+
+    /// Runs each probe with `attempt` standing in for the kernel.
+    fn run_with(&self, attempt: impl Fn(&Probe) -> std::io::Result<()>) -> Vec<ProbeOutcome> {
+        self.checks
+            .iter()
+            .map(|check| match &check.probe {
+                Ok(probe) => probe.outcome(attempt(probe)),
+                Err(reason) => ProbeOutcome::Skipped(reason.clone()),
+            })
+            .collect()
+    }
+
+    /// Forks a probe child that calls `install` and then runs the probes,
+    /// and collects what it found. The launcher itself stays unrestricted.
+    pub(crate) fn run_in_child(
+        self,
+        install: impl FnOnce() -> Result<(), String>,
+    ) -> std::io::Result<VerifyReport> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        let (mut read, mut write) =
+            unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+        match unsafe { libc::fork() } {
+            -1 => Err(Error::last_os_error()),
+            0 => {
+                drop(read);
+                let message = match install() {
+                    Ok(()) => {
+                        let outcomes = self.run_with(|probe| probe.attempt(self.probe_timeout));
+                        std::iter::once("installed".to_string())
+                            .chain(outcomes.iter().map(ProbeOutcome::encode))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    }
+                    Err(e) => format!("install-failed\t{}", e.replace('\n', " ")),
+                };
+                let code = i32::from(write.write_all(message.as_bytes()).is_err());
+                unsafe { libc::_exit(code) }
+            }
+            pid => {
+                drop(write);
+                let mut message = String::new();
+                let read_result = read.read_to_string(&mut message);
+                let mut status = 0;
+                unsafe { libc::waitpid(pid, &mut status, 0) };
+                read_result?;
+                Ok(self.report(&message))
+            }
+        }
+    }
+
+    /// Pairs the checks with what the probe child sent in `message`.
+    fn report(&self, message: &str) -> VerifyReport {
+        let mut lines = message.lines();
+        let install_error = match lines.next() {
+            Some("installed") => None,
+            Some(line) => Some(
+                line.strip_prefix("install-failed\t")
+                    .unwrap_or(line)
+                    .to_string(),
+            ),
+            None => Some("the probe child exited before reporting".to_string()),
+        };
+        let results = match install_error {
+            Some(_) => Vec::new(),
+            None => self
+                .checks
+                .iter()
+                .map(|check| ProbeResult {
+                    name: check.name,
+                    should_deny: check.should_deny,
+                    outcome: lines
+                        .next()
+                        .and_then(ProbeOutcome::decode)
+                        .unwrap_or_else(|| {
+                            ProbeOutcome::Inconclusive(
+                                "the probe child did not report it".to_string(),
+                            )
+                        }),
+                })
+                .collect(),
+        };
+        VerifyReport {
+            install_error,
+            results,
+        }
+    }
+}
+
+impl Drop for VerifyPlan {
+    fn drop(&mut self) {
+        if let Some(probe_dir) = &self.probe_dir {
+            let _ = std::fs::remove_dir_all(probe_dir);
+        }
+    }
+}
+
+/// A fresh directory outside `writable_roots`, under the first base that is
+/// not inside one of them.
+fn create_probe_dir(writable_roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let base = PROBE_DIR_BASES
+        .iter()
+        .filter_map(|base| Path::new(base).canonicalize().ok())
+        .find(|base| !writable_roots.iter().any(|root| base.starts_with(root)))
+        .ok_or("no directory outside the writable roots to write to")?;
+    let mut template = base.join("codex-verify.XXXXXX").into_os_string().into_vec();
+    template.push(0);
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(format!(
+            "could not create a directory in {}: {}",
+            base.display(),
+            Error::last_os_error()
+        ));
+    }
+    template.pop();
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(template)))
+}
+
+/// One probe's result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProbeResult {
+    pub(crate) name: &'static str,
+    pub(crate) should_deny: bool,
+    pub(crate) outcome: ProbeOutcome,
+}
+
+impl ProbeResult {
+    /// Whether the kernel did what the policy says. A skipped probe shows
+    /// nothing wrong.
+    pub(crate) fn as_expected(&self) -> bool {
+        match self.outcome {
+            ProbeOutcome::Denied => self.should_deny,
+            ProbeOutcome::Allowed => !self.should_deny,
+            ProbeOutcome::Inconclusive(_) => false,
+            ProbeOutcome::Skipped(_) => true,
+        }
+    }
+}
+
+/// What `--verify` prints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VerifyReport {
+    /// Why the sandbox could not be installed in the probe child.
+    pub(crate) install_error: Option<String>,
+    pub(crate) results: Vec<ProbeResult>,
+}
+
+impl VerifyReport {
+    /// Whether the sandbox was installed and every probe came out as the
+    /// policy says.
+    pub(crate) fn passed(&self) -> bool {
+        self.install_error.is_none() && self.results.iter().all(ProbeResult::as_expected)
+    }
+
+    pub(crate) fn to_json(&self, backend: &str) -> String {
+        let mut fields = vec![
+            format!("\"backend\":{}", json_string(backend)),
+            format!("\"installed\":{}", self.install_error.is_none()),
+        ];
+        if let Some(install_error) = &self.install_error {
+            fields.push(format!("\"error\":{}", json_string(install_error)));
+        }
+        fields.push(format!("\"passed\":{}", self.passed()));
+        let probes: Vec<String> = self
+            .results
+            .iter()
+            .map(|result| {
+                let expected = if result.should_deny {
+                    "denied"
+                } else {
+                    "allowed"
+                };
+                let mut probe = format!(
+                    "{{\"name\":{},\"expected\":{},\"result\":{},\"as_expected\":{}",
+                    json_string(result.name),
+                    json_string(expected),
+                    json_string(result.outcome.name()),
+                    result.as_expected()
+                );
+                if let Some(detail) = result.outcome.detail() {
+                    let _ = write!(probe, ",\"detail\":{}", json_string(detail));
+                }
+                probe.push('}');
+                probe
+            })
+            .collect();
+        fields.push(format!("\"probes\":[{}]", probes.join(",")));
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use pretty_assertions::assert_ne;
+
+    fn plan(should_deny: bool) -> VerifyPlan {
+        VerifyPlan {
+            checks: vec![
+                Check {
+                    name: "write_outside_roots",
+                    should_deny,
+                    probe: Ok(Probe::CreateFile(PathBuf::from("/outside/probe"))),
+                },
+                Check {
+                    name: "connect_to_host",
+                    should_deny,
+                    probe: Ok(Probe::Connect(SocketAddr::from(([127, 0, 0, 1], 9)))),
+                },
+            ],
+            probe_dir: None,
+            _listener: None,
+            probe_timeout: Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MILLIS),
+        }
+    }
+
+    /// A kernel that enforces the sandbox fails both probes the way Landlock
+    /// and seccomp do.
+    fn enforcing_kernel(probe: &Probe) -> std::io::Result<()> {
+        Err(Error::from_raw_os_error(match probe {
+            Probe::CreateFile(_) => libc::EACCES,
+            Probe::Connect(_) => libc::EPERM,
+        }))
+    }
+
+    fn report(plan: &VerifyPlan, outcomes: Vec<ProbeOutcome>) -> VerifyReport {
+        let message = std::iter::once("installed".to_string())
+            .chain(outcomes.iter().map(ProbeOutcome::encode))
+            .collect::<Vec<_>>()
+            .join("\n");
+        plan.report(&message)
+    }
+
+    #[test]
+    fn an_enforcing_kernel_passes() {
+        let plan = plan(true);
+        let report = report(&plan, plan.run_with(enforcing_kernel));
+
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|result| (result.name, result.outcome.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("write_outside_roots", ProbeOutcome::Denied),
+                ("connect_to_host", ProbeOutcome::Denied),
+            ]
+        );
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn a_kernel_that_enforces_nothing_fails() {
+        let plan = plan(true);
+        let report = report(&plan, plan.run_with(|_| Ok(())));
+
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(ProbeResult::as_expected)
+                .collect::<Vec<_>>(),
+            vec![false, false]
+        );
+        assert!(!report.passed());
+        assert_eq!(
+            report.to_json("landlock"),
+            r#"{"backend":"landlock","installed":true,"passed":false,"probes":[{"name":"write_outside_roots","expected":"denied","result":"allowed","as_expected":false},{"name":"connect_to_host","expected":"denied","result":"allowed","as_expected":false}]}"#
+        );
+    }
+
+    #[test]
+    fn unrelated_errors_are_inconclusive() {
+        let plan = plan(true);
+        let outcomes = plan.run_with(|_| Err(Error::from_raw_os_error(libc::EIO)));
+
+        assert!(
+            outcomes
+                .iter()
+                .all(|outcome| matches!(outcome, ProbeOutcome::Inconclusive(_))),
+            "{outcomes:?}"
+        );
+        assert!(!report(&plan, outcomes).passed());
+    }
+
+    #[test]
+    fn allowed_operations_must_get_through() {
+        let plan = plan(false);
+
+        assert!(report(&plan, plan.run_with(|_| Ok(()))).passed());
+        assert!(!report(&plan, plan.run_with(enforcing_kernel)).passed());
+    }
+
+    #[test]
+    fn probes_run_in_a_child_without_the_sandbox() {
+        let policy = SandboxPolicy::ReadOnly;
+        let options = SandboxOptions::for_policy(&policy);
+        let cwd = std::env::temp_dir();
+        let plan = VerifyPlan::prepare(
+            &policy,
+            &options,
+            &cwd,
+            0,
+            Duration::from_millis(DEFAULT_PROBE_TIMEOUT_MILLIS),
+        );
+        let probe_dir = plan.probe_dir.clone();
+
+        let report = plan.run_in_child(|| Ok(())).expect("run the probes");
+
+        // Nothing was installed, so every operation went through.
+        assert_eq!(report.install_error, None);
+        assert!(
+            report
+                .results
+                .iter()
+                .all(|result| result.outcome == ProbeOutcome::Allowed),
+            "{report:?}"
+        );
+        assert!(!report.passed());
+        if let Some(probe_dir) = probe_dir {
+            assert!(!probe_dir.exists());
+        }
+    }
+
+    #[test]
+    fn install_failures_are_reported() {
+        let plan = plan(true);
+
+        let report = plan
+            .run_in_child(|| Err("Landlock is not supported".to_string()))
+            .expect("run the probes");
+
+        assert_eq!(
+            report,
+            VerifyReport {
+                install_error: Some("Landlock is not supported".to_string()),
+                results: Vec::new(),
+            }
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn a_hung_probe_times_out() {
+        let started = std::time::Instant::now();
+        let result = with_timeout(Duration::from_millis(50), || {
+            std::thread::sleep(Duration::from_secs(30));
+            Ok(())
+        });
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let err = result.expect_err("the probe should time out");
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "no answer within 50 ms");
+    }
+
+    #[test]
+    fn timeouts_deny_a_connect_but_not_a_write() {
+        let timed_out = || {
+            Err(Error::new(
+                std::io::ErrorKind::TimedOut,
+                "no answer within 50 ms",
+            ))
+        };
+        let connect = Probe::Connect(SocketAddr::from(([192, 0, 2, 1], 9)));
+        let create = Probe::CreateFile(PathBuf::from("/outside/probe"));
+
+        assert_eq!(connect.outcome(timed_out()), ProbeOutcome::Denied);
+        assert_eq!(
+            create.outcome(timed_out()),
+            ProbeOutcome::Inconclusive("no answer within 50 ms".to_string())
+        );
+    }
+
+    #[test]
+    fn a_connect_to_a_black_holed_address_finishes_within_the_timeout() {
+        // TEST-NET-1 is never routed: the connect gets no answer, or fails
+        // at once on a host without a route.
+        let probe = Probe::Connect(SocketAddr::from(([192, 0, 2, 1], 9)));
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+
+        let outcome = probe.outcome(probe.attempt(timeout));
+
+        assert!(started.elapsed() < timeout * 5, "{:?}", started.elapsed());
+        assert_ne!(outcome, ProbeOutcome::Allowed);
+    }
+}
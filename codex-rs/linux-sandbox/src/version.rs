@@ -0,0 +1,135 @@
+//! `codex-linux-sandbox version`: the launcher's version and what it can
+//! enforce, so integrators can check compatibility before relying on it.
+//!
+//! Unlike `preflight`, this describes the launcher rather than a policy.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::bwrap::BWRAP_PATH;
+use crate::bwrap::BWRAP_VERSION;
+use crate::bwrap::BwrapVersion;
+use crate::json::json_string;
+use crate::landlock::LANDLOCK_FULL_FILESYSTEM_ABI;
+
+#[derive(Debug, Parser)]
+#[command(name = "codex-linux-sandbox version")]
+pub struct VersionCommand {
+    /// Print the report as a single JSON object.
+    #[arg(long = "json")]
+    pub json: bool,
+}
+
+/// Parses `args` (program name first, without the `version` subcommand) and
+/// prints the report.
+pub(crate) fn run_version_main(args: impl IntoIterator<Item = OsString>) -> ! {
+    let VersionCommand { json } = VersionCommand::parse_from(args);
+    let report = VersionReport::detect();
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.render());
+    }
+    std::process::exit(0);
+}
+
+/// Oldest Landlock ABI the launcher uses. Later ABIs add rights, which are
+/// requested on a best-effort basis up to [`LANDLOCK_FULL_FILESYSTEM_ABI`].
+const LANDLOCK_MIN_ABI: i32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VersionReport {
+    pub(crate) version: &'static str,
+    /// The seccomp network filter and syscall allowlists are always built
+    /// in; whether the kernel supports them is for `preflight` to say.
+    pub(crate) seccomp: bool,
+    pub(crate) otel: bool,
+    pub(crate) bwrap: Option<PathBuf>,
+    pub(crate) bwrap_version: Option<BwrapVersion>,
+}
+
+impl VersionReport {
+    fn detect() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            seccomp: true,
+            otel: cfg!(feature = "otel"),
+            bwrap: BWRAP_PATH.clone(),
+            bwrap_version: *BWRAP_VERSION,
+        }
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let built_in = |enabled| if enabled { "built in" } else { "not built" };
+        let bwrap = match (&self.bwrap, self.bwrap_version) {
+            (Some(path), Some(version)) => format!("{} ({version})", path.display()),
+            (Some(path), None) => format!("{} (unknown version)", path.display()),
+            (None, _) => "not found on PATH".to_string(),
+        };
+        format!(
+            "codex-linux-sandbox {}\nlandlock ABI: {LANDLOCK_MIN_ABI}-{LANDLOCK_FULL_FILESYSTEM_ABI}\nseccomp: {}\notel: {}\nbwrap fallback: {bwrap}\n",
+            self.version,
+            built_in(self.seccomp),
+            built_in(self.otel),
+        )
+    }
+
+    /// Single-line JSON object.
+    pub(crate) fn to_json(&self) -> String {
+        let or_null = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        format!(
+            r#"{{"version":{},"landlock_abi":{{"min":{LANDLOCK_MIN_ABI},"max":{LANDLOCK_FULL_FILESYSTEM_ABI}}},"seccomp":{},"otel":{},"bwrap":{{"available":{},"path":{},"version":{}}}}}"#,
+            json_string(self.version),
+            self.seccomp,
+            self.otel,
+            self.bwrap.is_some(),
+            or_null(
+                self.bwrap
+                    .as_ref()
+                    .map(|path| json_string(&path.to_string_lossy()))
+            ),
+            or_null(
+                self.bwrap_version
+                    .map(|version| json_string(&version.to_string()))
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn report(bwrap: Option<&str>, bwrap_version: Option<BwrapVersion>) -> VersionReport {
+        VersionReport {
+            version: "1.2.3",
+            seccomp: true,
+            otel: false,
+            bwrap: bwrap.map(PathBuf::from),
+            bwrap_version,
+        }
+    }
+
+    #[test]
+    fn json_lists_version_and_capabilities() {
+        assert_eq!(
+            report(Some("/usr/bin/bwrap"), Some(BwrapVersion(0, 8, 0))).to_json(),
+            r#"{"version":"1.2.3","landlock_abi":{"min":1,"max":5},"seccomp":true,"otel":false,"bwrap":{"available":true,"path":"/usr/bin/bwrap","version":"0.8.0"}}"#
+        );
+        assert_eq!(
+            report(None, None).to_json(),
+            r#"{"version":"1.2.3","landlock_abi":{"min":1,"max":5},"seccomp":true,"otel":false,"bwrap":{"available":false,"path":null,"version":null}}"#
+        );
+    }
+
+    #[test]
+    fn text_report() {
+        assert_eq!(
+            report(Some("/usr/bin/bwrap"), None).render(),
+            "codex-linux-sandbox 1.2.3\nlandlock ABI: 1-5\nseccomp: built in\notel: not built\nbwrap fallback: /usr/bin/bwrap (unknown version)\n"
+        );
+    }
+}
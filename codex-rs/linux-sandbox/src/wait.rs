@@ -0,0 +1,294 @@
+//! `--wait` mode: the launcher forks, the child installs the sandbox and execs
+//! the command, and the launcher waits for it so it can report how it exited
+//! and what resources it used.
+
+use std::fs::File;
+use std::io::ErrorKind;
+use std::io::Write as _;
+use std::os::fd::FromRawFd;
+use std::os::fd::RawFd;
+use std::path::Path;
+
+use tracing::warn;
+
+use crate::cgroup::CommandCgroup;
+use crate::enforcement::EnforcementVerdict;
+use crate::forward_output::OutputPipes;
+use crate::json::json_string;
+use crate::manifest::ManifestFile;
+use crate::manifest::RunManifest;
+use crate::self_test::VerdictPipe;
+use crate::telemetry::Telemetry;
+
+/// How the command ended, as collected by `wait4`, and whether it ran
+/// sandboxed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChildReport {
+    /// Exit status, when the command exited normally.
+    pub(crate) exit_code: Option<i32>,
+    /// Terminating signal, when the command was killed by one.
+    pub(crate) signal: Option<i32>,
+    pub(crate) rusage: ChildRusage,
+    /// Peak memory usage of the command's cgroup, in bytes, when it ran in
+    /// one that accounts memory.
+    pub(crate) memory_peak: Option<u64>,
+    /// The verdict the child sent after its self-test.
+    pub(crate) enforcement: EnforcementVerdict,
+}
+
+/// The subset of `struct rusage` included in the report. It covers the
+/// command and every descendant it waited for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ChildRusage {
+    /// User CPU time, in microseconds.
+    pub(crate) utime: i64,
+    /// System CPU time, in microseconds.
+    pub(crate) stime: i64,
+    /// Peak resident set size, in KiB.
+    pub(crate) maxrss: i64,
+    /// Page faults served without I/O.
+    pub(crate) minflt: i64,
+    /// Page faults that required I/O.
+    pub(crate) majflt: i64,
+}
+
+impl ChildRusage {
+    fn from_rusage(rusage: &libc::rusage) -> Self {
+        let micros = |time: libc::timeval| time.tv_sec * 1_000_000 + time.tv_usec;
+        Self {
+            utime: micros(rusage.ru_utime),
+            stime: micros(rusage.ru_stime),
+            maxrss: rusage.ru_maxrss,
+            minflt: rusage.ru_minflt,
+            majflt: rusage.ru_majflt,
+        }
+    }
+}
+
+impl ChildReport {
+    fn from_wait_status(
+        status: libc::c_int,
+        rusage: ChildRusage,
+        enforcement: EnforcementVerdict,
+    ) -> Self {
+        Self {
+            exit_code: libc::WIFEXITED(status).then(|| libc::WEXITSTATUS(status)),
+            signal: libc::WIFSIGNALED(status).then(|| libc::WTERMSIG(status)),
+            rusage,
+            memory_peak: None,
+            enforcement,
+        }
+    }
+
+    /// Exit code for the launcher, using the shell's `128 + signal`
+    /// convention for a command killed by a signal.
+    pub(crate) fn launcher_exit_code(&self) -> i32 {
+        match (self.exit_code, self.signal) {
+            (Some(exit_code), _) => exit_code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => 1,
+        }
+    }
+
+    /// Single-line JSON object written to `--report-fd`.
+    pub(crate) fn to_json(&self) -> String {
+        let ChildRusage {
+            utime,
+            stime,
+            maxrss,
+            minflt,
+            majflt,
+        } = self.rusage;
+        // Left out rather than `null` without a cgroup, so the report only
+        // changes for runs that have one.
+        let memory_peak = self
+            .memory_peak
+            .map_or_else(String::new, |peak| format!(r#","memory_peak":{peak}"#));
+        format!(
+            r#"{{"exit_code":{},"signal":{},"rusage":{{"utime":{utime},"stime":{stime},"maxrss":{maxrss},"minflt":{minflt},"majflt":{majflt}}}{memory_peak},"enforced":{},"enforcement_reason":{}}}"#,
+            json_number(self.exit_code),
+            json_number(self.signal),
+            self.enforcement.enforced,
+            json_string(&self.enforcement.reason),
+        )
+    }
+}
+
+fn json_number(value: Option<i32>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
+
+/// Forwards the command's output if it goes to `output_pipes`, waits for
+/// `pid`, writes the report to `report_fd` if there is one, and exits with
+/// the command's status. The command's cgroup, if any, is removed
+/// once it has exited and its peak memory usage is read, the report is added
+/// to the manifest, and the recorded spans are exported. `private_tmp` is a
+/// directory the launcher created for the command, removed with whatever the
+/// command left in it once it has exited.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn wait_for_child_and_exit(
+    pid: libc::pid_t,
+    report_fd: Option<RawFd>,
+    cgroup: Option<CommandCgroup>,
+    private_tmp: Option<&Path>,
+    manifest: Option<(ManifestFile, RunManifest)>,
+    verdict_pipe: VerdictPipe,
+    output_pipes: Option<OutputPipes>,
+    mut telemetry: Telemetry,
+) -> ! {
+    telemetry.wait_for_exec();
+    let enforcement = verdict_pipe.receive();
+    // Until the command, and whatever it started that kept its stdout or
+    // stderr, has closed them.
+    if let Some(output_pipes) = output_pipes
+        && let Err(e) = output_pipes.forward()
+    {
+        warn!("stopped forwarding the command's output: {e}");
+    }
+    let mut report = match wait_for_child(pid, enforcement) {
+        Ok(report) => report,
+        Err(e) => panic!("error waiting for the command: {e}"),
+    };
+    if let Some(cgroup) = cgroup {
+        report.memory_peak = cgroup.memory_peak();
+        cgroup.remove();
+    }
+    if let Some(private_tmp) = private_tmp
+        && let Err(e) = std::fs::remove_dir_all(private_tmp)
+    {
+        warn!("could not remove {}: {e}", private_tmp.display());
+    }
+
+    if let Some(report_fd) = report_fd {
+        // The fd was handed to us for this report alone, so it is ours to
+        // close.
+        let mut file = unsafe { File::from_raw_fd(report_fd) };
+        if let Err(e) = writeln!(file, "{}", report.to_json()) {
+            warn!("could not write the report to fd {report_fd}: {e}");
+        }
+    }
+    if let Some((mut file, manifest)) = manifest
+        && let Err(e) = manifest.write(&mut file, Some(&report.enforcement), Some(&report))
+    {
+        warn!("could not add the exit report to {file}: {e}");
+    }
+
+    telemetry.record_exit(&report);
+    telemetry.export();
+
+    std::process::exit(report.launcher_exit_code());
+}
+
+fn wait_for_child(
+    pid: libc::pid_t,
+    enforcement: EnforcementVerdict,
+) -> std::io::Result<ChildReport> {
+    let mut status: libc::c_int = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } == pid {
+            break;
+        }
+        let err = std::io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    Ok(ChildReport::from_wait_status(
+        status,
+        ChildRusage::from_rusage(&rusage),
+        enforcement,
+    ))
+}
+
+/// Marks an fd handed to the launcher (`--report-fd`, `--freeze-until-ready`,
+/// `--events-fd`) close-on-exec, so only the launcher holds it and the command
+/// cannot use it. Fails if it is not an open fd.
+pub(crate) fn set_cloexec(fd: RawFd) -> std::io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const RUSAGE: ChildRusage = ChildRusage {
+        utime: 1_500_000,
+        stime: 20_000,
+        maxrss: 4096,
+        minflt: 300,
+        majflt: 2,
+    };
+
+    #[test]
+    fn reports_a_normal_exit() {
+        // Wait status encoding of `exit(3)`.
+        let report =
+            ChildReport::from_wait_status(3 << 8, RUSAGE, EnforcementVerdict::unsandboxed());
+
+        assert_eq!(
+            report,
+            ChildReport {
+                exit_code: Some(3),
+                signal: None,
+                rusage: RUSAGE,
+                memory_peak: None,
+                enforcement: EnforcementVerdict::unsandboxed(),
+            }
+        );
+        assert_eq!(report.launcher_exit_code(), 3);
+        assert_eq!(
+            report.to_json(),
+            r#"{"exit_code":3,"signal":null,"rusage":{"utime":1500000,"stime":20000,"maxrss":4096,"minflt":300,"majflt":2},"enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox"}"#
+        );
+    }
+
+    #[test]
+    fn reports_death_by_signal() {
+        let report = ChildReport::from_wait_status(
+            libc::SIGKILL,
+            ChildRusage::default(),
+            EnforcementVerdict::never_reported(),
+        );
+
+        assert_eq!(
+            report,
+            ChildReport {
+                exit_code: None,
+                signal: Some(libc::SIGKILL),
+                rusage: ChildRusage::default(),
+                memory_peak: None,
+                enforcement: EnforcementVerdict::never_reported(),
+            }
+        );
+        assert_eq!(report.launcher_exit_code(), 128 + libc::SIGKILL);
+        assert_eq!(
+            report.to_json(),
+            r#"{"exit_code":null,"signal":9,"rusage":{"utime":0,"stime":0,"maxrss":0,"minflt":0,"majflt":0},"enforced":false,"enforcement_reason":"the command's process exited before the sandbox was verified"}"#
+        );
+    }
+
+    #[test]
+    fn reports_the_memory_peak_of_the_cgroup() {
+        let report = ChildReport {
+            memory_peak: Some(33_554_432),
+            ..ChildReport::from_wait_status(0, RUSAGE, EnforcementVerdict::unsandboxed())
+        };
+
+        assert_eq!(
+            report.to_json(),
+            r#"{"exit_code":0,"signal":null,"rusage":{"utime":1500000,"stime":20000,"maxrss":4096,"minflt":300,"majflt":2},"memory_peak":33554432,"enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox"}"#
+        );
+    }
+
+    #[test]
+    fn launcher_fds_must_be_open() {
+        let err = set_cloexec(12345).expect_err("fd is not open");
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    }
+}
@@ -0,0 +1,509 @@
+//! Resolution of the policy's writable roots against the sandbox policy cwd.
+//!
+//! Relative writable roots are joined onto `sandbox_policy_cwd` (not the
+//! launcher's own cwd) and canonicalized, so `..` and symlinks are resolved
+//! before the roots reach Landlock or bwrap. A relative root that resolves to
+//! one of [`PROTECTED_DIRS`] is rejected.
+//!
+//! With `--create-roots`, missing writable roots are created up front instead
+//! of being skipped, but never directly inside one of [`PROTECTED_DIRS`].
+//!
+//! Roots added with `--writable-root` are resolved the same way, and rejected
+//! when they are one of [`PROTECTED_DIRS`] even if given as absolute paths.
+//!
+//! A root that is a symlink, or lies beneath one, is replaced by its target
+//! once the defaults are added, along with its read-only subpaths. Landlock
+//! already follows the link when it opens the root, but bwrap would bind the
+//! link's own path, so both backends get the target to agree on. A root whose
+//! target is one of [`PROTECTED_DIRS`] is rejected, whichever way it is given.
+
+use std::io::ErrorKind;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::error::CodexErr;
+use codex_core::error::Result;
+use codex_core::error::SandboxErr;
+use codex_core::protocol::SandboxPolicy;
+use codex_core::protocol::WritableRoot;
+
+/// System directories a relative writable root may not resolve to.
+const PROTECTED_DIRS: &[&str] = &[
+    "/", "/bin", "/boot", "/dev", "/etc", "/lib", "/lib64", "/proc", "/sbin", "/sys", "/usr",
+];
+
+/// Like [`SandboxPolicy::get_writable_roots_with_cwd`], with relative
+/// writable roots resolved against `cwd` first and symlinked roots replaced
+/// by their targets.
+pub(crate) fn writable_roots_with_cwd(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> Result<Vec<WritableRoot>> {
+    unresolved_writable_roots(sandbox_policy, cwd)?
+        .into_iter()
+        .map(resolve_symlinked_root)
+        .collect()
+}
+
+/// The writable roots that are symlinks, each with its target, so the
+/// launcher can say where writes actually land.
+pub(crate) fn symlinked_writable_roots(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> Vec<(PathBuf, PathBuf)> {
+    unresolved_writable_roots(sandbox_policy, cwd)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|writable_root| writable_root.root.is_symlink())
+        .filter_map(|writable_root| {
+            let target = writable_root.root.canonicalize().ok()?;
+            Some((writable_root.root, target))
+        })
+        .collect()
+}
+
+fn unresolved_writable_roots(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> Result<Vec<WritableRoot>> {
+    let mut sandbox_policy = sandbox_policy.clone();
+    if let SandboxPolicy::WorkspaceWrite { writable_roots, .. } = &mut sandbox_policy {
+        for root in writable_roots.iter_mut() {
+            *root = resolve_writable_root(root, cwd)?;
+        }
+    }
+    Ok(sandbox_policy.get_writable_roots_with_cwd(cwd))
+}
+
+/// Replaces a root that resolves elsewhere with its target, rebasing its
+/// read-only subpaths onto it. A missing root is kept as given.
+fn resolve_symlinked_root(writable_root: WritableRoot) -> Result<WritableRoot> {
+    let Ok(target) = writable_root.root.canonicalize() else {
+        return Ok(writable_root);
+    };
+    if target == writable_root.root {
+        return Ok(writable_root);
+    }
+    if PROTECTED_DIRS.iter().any(|dir| target == Path::new(dir)) {
+        return Err(CodexErr::Sandbox(SandboxErr::ProtectedWritableRoot(
+            format!(
+                "{} resolves to {}",
+                writable_root.root.display(),
+                target.display()
+            ),
+        )));
+    }
+    let read_only_subpaths = writable_root
+        .read_only_subpaths
+        .iter()
+        .map(|subpath| match subpath.strip_prefix(&writable_root.root) {
+            Ok(relative) => target.join(relative),
+            Err(_) => subpath.clone(),
+        })
+        .collect();
+    Ok(WritableRoot {
+        root: target,
+        read_only_subpaths,
+    })
+}
+
+/// Resolves one configured writable root. Absolute roots are kept as given.
+fn resolve_writable_root(root: &Path, cwd: &Path) -> Result<PathBuf> {
+    if root.is_absolute() {
+        return Ok(root.to_path_buf());
+    }
+
+    let joined = cwd.join(root);
+    let resolved = match joined.canonicalize() {
+        Ok(resolved) => resolved,
+        // A missing root grants nothing: Landlock skips it and bwrap fails
+        // to bind it.
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(joined),
+        Err(err) => return Err(err.into()),
+    };
+
+    if PROTECTED_DIRS.iter().any(|dir| resolved == Path::new(dir)) {
+        return Err(CodexErr::Sandbox(SandboxErr::ProtectedWritableRoot(
+            format!("{} resolves to {}", root.display(), resolved.display()),
+        )));
+    }
+    Ok(resolved)
+}
+
+/// Adds the `--writable-root` values in `roots` to the writable roots of
+/// `sandbox_policy`, skipping the ones it already has. A policy that allows
+/// writing everywhere is returned unchanged, while a read-only policy has no
+/// writable roots to add to.
+pub(crate) fn add_writable_roots(
+    sandbox_policy: SandboxPolicy,
+    roots: &[PathBuf],
+    cwd: &Path,
+) -> Result<SandboxPolicy> {
+    if roots.is_empty() {
+        return Ok(sandbox_policy);
+    }
+    let mut sandbox_policy = sandbox_policy;
+    match &mut sandbox_policy {
+        SandboxPolicy::DangerFullAccess => {}
+        SandboxPolicy::ReadOnly => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "--writable-root needs a workspace-write policy, not read-only",
+            )
+            .into());
+        }
+        SandboxPolicy::WorkspaceWrite { writable_roots, .. } => {
+            for root in roots {
+                let resolved = resolve_writable_root(root, cwd)?;
+                let canonical = resolved.canonicalize().unwrap_or_else(|_| resolved.clone());
+                if PROTECTED_DIRS.iter().any(|dir| canonical == Path::new(dir)) {
+                    return Err(CodexErr::Sandbox(SandboxErr::ProtectedWritableRoot(
+                        format!(
+                            "{} is the system directory {}",
+                            root.display(),
+                            canonical.display()
+                        ),
+                    )));
+                }
+                if !writable_roots.contains(&resolved) {
+                    writable_roots.push(resolved);
+                }
+            }
+        }
+    }
+    Ok(sandbox_policy)
+}
+
+/// Creates the writable roots of `sandbox_policy` that do not exist yet,
+/// parents included, and returns the roots it created. With `umask`, the new
+/// directories get mode `0777 & !umask`; otherwise the process umask applies
+/// as usual. They are owned by the launcher's user, which the command runs
+/// as.
+pub(crate) fn create_missing_writable_roots(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    umask: Option<libc::mode_t>,
+) -> Result<Vec<PathBuf>> {
+    let mut created = Vec::new();
+    for writable_root in writable_roots_with_cwd(sandbox_policy, cwd)? {
+        let root = writable_root.root;
+        if root.exists() {
+            continue;
+        }
+        ensure_creatable(&root)?;
+
+        // Remember which ancestors are missing, so only the directories
+        // created here get their mode set.
+        let missing: Vec<PathBuf> = root
+            .ancestors()
+            .take_while(|ancestor| !ancestor.exists())
+            .map(Path::to_path_buf)
+            .collect();
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o777)
+            .create(&root)?;
+        if let Some(umask) = umask {
+            for dir in &missing {
+                let mode = 0o777 & !umask;
+                std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode))?;
+            }
+        }
+        created.push(root);
+    }
+    Ok(created)
+}
+
+/// Rejects creating `root` when its closest existing ancestor is a protected
+/// system directory, e.g. `/etc/out` or `/new-top-level-dir`.
+fn ensure_creatable(root: &Path) -> Result<()> {
+    let Some(ancestor) = root.ancestors().skip(1).find(|ancestor| ancestor.exists()) else {
+        return Ok(());
+    };
+    let ancestor = ancestor.canonicalize()?;
+    if PROTECTED_DIRS.iter().any(|dir| ancestor == Path::new(dir)) {
+        return Err(CodexErr::Sandbox(SandboxErr::ProtectedWritableRoot(
+            format!(
+                "{} would be created inside {}",
+                root.display(),
+                ancestor.display()
+            ),
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::os::unix::fs::MetadataExt;
+
+    /// Creates `<tmp>/work/cwd`, `<tmp>/work/sibling` and `<tmp>/outside` and
+    /// returns the canonical tempdir along with it.
+    fn layout() -> (tempfile::TempDir, PathBuf) {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let base = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+        for dir in ["work/cwd", "work/sibling", "outside"] {
+            std::fs::create_dir_all(base.join(dir)).expect("create dir");
+        }
+        (tmpdir, base)
+    }
+
+    #[test]
+    fn relative_roots_resolve_against_the_policy_cwd() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+
+        for (root, expected) in [
+            (".", base.join("work/cwd")),
+            ("./", base.join("work/cwd")),
+            ("..", base.join("work")),
+            ("../sibling", base.join("work/sibling")),
+            ("./../sibling/.", base.join("work/sibling")),
+            ("../../outside", base.join("outside")),
+        ] {
+            assert_eq!(
+                resolve_writable_root(Path::new(root), &cwd).expect("resolve root"),
+                expected,
+                "{root}"
+            );
+        }
+    }
+
+    #[test]
+    fn relative_roots_follow_symlinks() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        std::os::unix::fs::symlink(base.join("outside"), cwd.join("link")).expect("symlink");
+
+        assert_eq!(
+            resolve_writable_root(Path::new("link/../work"), &cwd).expect("resolve root"),
+            // `..` applies to the symlink's target, not to `cwd`.
+            base.join("work")
+        );
+    }
+
+    #[test]
+    fn absolute_and_missing_roots_are_kept_as_given() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+
+        assert_eq!(
+            resolve_writable_root(Path::new("/"), &cwd).expect("absolute root"),
+            PathBuf::from("/")
+        );
+        assert_eq!(
+            resolve_writable_root(Path::new("missing/.."), &cwd).expect("missing root"),
+            cwd.join("missing/..")
+        );
+    }
+
+    #[test]
+    fn relative_roots_escaping_to_protected_dirs_are_rejected() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let up = "../".repeat(cwd.components().count());
+
+        let err = resolve_writable_root(Path::new(&up), &cwd).expect_err("resolves to /");
+        assert_eq!(
+            err.to_string(),
+            format!("sandbox error: writable root is not allowed: {up} resolves to /")
+        );
+
+        let etc = format!("{up}etc");
+        let err = resolve_writable_root(Path::new(&etc), &cwd).expect_err("resolves to /etc");
+        assert_eq!(
+            err.to_string(),
+            format!("sandbox error: writable root is not allowed: {etc} resolves to /etc")
+        );
+    }
+
+    #[test]
+    fn policy_roots_are_resolved_before_defaults_are_added() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![PathBuf::from("../sibling")],
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        };
+
+        let roots: Vec<PathBuf> = writable_roots_with_cwd(&policy, &cwd)
+            .expect("resolve roots")
+            .into_iter()
+            .map(|writable_root| writable_root.root)
+            .collect();
+        assert_eq!(roots, vec![base.join("work/sibling"), cwd]);
+    }
+
+    #[test]
+    fn symlinked_roots_are_replaced_by_their_targets() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let target = base.join("outside");
+        std::fs::create_dir(target.join(".git")).expect("create .git");
+        let link = base.join("work/link");
+        std::os::unix::fs::symlink(&target, &link).expect("symlink");
+        let policy = workspace_write(vec![link.clone()]);
+
+        assert_eq!(
+            writable_roots_with_cwd(&policy, &cwd).expect("resolve roots"),
+            vec![
+                WritableRoot {
+                    root: target.clone(),
+                    read_only_subpaths: vec![target.join(".git")],
+                },
+                WritableRoot {
+                    root: cwd.clone(),
+                    read_only_subpaths: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            symlinked_writable_roots(&policy, &cwd),
+            vec![(link, target)]
+        );
+    }
+
+    #[test]
+    fn symlinks_to_protected_dirs_are_rejected() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let link = base.join("work/etc");
+        std::os::unix::fs::symlink("/etc", &link).expect("symlink");
+
+        let err = writable_roots_with_cwd(&workspace_write(vec![link.clone()]), &cwd)
+            .expect_err("the link resolves to /etc");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "sandbox error: writable root is not allowed: {} resolves to /etc",
+                link.display()
+            )
+        );
+    }
+
+    fn workspace_write(writable_roots: Vec<PathBuf>) -> SandboxPolicy {
+        SandboxPolicy::WorkspaceWrite {
+            writable_roots,
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+        }
+    }
+
+    #[test]
+    fn cli_roots_are_merged_into_the_policy_roots() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let sibling = base.join("work/sibling");
+        let policy = workspace_write(vec![sibling.clone()]);
+
+        let policy = add_writable_roots(
+            policy,
+            &[
+                PathBuf::from("../sibling"),
+                base.join("outside"),
+                base.join("missing"),
+            ],
+            &cwd,
+        )
+        .expect("add writable roots");
+        assert_eq!(
+            policy,
+            workspace_write(vec![sibling, base.join("outside"), base.join("missing")])
+        );
+    }
+
+    #[test]
+    fn cli_roots_may_not_be_system_dirs() {
+        let (_tmpdir, base) = layout();
+        let err = add_writable_roots(workspace_write(Vec::new()), &[PathBuf::from("/etc")], &base)
+            .expect_err("/etc is protected");
+        assert_eq!(
+            err.to_string(),
+            "sandbox error: writable root is not allowed: /etc is the system directory /etc"
+        );
+
+        let err = add_writable_roots(SandboxPolicy::ReadOnly, &[base.join("outside")], &base)
+            .expect_err("read-only has no writable roots");
+        assert!(err.to_string().contains("needs a workspace-write policy"));
+
+        assert_eq!(
+            add_writable_roots(
+                SandboxPolicy::DangerFullAccess,
+                &[PathBuf::from("/etc")],
+                &base
+            )
+            .expect("full access"),
+            SandboxPolicy::DangerFullAccess
+        );
+    }
+
+    #[test]
+    fn missing_roots_are_created_with_the_umask() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+        let policy = workspace_write(vec![
+            PathBuf::from("out/nested"),
+            base.join("outside/absolute"),
+            PathBuf::from("../sibling"),
+        ]);
+
+        let created =
+            create_missing_writable_roots(&policy, &cwd, Some(0o027)).expect("create roots");
+        assert_eq!(
+            created,
+            vec![cwd.join("out/nested"), base.join("outside/absolute")]
+        );
+        for dir in [
+            cwd.join("out"),
+            cwd.join("out/nested"),
+            base.join("outside/absolute"),
+        ] {
+            let metadata = std::fs::metadata(&dir).expect("created dir");
+            assert_eq!(
+                (metadata.permissions().mode() & 0o777, metadata.uid()),
+                (0o750, unsafe { libc::geteuid() }),
+                "{}",
+                dir.display()
+            );
+        }
+        // Existing directories are left alone.
+        assert_eq!(
+            create_missing_writable_roots(&policy, &cwd, Some(0o027)).expect("create roots"),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn roots_are_not_created_inside_protected_dirs() {
+        let (_tmpdir, base) = layout();
+        let cwd = base.join("work/cwd");
+
+        for root in [
+            "/etc/codex-create-roots-test/out",
+            "/codex-create-roots-test",
+        ] {
+            let policy = workspace_write(vec![PathBuf::from(root)]);
+            let err = create_missing_writable_roots(&policy, &cwd, None)
+                .expect_err("must not create under a protected dir");
+            let parent = if root.starts_with("/etc") {
+                "/etc"
+            } else {
+                "/"
+            };
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "sandbox error: writable root is not allowed: {root} would be created inside {parent}"
+                )
+            );
+            assert!(!Path::new(root).exists());
+        }
+    }
+}
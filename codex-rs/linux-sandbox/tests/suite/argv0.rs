@@ -0,0 +1,20 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn argv0_overrides_the_program_name() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--argv0", "custom-name", "--", "cat", "/proc/self/cmdline"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    // `cat` was still found on PATH; only the argv[0] it sees changed.
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "custom-name\0/proc/self/cmdline\0"
+    );
+}
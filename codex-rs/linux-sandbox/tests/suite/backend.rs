@@ -0,0 +1,52 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+
+#[test]
+fn none_backend_does_not_restrict_writes() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let target = tmpdir.path().join("created-by-sandbox");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--backend", "none", "--", "touch"])
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(target.exists());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("without a sandbox"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn landlock_backend_restricts_writes() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let target = tmpdir.path().join("created-by-sandbox");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--backend", "landlock", "--", "touch"])
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!target.exists());
+}
+
+#[test]
+fn bwrap_backend_without_bwrap_is_an_error() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        // An empty directory, so bwrap cannot be found regardless of the host.
+        .env("PATH", tmpdir.path())
+        .args(["--backend", "bwrap", "--", "/bin/true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("bwrap was not found on PATH"),
+        "{output:?}"
+    );
+}
@@ -0,0 +1,181 @@
+#![cfg(target_os = "linux")]
+//! The bwrap backend end to end. Skipped on hosts without bwrap.
+use std::path::Path;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Only the sandbox policy cwd is writable.
+const CWD_ONLY_POLICY: &str = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+fn run_under_bwrap(cwd: &Path, args: &[&str], script: &str) -> Output {
+    sandbox_command(CWD_ONLY_POLICY, cwd)
+        .args(["--backend", "bwrap"])
+        .args(args)
+        .args(["--", "sh", "-c", script])
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+#[test]
+fn writes_are_confined_to_writable_roots() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    std::fs::create_dir_all(cwd.join(".git")).expect("create cwd/.git");
+    let outside = tmpdir.path().join("outside");
+
+    let output = run_under_bwrap(&cwd, &[], &format!("echo ok > {}/inside", cwd.display()));
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        std::fs::read_to_string(cwd.join("inside")).expect("read inside"),
+        "ok\n"
+    );
+
+    let output = run_under_bwrap(&cwd, &[], &format!("echo no > {}", outside.display()));
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!outside.exists());
+
+    // Read-only subpaths of a writable root stay read-only.
+    let output = run_under_bwrap(&cwd, &[], &format!("touch {}/.git/config", cwd.display()));
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!cwd.join(".git/config").exists());
+}
+
+#[test]
+fn proc_does_not_lead_back_to_the_host() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    std::fs::create_dir(&cwd).expect("create cwd");
+    let outside = tmpdir.path().join("outside");
+
+    // Every process the sandbox can see, including pid 1, has the sandbox's
+    // own root, where `outside` is read-only.
+    let script = format!(
+        r#"echo no > /proc/1/root{outside} || true
+for root in /proc/[0-9]*/root; do echo no > "$root{outside}" 2>/dev/null; done
+true"#,
+        outside = outside.display()
+    );
+    let output = run_under_bwrap(&cwd, &[], &script);
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(!outside.exists());
+}
+
+#[test]
+fn reads_outside_a_minimal_root_fail() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    let kept = tmpdir.path().join("kept");
+    let hidden = tmpdir.path().join("hidden");
+    std::fs::create_dir(&cwd).expect("create cwd");
+    std::fs::create_dir(&kept).expect("create kept");
+    std::fs::create_dir(&hidden).expect("create hidden");
+    std::fs::write(kept.join("file"), "kept").expect("write kept file");
+    std::fs::write(hidden.join("file"), "hidden").expect("write hidden file");
+    let keep_mount = kept.to_string_lossy().into_owned();
+    let args = ["--minimal-root", "--keep-mount", keep_mount.as_str()];
+
+    let output = run_under_bwrap(&cwd, &args, &format!("cat {}/file", kept.display()));
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "kept");
+
+    let output = run_under_bwrap(&cwd, &args, &format!("cat {}/file", hidden.display()));
+    assert!(!output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, b"");
+}
+
+#[test]
+fn symlinked_roots_are_bound_at_their_target() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    let target = tmpdir.path().join("target");
+    let link = tmpdir.path().join("link");
+    std::fs::create_dir(&cwd).expect("create cwd");
+    std::fs::create_dir(&target).expect("create target");
+    std::os::unix::fs::symlink(&target, &link).expect("symlink");
+    let root = link.to_string_lossy().into_owned();
+
+    let output = run_under_bwrap(
+        &cwd,
+        &["--writable-root", root.as_str()],
+        &format!(
+            "touch {}/via-link && touch {}/direct",
+            link.display(),
+            target.display()
+        ),
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert!(target.join("via-link").exists());
+    assert!(target.join("direct").exists());
+}
+
+/// Mounting on the host needs root, so this only runs as root.
+#[test]
+fn host_mounts_made_later_stay_out_of_writable_roots() {
+    if !bwrap_on_path() || unsafe { libc::geteuid() } != 0 {
+        eprintln!("skipping: needs bwrap on PATH and root to mount on the host");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    let mnt = cwd.join("mnt");
+    std::fs::create_dir_all(&mnt).expect("create cwd/mnt");
+
+    // Signals that the sandbox is set up, then waits for the host's mount.
+    let script = r#"
+touch ready
+i=0
+while [ ! -e go ] && [ "$i" -lt 200 ]; do sleep 0.05; i=$((i + 1)); done
+cat mnt/hello 2>/dev/null || echo hidden
+"#;
+    let child = sandbox_command(CWD_ONLY_POLICY, &cwd)
+        .args(["--backend", "bwrap", "--", "sh", "-c", script])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("run codex-linux-sandbox");
+    let mut waited = 0;
+    while !cwd.join("ready").exists() && waited < 200 {
+        std::thread::sleep(Duration::from_millis(50));
+        waited += 1;
+    }
+    let mounted = Command::new("mount")
+        .args(["-t", "tmpfs", "tmpfs"])
+        .arg(&mnt)
+        .status()
+        .expect("run mount");
+    assert!(mounted.success(), "{mounted:?}");
+    std::fs::write(mnt.join("hello"), "from the host\n").expect("write into the mount");
+    std::fs::write(cwd.join("go"), "").expect("let the command go on");
+
+    let output = child
+        .wait_with_output()
+        .expect("wait for codex-linux-sandbox");
+    let _ = Command::new("umount").arg(&mnt).status();
+
+    // bwrap is started in a mount namespace whose mounts are private, so the
+    // new one does not propagate in.
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hidden\n");
+}
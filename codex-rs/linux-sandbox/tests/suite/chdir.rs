@@ -0,0 +1,63 @@
+#![cfg(target_os = "linux")]
+//! The command starts in the same directory under every backend.
+use std::path::Path;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// The directory `pwd -P` reports under `backend`, started from `launcher_cwd`.
+fn command_cwd(backend: &str, launcher_cwd: &Path, policy_cwd: &Path, args: &[&str]) -> String {
+    let output = sandbox_command(READ_ONLY_POLICY, policy_cwd)
+        .current_dir(launcher_cwd)
+        .args(["--backend", backend])
+        .args(args)
+        .args(["--", "sh", "-c", "pwd -P"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{backend}: {output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn backends_start_the_command_in_the_same_directory() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let root = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+    let launcher_cwd = root.join("launcher");
+    let work = root.join("work");
+    std::fs::create_dir(&launcher_cwd).expect("create launcher");
+    std::fs::create_dir_all(work.join("sub")).expect("create work/sub");
+
+    let mut backends = vec!["landlock"];
+    if bwrap_on_path() {
+        backends.push("bwrap");
+    } else {
+        eprintln!("not comparing against bwrap: bwrap is not on PATH");
+    }
+    for backend in backends {
+        assert_eq!(
+            command_cwd(backend, &launcher_cwd, &work, &[]),
+            format!("{}\n", work.display()),
+            "{backend}"
+        );
+        assert_eq!(
+            command_cwd(backend, &launcher_cwd, &work, &["--chdir", "sub"]),
+            format!("{}\n", work.join("sub").display()),
+            "{backend}"
+        );
+    }
+}
+
+#[test]
+fn missing_chdir_fails_the_launch() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--chdir", "missing", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot start the command in "), "{stderr}");
+}
@@ -0,0 +1,52 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// `TMPDIR` and `GREETING` as the command sees them.
+fn command_env(cwd: &Path, args: &[&str]) -> String {
+    let output = sandbox_command(READ_ONLY_POLICY, cwd)
+        .env("GREETING", "inherited")
+        .args(args)
+        .args([
+            "--",
+            "sh",
+            "-c",
+            r#"printf '%s\n%s\n' "$TMPDIR" "$GREETING""#,
+        ])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn derived_variables_are_set_unless_overridden() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+
+    let derived = command_env(tmpdir.path(), &["--private-tmp"]);
+    let (private_tmp, greeting) = derived.split_once('\n').expect("two lines");
+    assert!(
+        private_tmp.starts_with("/tmp/codex-private-tmp."),
+        "{private_tmp}"
+    );
+    assert_eq!(greeting, "inherited\n");
+    let _ = std::fs::remove_dir_all(private_tmp);
+
+    // --setenv wins over the derived TMPDIR and gets past --env-deny.
+    let overridden = command_env(
+        tmpdir.path(),
+        &[
+            "--private-tmp",
+            "--env-deny",
+            "GREETING",
+            "--setenv",
+            "TMPDIR=/scratch",
+            "--setenv",
+            "GREETING=from setenv",
+        ],
+    );
+    assert_eq!(overridden, "/scratch\nfrom setenv\n");
+}
@@ -0,0 +1,84 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Every field documented in the README.
+const FIELDS: &[&str] = &[
+    "timestamp",
+    "backend",
+    "policy",
+    "cwd",
+    "argv",
+    "network",
+    "paths",
+    "enforced",
+    "enforcement_reason",
+];
+
+#[test]
+fn each_run_appends_a_complete_record() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let log_path = tmpdir.path().join("compliance.jsonl");
+    for word in ["one", "two"] {
+        let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+            .arg("--compliance-log")
+            .arg(&log_path)
+            .args(["--", "echo", word])
+            .output()
+            .expect("run codex-linux-sandbox");
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    let log = std::fs::read_to_string(&log_path).expect("read compliance log");
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("parse compliance record"))
+        .collect();
+    assert_eq!(records.len(), 2, "{log}");
+
+    let record = &records[1];
+    for field in FIELDS {
+        assert!(record.get(field).is_some(), "{field} missing from {record}");
+    }
+    assert_eq!(record["backend"], "landlock");
+    assert_eq!(record["policy"], "read-only");
+    assert_eq!(record["network"], "blocked");
+    assert_eq!(record["argv"], serde_json::json!(["echo", "two"]));
+    assert!(record["enforced"].as_bool().is_some(), "{record}");
+    assert!(
+        record["timestamp"]
+            .as_str()
+            .is_some_and(|timestamp| timestamp.ends_with('Z')),
+        "{record}"
+    );
+    let paths = record["paths"].as_array().expect("paths array");
+    assert_eq!(
+        paths.first(),
+        Some(&serde_json::json!({"path": "/", "access": "read-only"}))
+    );
+    assert!(
+        paths.contains(&serde_json::json!({"path": "/dev/null", "access": "read-write"})),
+        "{record}"
+    );
+}
+
+#[test]
+fn an_unwritable_log_stops_the_run() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .arg("--compliance-log")
+        .arg(tmpdir.path().join("missing").join("compliance.jsonl"))
+        .arg("--")
+        .arg("touch")
+        .arg(&marker)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!marker.exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--compliance-log"), "{stderr}");
+}
@@ -0,0 +1,41 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::MetadataExt;
+
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn create_roots_makes_missing_roots_writable() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let policy = r#"{"mode":"workspace-write","writable_roots":["out/nested"],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+    let file = tmpdir.path().join("out/nested/result");
+
+    let output = sandbox_command(policy, tmpdir.path())
+        .args(["--create-roots", "--umask", "077", "--", "touch"])
+        .arg(&file)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(file.exists());
+    let metadata = std::fs::metadata(tmpdir.path().join("out/nested")).expect("created root");
+    assert_eq!(metadata.mode() & 0o777, 0o700);
+}
+
+#[test]
+fn create_roots_refuses_system_directories() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let policy = r#"{"mode":"workspace-write","writable_roots":["/etc/codex-create-roots-test"],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+    let output = sandbox_command(policy, tmpdir.path())
+        .args(["--create-roots", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("would be created inside /etc"),
+        "{output:?}"
+    );
+    assert!(!std::path::Path::new("/etc/codex-create-roots-test").exists());
+}
@@ -0,0 +1,61 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+const WORKSPACE_WRITE_POLICY: &str = r#"{"mode":"workspace-write","network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+#[test]
+fn dry_run_prints_the_manifest_without_running_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+
+    let output = sandbox_command(WORKSPACE_WRITE_POLICY, tmpdir.path())
+        .args(["--dry-run", "--", "touch"])
+        .arg(&marker)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(r#"{"kernel":"#), "{stdout}");
+    assert!(stdout.ends_with(",\"exit\":null}\n"), "{stdout}");
+    assert!(!marker.exists(), "the command ran");
+}
+
+#[test]
+fn print_roots_separates_canonical_paths_with_nul() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let base = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+    let cwd = base.join("cwd");
+    let awkward = base.join("with space\nand newline");
+    for dir in [&cwd, &awkward] {
+        std::fs::create_dir(dir).expect("create dir");
+    }
+    std::os::unix::fs::symlink(&awkward, base.join("link")).expect("symlink");
+
+    let print_roots = |roots: &str| {
+        let output = sandbox_command(WORKSPACE_WRITE_POLICY, &cwd)
+            .args([
+                "--writable-root",
+                "../link",
+                "--dry-run",
+                "--print-roots",
+                roots,
+            ])
+            .args(["--", "true"])
+            .output()
+            .expect("run codex-linux-sandbox");
+        assert!(output.status.success(), "{output:?}");
+        output.stdout
+    };
+
+    let mut expected = Vec::new();
+    for root in [&awkward, &cwd] {
+        expected.extend_from_slice(root.as_os_str().as_bytes());
+        expected.push(0);
+    }
+    assert_eq!(print_roots("writable"), expected);
+    assert_eq!(print_roots("readable"), b"/\0");
+}
@@ -0,0 +1,76 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+
+fn run_with_manifest(cwd: &Path, args: &[&str]) -> String {
+    let manifest_path = cwd.join("manifest.json");
+    let output = sandbox_command(READ_ONLY_POLICY, cwd)
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .args(args)
+        .args(["--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+    std::fs::read_to_string(&manifest_path).expect("read manifest")
+}
+
+#[test]
+fn strict_landlock_runs_are_enforced() {
+    // Strict mode refuses to run with gaps, so a run that got this far must
+    // also have passed the self-test.
+    for wait in [false, true] {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let mut args = vec!["--backend", "landlock", "--enforcement", "strict"];
+        if wait {
+            args.push("--wait");
+        }
+        let manifest = run_with_manifest(tmpdir.path(), &args);
+
+        assert!(
+            manifest.contains(r#""enforced":true,"enforcement_reason":"the self-test confirmed"#),
+            "{manifest}"
+        );
+    }
+}
+
+#[test]
+fn backend_none_is_never_enforced() {
+    for wait in [false, true] {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let mut args = vec!["--backend", "none"];
+        if wait {
+            args.push("--wait");
+        }
+        let manifest = run_with_manifest(tmpdir.path(), &args);
+
+        assert!(
+            manifest.contains(
+                r#""enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox""#
+            ),
+            "{manifest}"
+        );
+        if wait {
+            // The exit report carries the same verdict.
+            assert!(
+                manifest.ends_with(
+                    r#""enforced":false,"enforcement_reason":"--backend none runs the command without a sandbox"}}
+"#
+                ),
+                "{manifest}"
+            );
+        }
+    }
+}
+
+#[test]
+fn best_effort_runs_report_a_verdict() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let manifest = run_with_manifest(tmpdir.path(), &["--backend", "landlock"]);
+
+    // true or false depending on the kernel's Landlock ABI, but never left
+    // unset.
+    assert!(!manifest.contains(r#""enforced":null"#), "{manifest}");
+}
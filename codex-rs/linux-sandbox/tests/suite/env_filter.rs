@@ -0,0 +1,66 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Runs `env` under the sandbox with a few test variables set and returns the
+/// names of those it still sees.
+fn visible_test_vars(flags: &[&str]) -> Vec<String> {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .env("CODEX_ENV_TEST_PUBLIC", "1")
+        .env("CODEX_ENV_TEST_SECRET", "1")
+        .env("GITHUB_TOKEN", "1")
+        .args(flags)
+        .args(["--", "env"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    let mut names: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('=').map(|(name, _)| name.to_string()))
+        .filter(|name| name.starts_with("CODEX_ENV_TEST_") || name == "GITHUB_TOKEN")
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn environment_is_untouched_without_filter_flags() {
+    assert_eq!(
+        visible_test_vars(&[]),
+        vec![
+            "CODEX_ENV_TEST_PUBLIC",
+            "CODEX_ENV_TEST_SECRET",
+            "GITHUB_TOKEN"
+        ]
+    );
+}
+
+#[test]
+fn deny_beats_allow() {
+    assert_eq!(
+        visible_test_vars(&[
+            "--env-allow",
+            "CODEX_ENV_TEST_*",
+            "--env-allow",
+            "GITHUB_*",
+            "--env-deny",
+            "*_SECRET",
+        ]),
+        vec!["CODEX_ENV_TEST_PUBLIC"]
+    );
+}
+
+#[test]
+fn default_deny_set_can_be_disabled() {
+    assert_eq!(
+        visible_test_vars(&["--env-allow", "*", "--no-default-env-deny"]),
+        vec![
+            "CODEX_ENV_TEST_PUBLIC",
+            "CODEX_ENV_TEST_SECRET",
+            "GITHUB_TOKEN"
+        ]
+    );
+}
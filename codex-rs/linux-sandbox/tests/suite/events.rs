@@ -0,0 +1,72 @@
+#![cfg(target_os = "linux")]
+use std::fs::File;
+use std::io::Read as _;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::unix::process::CommandExt;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// fd number the write end of the pipe is passed on in the launcher.
+const EVENTS_FD: i32 = 3;
+
+#[test]
+fn events_follow_the_sandbox_setup() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let mut fds = [0; 2];
+    assert_eq!(
+        unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) },
+        0,
+        "pipe2"
+    );
+    let (read_end, write_end) =
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    let write_fd = write_end.as_raw_fd();
+
+    let mut command = sandbox_command(READ_ONLY_POLICY, tmpdir.path());
+    command.args([
+        "--backend",
+        "landlock",
+        "--events-fd",
+        &EVENTS_FD.to_string(),
+        "--",
+        "echo",
+        "hi",
+    ]);
+    // Runs in the forked child just before exec.
+    unsafe {
+        command.pre_exec(move || {
+            if libc::dup2(write_fd, EVENTS_FD) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let output = command.output().expect("run codex-linux-sandbox");
+    // The launcher's copy closed at exec, so reading ends here.
+    drop(write_end);
+    assert!(output.status.success(), "{output:?}");
+
+    let mut events = String::new();
+    File::from(read_end)
+        .read_to_string(&mut events)
+        .expect("read events");
+    let events: Vec<&str> = events.lines().collect();
+    assert_eq!(events.len(), 4, "{events:?}");
+    assert_eq!(
+        events[0],
+        r#"{"event":"seccomp_installed","network":"blocked"}"#
+    );
+    assert!(
+        events[1].starts_with(r#"{"event":"landlock_installed","abi":"#),
+        "{events:?}"
+    );
+    assert!(
+        events[2].starts_with(r#"{"event":"enforcement","enforced":"#),
+        "{events:?}"
+    );
+    assert_eq!(events[3], r#"{"event":"exec","argv":["echo","hi"]}"#);
+}
@@ -0,0 +1,57 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn wrapper_runs_inside_the_sandbox_and_execs_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let wrapper = tmpdir.path().join("wrapper.sh");
+    std::fs::write(
+        &wrapper,
+        r#"#!/bin/sh
+probe="$1"
+shift
+if : > "$probe" 2>/dev/null; then echo writable; else echo read-only; fi
+exec "$@"
+"#,
+    )
+    .expect("write wrapper");
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))
+        .expect("make wrapper executable");
+    let probe = tmpdir.path().join("probe");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--exec-wrapper")
+        .arg(&wrapper)
+        .arg("--exec-wrapper-arg")
+        .arg(&probe)
+        .args(["--", "echo", "command ran"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "read-only\ncommand ran\n"
+    );
+    assert!(!probe.exists());
+}
+
+#[test]
+fn missing_wrapper_is_reported() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--exec-wrapper", "/nonexistent/wrapper", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--exec-wrapper /nonexistent/wrapper cannot be executed inside the sandbox"),
+        "{output:?}"
+    );
+}
@@ -0,0 +1,41 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+use std::process::Output;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Lists what each of the shell's own open fds points to.
+fn open_fd_targets(cwd: &Path, backend: &str) -> Output {
+    sandbox_command(READ_ONLY_POLICY, cwd)
+        .args(["--backend", backend, "--", "sh", "-c"])
+        .arg(r#"for fd in /proc/$$/fd/*; do echo "${fd##*/} $(readlink "$fd")"; done"#)
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+/// The fd numbers from [`open_fd_targets`], since pipe targets differ
+/// between runs.
+fn fd_numbers(targets: &str) -> Vec<&str> {
+    targets
+        .lines()
+        .filter_map(|line| line.split(' ').next())
+        .collect()
+}
+
+#[test]
+fn landlock_ruleset_fd_does_not_leak_into_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+
+    let sandboxed = open_fd_targets(tmpdir.path(), "landlock");
+    assert!(sandboxed.status.success(), "{sandboxed:?}");
+    let sandboxed = String::from_utf8_lossy(&sandboxed.stdout);
+    assert!(!sandboxed.contains("landlock-ruleset"), "{sandboxed}");
+
+    // The command has exactly the fds it would have without a sandbox.
+    let unsandboxed = open_fd_targets(tmpdir.path(), "none");
+    assert!(unsandboxed.status.success(), "{unsandboxed:?}");
+    let unsandboxed = String::from_utf8_lossy(&unsandboxed.stdout);
+    assert_eq!(fd_numbers(&sandboxed), fd_numbers(&unsandboxed));
+}
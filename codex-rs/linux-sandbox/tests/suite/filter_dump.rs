@@ -0,0 +1,29 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::sandbox_command;
+
+#[test]
+fn dump_filter_lists_network_rules_for_a_network_blocking_policy() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let dump_path = tmpdir.path().join("filter.txt");
+    let output = sandbox_command(r#"{"mode":"read-only"}"#, tmpdir.path())
+        .args(["--backend", "landlock", "--dump-filter"])
+        .arg(&dump_path)
+        .args(["--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let dump = std::fs::read_to_string(&dump_path).expect("read filter dump");
+    for expected in [
+        "# seccomp network filter",
+        "; connect",
+        "; socket",
+        "; sendmsg",
+        "ERRNO(1)",
+        "# Landlock ruleset",
+        "read-only: /",
+        "read-write: /dev/null",
+    ] {
+        assert!(dump.contains(expected), "missing {expected:?} in:\n{dump}");
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg(target_os = "linux")]
+use std::io::BufRead as _;
+use std::io::BufReader;
+use std::io::Read as _;
+use std::process::Stdio;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Prints a line and a progress fragment, then only goes on once the test
+/// has seen both, giving up after about ten seconds.
+const INCREMENTAL: &str = r#"
+echo one
+printf 'progress' >&2
+i=0
+while [ ! -e go ] && [ "$i" -lt 200 ]; do sleep 0.05; i=$((i + 1)); done
+[ -e go ] || { echo timed out; exit 1; }
+echo ' done' >&2
+echo two
+echo three
+"#;
+
+#[test]
+fn incremental_output_is_forwarded_promptly_and_in_order() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let mut child = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .current_dir(tmpdir.path())
+        .args(["--wait", "--forward-output", "--", "sh", "-c", INCREMENTAL])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run codex-linux-sandbox");
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout"));
+    let mut stderr = child.stderr.take().expect("stderr");
+
+    // Both arrive while the command is still waiting, so neither was held
+    // back until it exited.
+    let mut line = String::new();
+    stdout.read_line(&mut line).expect("read the first line");
+    assert_eq!(line, "one\n");
+    // The launcher may log something of its own first.
+    let mut early_stderr = Vec::new();
+    while !early_stderr.ends_with(b"progress") {
+        let mut byte = [0u8; 1];
+        stderr
+            .read_exact(&mut byte)
+            .expect("read the unfinished line");
+        early_stderr.push(byte[0]);
+    }
+
+    std::fs::write(tmpdir.path().join("go"), "").expect("let the command go on");
+    let mut rest = String::new();
+    stdout.read_to_string(&mut rest).expect("read the rest");
+    let mut rest_of_stderr = String::new();
+    stderr
+        .read_to_string(&mut rest_of_stderr)
+        .expect("read the rest of stderr");
+    let status = child.wait().expect("wait for codex-linux-sandbox");
+
+    assert!(status.success(), "{status:?} {rest} {rest_of_stderr}");
+    assert_eq!(rest, "two\nthree\n");
+    assert!(rest_of_stderr.starts_with(" done\n"), "{rest_of_stderr}");
+}
+
+#[test]
+fn many_lines_arrive_intact() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--wait", "--forward-output", "--", "seq", "1", "20000"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let expected: String = (1..=20000).map(|n| format!("{n}\n")).collect();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn a_closed_reader_does_not_kill_the_launcher() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let mut child = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--wait", "--forward-output", "--", "sh", "-c"])
+        .arg("trap '' PIPE; sleep 0.2; for i in 1 2 3; do echo line; done; exit 3")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("run codex-linux-sandbox");
+    drop(child.stdout.take());
+
+    let status = child.wait().expect("wait for codex-linux-sandbox");
+
+    // The command's status is passed on rather than the launcher dying of
+    // SIGPIPE.
+    assert_eq!(status.code(), Some(3), "{status:?}");
+}
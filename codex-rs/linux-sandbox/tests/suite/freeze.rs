@@ -0,0 +1,74 @@
+#![cfg(target_os = "linux")]
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// fd number the ready pipe is passed on in the launcher.
+const READY_FD: i32 = 3;
+
+#[test]
+fn command_does_not_run_until_thawed() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+    let (ready_read, mut ready_write) = unsafe {
+        (
+            std::fs::File::from_raw_fd(fds[0]),
+            std::fs::File::from_raw_fd(fds[1]),
+        )
+    };
+    let ready_read_fd = ready_read.as_raw_fd();
+
+    let mut command = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path());
+    command
+        .args([
+            "--wait",
+            "--freeze-until-ready",
+            &READY_FD.to_string(),
+            "--",
+        ])
+        .arg("touch")
+        .arg(&marker)
+        .stderr(Stdio::piped());
+    // Runs in the forked child just before exec.
+    unsafe {
+        command.pre_exec(move || {
+            if libc::dup2(ready_read_fd, READY_FD) < 0
+                || libc::fcntl(READY_FD, libc::F_SETFD, 0) < 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().expect("spawn codex-linux-sandbox");
+    drop(ready_read);
+
+    std::thread::sleep(Duration::from_millis(500));
+    if let Some(status) = child.try_wait().expect("poll codex-linux-sandbox") {
+        let output = child.wait_with_output().expect("collect output");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("needs the cgroup v2 freezer") {
+            eprintln!("skipping: {stderr}");
+            return;
+        }
+        panic!("launcher exited before the ready signal ({status}): {stderr}");
+    }
+    assert!(!marker.exists(), "command ran before it was thawed");
+
+    ready_write.write_all(b"1").expect("send ready signal");
+    let output = child
+        .wait_with_output()
+        .expect("wait for codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+    assert!(marker.exists(), "command did not run after it was thawed");
+}
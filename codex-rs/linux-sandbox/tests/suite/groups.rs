@@ -0,0 +1,87 @@
+#![cfg(target_os = "linux")]
+use std::collections::BTreeSet;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Some gid the test process is unlikely to be in already.
+const EXTRA_GID: libc::gid_t = 4242;
+
+fn current_groups() -> Vec<libc::gid_t> {
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    assert!(count >= 0, "getgroups");
+    let mut gids = vec![0; count as usize];
+    let count = unsafe { libc::getgroups(count, gids.as_mut_ptr()) };
+    assert!(count >= 0, "getgroups");
+    gids.truncate(count as usize);
+    gids
+}
+
+fn gid_list(gids: &[libc::gid_t]) -> String {
+    gids.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Runs `id -G`, which prints the effective gid followed by the
+/// supplementary groups, with `--groups gids`.
+fn groups_of_sandboxed_command(gids: &[libc::gid_t]) -> std::process::Output {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--groups", &gid_list(gids), "--", "id", "-G"])
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+fn expected_groups(gids: &[libc::gid_t]) -> BTreeSet<String> {
+    let egid = unsafe { libc::getegid() };
+    gids.iter()
+        .chain([&egid])
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn command_gets_the_requested_groups() {
+    let gids = if unsafe { libc::geteuid() } == 0 {
+        vec![EXTRA_GID, 0]
+    } else {
+        // Without CAP_SETGID only the current groups can be requested.
+        let mut gids = current_groups();
+        gids.reverse();
+        gids
+    };
+    if gids.is_empty() {
+        eprintln!("skipping: the test process has no supplementary groups to request");
+        return;
+    }
+
+    let output = groups_of_sandboxed_command(&gids);
+    assert!(output.status.success(), "{output:?}");
+
+    let groups: BTreeSet<String> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    assert_eq!(groups, expected_groups(&gids));
+}
+
+#[test]
+fn new_groups_need_cap_setgid() {
+    if unsafe { libc::geteuid() } == 0 || current_groups().contains(&EXTRA_GID) {
+        eprintln!("skipping: the test process may already set these groups");
+        return;
+    }
+
+    let mut gids = current_groups();
+    gids.push(EXTRA_GID);
+    let output = groups_of_sandboxed_command(&gids);
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("needs CAP_SETGID"),
+        "{output:?}"
+    );
+}
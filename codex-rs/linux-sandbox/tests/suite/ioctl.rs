@@ -0,0 +1,65 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+use crate::suite::support::which_python;
+use pretty_assertions::assert_eq;
+
+/// Makes an allowed ioctl and an arbitrary one on a pipe, printing how each
+/// went.
+const PROBE: &str = r#"
+import errno, fcntl, os, struct, termios
+r, w = os.pipe()
+os.write(w, b"abc")
+print(struct.unpack("i", fcntl.ioctl(r, termios.FIONREAD, b"\0" * 4))[0])
+try:
+    fcntl.ioctl(r, 0x1234, b"\0" * 4)
+except OSError as e:
+    print(errno.errorcode[e.errno])
+"#;
+
+fn run_probe(args: &[&str]) -> String {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(args)
+        .args(["--", "python3", "-c", PROBE])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn restrict_ioctls_blocks_unlisted_requests() {
+    if which_python().is_none() {
+        eprintln!("skipping: python3 is not installed");
+        return;
+    }
+
+    assert_eq!(run_probe(&["--restrict-ioctls"]), "3\nEPERM\n");
+    // Without the filter the pipe rejects the request itself.
+    assert_eq!(run_probe(&[]), "3\nENOTTY\n");
+}
+
+#[test]
+fn the_bwrap_backend_rejects_restrict_ioctls() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--backend", "bwrap", "--restrict-ioctls", "--", "touch"])
+        .arg(&marker)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!marker.exists());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--restrict-ioctls is not supported by the bwrap backend"),
+        "{output:?}"
+    );
+}
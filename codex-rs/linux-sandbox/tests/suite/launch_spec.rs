@@ -0,0 +1,62 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+use std::process::Command;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+fn write_spec(dir: &Path, spec: &str) -> std::path::PathBuf {
+    let path = dir.join("launch.json");
+    std::fs::write(&path, spec).expect("write spec");
+    path
+}
+
+#[test]
+fn spec_alone_describes_the_launch() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let spec = write_spec(
+        tmpdir.path(),
+        &format!(
+            r#"{{"policy":{{"mode":"read-only"}},"cwd":{:?},"env":{{"SPEC_GREETING":"hello from the spec"}},"command":["sh","-c","echo \"$SPEC_GREETING\"; umask"],"flags":{{"umask":"077"}}}}"#,
+            tmpdir.path().display().to_string()
+        ),
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .arg("--spec")
+        .arg(&spec)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "hello from the spec\n0077\n"
+    );
+}
+
+#[test]
+fn command_line_overrides_the_spec() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let spec = write_spec(
+        tmpdir.path(),
+        r#"{"policy":{"mode":"read-only"},"command":["echo","from the spec"],"flags":{"umask":"077"}}"#,
+    );
+
+    // The command line's policy, umask and command all win over the spec's.
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .arg("--spec")
+        .arg(&spec)
+        .args(["--umask", "022", "--", "sh", "-c"])
+        .arg(format!(
+            "umask; touch {}",
+            tmpdir.path().join("written").display()
+        ))
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0022\n");
+    assert!(tmpdir.path().join("written").exists());
+}
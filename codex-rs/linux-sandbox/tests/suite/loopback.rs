@@ -0,0 +1,55 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::sandbox_command;
+use crate::suite::support::which_python;
+use pretty_assertions::assert_eq;
+
+/// Listens on and connects to loopback, then tries an external address.
+const PROBE: &str = r#"
+import socket
+server = socket.socket()
+server.bind(("127.0.0.1", 0))
+server.listen()
+socket.create_connection(server.getsockname(), timeout=5)
+print("loopback ok")
+try:
+    socket.create_connection(("1.1.1.1", 80), timeout=5)
+    print("external ok")
+except OSError:
+    print("external blocked")
+"#;
+
+#[test]
+fn allow_loopback_permits_loopback_but_not_external_connections() {
+    if which_python().is_none() {
+        eprintln!("skipping: python3 is not installed");
+        return;
+    }
+
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(r#"{"mode":"read-only"}"#, tmpdir.path())
+        .args(["--allow-loopback", "--", "python3", "-c", PROBE])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "loopback ok\nexternal blocked\n"
+    );
+}
+
+#[test]
+fn loopback_is_blocked_without_allow_loopback() {
+    if which_python().is_none() {
+        eprintln!("skipping: python3 is not installed");
+        return;
+    }
+
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(r#"{"mode":"read-only"}"#, tmpdir.path())
+        .args(["--", "python3", "-c", PROBE])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+}
@@ -0,0 +1,71 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Every field documented in the README, in order.
+const FIELDS: &[&str] = &[
+    "kernel",
+    "landlock_abi",
+    "seccomp",
+    "backend",
+    "readable_roots",
+    "read_deny",
+    "writable_roots",
+    "private_tmp",
+    "devices",
+    "network",
+    "env",
+    "argv",
+    "enforced",
+    "enforcement_reason",
+    "exit",
+];
+
+fn field_positions(manifest: &str) -> Vec<Option<usize>> {
+    FIELDS
+        .iter()
+        .map(|field| manifest.find(&format!(r#""{field}":"#)))
+        .collect()
+}
+
+#[test]
+fn manifest_describes_the_run() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let manifest_path = tmpdir.path().join("manifest.json");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .args(["--env-deny", "SECRET_*", "--", "echo", "hi"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    let manifest = std::fs::read_to_string(&manifest_path).expect("read manifest");
+    let positions = field_positions(&manifest);
+    assert!(positions.iter().all(Option::is_some), "{manifest}");
+    assert!(positions.is_sorted(), "{manifest}");
+    assert!(manifest.contains(r#""network":"blocked""#), "{manifest}");
+    assert!(manifest.contains(r#""SECRET_*""#), "{manifest}");
+    assert!(manifest.contains(r#""argv":["echo","hi"],"#), "{manifest}");
+    assert!(manifest.ends_with(",\"exit\":null}\n"), "{manifest}");
+}
+
+#[test]
+fn wait_mode_adds_the_exit_status() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let manifest_path = tmpdir.path().join("manifest.json");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--manifest")
+        .arg(&manifest_path)
+        .args(["--wait", "--", "sh", "-c", "exit 4"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert_eq!(output.status.code(), Some(4), "{output:?}");
+
+    let manifest = std::fs::read_to_string(&manifest_path).expect("read manifest");
+    assert!(
+        manifest.contains(r#""exit":{"exit_code":4,"signal":null,"rusage":"#),
+        "{manifest}"
+    );
+}
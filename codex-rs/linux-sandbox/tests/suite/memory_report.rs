@@ -0,0 +1,72 @@
+#![cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+
+/// fd number the report file is passed on in the launcher.
+const REPORT_FD: i32 = 3;
+
+/// Bytes the helper holds in memory at once.
+const ALLOCATION: u64 = 64 * 1024 * 1024;
+
+#[test]
+fn report_includes_the_peak_memory_of_the_cgroup() {
+    if !std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        eprintln!("skipping: no cgroup v2 hierarchy");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let report_path = tmpdir.path().join("report.json");
+    let report_file = std::fs::File::create(&report_path).expect("create report file");
+    let report_file_fd = report_file.as_raw_fd();
+
+    let mut command = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path());
+    command
+        .args([
+            "--wait",
+            "--memory-report",
+            "--report-fd",
+            &REPORT_FD.to_string(),
+            "--",
+        ])
+        .args([
+            "sh",
+            "-c",
+            // The command substitution keeps all of it in the shell's memory.
+            r#"x=$(head -c "$1" /dev/zero | tr '\0' x); [ "${#x}" -eq "$1" ]"#,
+            "sh",
+            &ALLOCATION.to_string(),
+        ]);
+    // Runs in the forked child just before exec.
+    unsafe {
+        command.pre_exec(move || {
+            // dup2 onto itself keeps O_CLOEXEC, so clear it explicitly.
+            if libc::dup2(report_file_fd, REPORT_FD) < 0
+                || libc::fcntl(REPORT_FD, libc::F_SETFD, 0) < 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let output = command.output().expect("run codex-linux-sandbox");
+    drop(report_file);
+    assert!(output.status.success(), "{output:?}");
+
+    let report = std::fs::read_to_string(&report_path).expect("read report");
+    let Some((_, rest)) = report.split_once(r#""memory_peak":"#) else {
+        eprintln!(
+            "skipping: the command's cgroup does not account memory: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return;
+    };
+    let memory_peak: u64 = rest
+        .split(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|digits| digits.parse().ok())
+        .expect("memory_peak is a number");
+    assert!(memory_peak >= ALLOCATION, "{report}");
+}
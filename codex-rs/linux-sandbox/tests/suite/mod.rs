@@ -1,2 +1,47 @@
 // Aggregates all former standalone integration tests as modules.
+mod argv0;
+mod backend;
+mod bwrap;
+mod chdir;
+mod command_env;
+mod compliance_log;
+mod create_roots;
+mod dry_run;
+mod enforced;
+mod env_filter;
+mod events;
+mod exec_wrapper;
+mod fd_leak;
+mod filter_dump;
+mod forward_output;
+mod freeze;
+mod groups;
+mod ioctl;
 mod landlock;
+mod launch_spec;
+mod loopback;
+mod manifest;
+mod memory_report;
+mod no_proc;
+mod non_utf8;
+mod policy_validator;
+mod preflight;
+mod priority;
+mod private_tmp;
+mod profile_syscalls;
+mod read_deny;
+mod redirect;
+mod relative_command;
+mod seccomp_tsync;
+mod self_access;
+mod self_harden;
+mod setuid;
+mod sigpipe;
+mod support;
+mod syscall_allowlist;
+mod umask;
+mod verbosity;
+mod verify;
+mod version;
+mod wait;
+mod writable_root;
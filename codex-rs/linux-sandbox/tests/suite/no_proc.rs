@@ -0,0 +1,66 @@
+#![cfg(target_os = "linux")]
+use std::process::Command;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use pretty_assertions::assert_eq;
+
+/// `unshare` arguments that run a shell script in new user and mount
+/// namespaces.
+const IN_NAMESPACES: [&str; 4] = ["--map-root-user", "--mount", "sh", "-c"];
+
+/// Runs the launcher with an empty tmpfs mounted over `/proc`, inside the
+/// namespaces so that the host is unaffected. Returns `None` when the host
+/// does not allow that.
+fn run_without_proc(args: &[&str]) -> Option<std::process::Output> {
+    let probe = Command::new("unshare")
+        .args(IN_NAMESPACES)
+        .arg("mount -t tmpfs tmpfs /proc")
+        .output()
+        .ok()?;
+    if !probe.status.success() {
+        eprintln!(
+            "skipping: cannot hide /proc on this host: {}",
+            String::from_utf8_lossy(&probe.stderr)
+        );
+        return None;
+    }
+
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = Command::new("unshare")
+        .args(IN_NAMESPACES)
+        .arg(r#"mount -t tmpfs tmpfs /proc && exec "$@""#)
+        .arg("sh")
+        .arg(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .arg("--sandbox-policy-cwd")
+        .arg(tmpdir.path())
+        .args(["--sandbox-policy", READ_ONLY_POLICY])
+        .args(args)
+        .output()
+        .expect("run codex-linux-sandbox under unshare");
+    Some(output)
+}
+
+#[test]
+fn sandboxed_command_runs_without_proc() {
+    let Some(output) = run_without_proc(&["--", "echo", "ok"]) else {
+        return;
+    };
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
+
+#[test]
+fn freeze_until_ready_names_proc_when_it_is_missing() {
+    let Some(output) = run_without_proc(&["--wait", "--freeze-until-ready", "0", "--", "true"])
+    else {
+        return;
+    };
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("/proc/self/mountinfo is unavailable because /proc is not mounted"),
+        "{stderr}"
+    );
+}
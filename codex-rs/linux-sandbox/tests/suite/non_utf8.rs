@@ -0,0 +1,41 @@
+#![cfg(target_os = "linux")]
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Latin-1 `café`, which is not valid UTF-8.
+const NON_UTF8: &[u8] = b"caf\xe9";
+
+#[test]
+fn non_utf8_arguments_reach_the_command_unchanged() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--", "printf", "%s"])
+        .arg(OsStr::from_bytes(NON_UTF8))
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout, NON_UTF8);
+}
+
+#[test]
+fn non_utf8_policy_cwd_is_writable() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join(OsStr::from_bytes(NON_UTF8));
+    std::fs::create_dir(&cwd).expect("create non-UTF-8 cwd");
+    let file = cwd.join(OsStr::from_bytes(b"file-\xff"));
+
+    let policy = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+    let output = sandbox_command(policy, &cwd)
+        .args(["--", "touch"])
+        .arg(&file)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(file.exists(), "{} was not created", file.display());
+}
@@ -0,0 +1,81 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+
+fn write_validator(dir: &Path, script: &str) -> PathBuf {
+    let validator = dir.join("validator.sh");
+    std::fs::write(&validator, format!("#!/bin/sh\n{script}\n")).expect("write validator");
+    std::fs::set_permissions(&validator, std::fs::Permissions::from_mode(0o755))
+        .expect("make validator executable");
+    validator
+}
+
+#[test]
+fn validator_can_tighten_the_policy() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    // Receives the full-access policy and turns it into a read-only one.
+    let validator = write_validator(
+        tmpdir.path(),
+        r#"grep -q danger-full-access || exit 1; echo '{"mode":"read-only"}'"#,
+    );
+    let probe = tmpdir.path().join("probe");
+
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .arg("--policy-validator")
+        .arg(&validator)
+        .args(["--", "touch"])
+        .arg(&probe)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!probe.exists());
+}
+
+#[test]
+fn rejecting_validator_stops_the_launch() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let validator = write_validator(tmpdir.path(), "echo 'not allowed here' >&2; exit 7");
+    let probe = tmpdir.path().join("probe");
+
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .arg("--policy-validator")
+        .arg(&validator)
+        .args(["--", "touch"])
+        .arg(&probe)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // The validator's own stderr is passed through.
+    assert!(stderr.contains("not allowed here"), "{output:?}");
+    assert!(
+        stderr.contains("rejected the policy (exit status: 7)"),
+        "{output:?}"
+    );
+    assert!(!probe.exists());
+}
+
+#[test]
+fn hung_validator_times_out() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let validator = write_validator(tmpdir.path(), "exec sleep 30");
+
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .arg("--policy-validator")
+        .arg(&validator)
+        .args(["--policy-validator-timeout", "1", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("did not finish within 1s"),
+        "{output:?}"
+    );
+}
@@ -0,0 +1,35 @@
+#![cfg(target_os = "linux")]
+use std::process::Command;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use pretty_assertions::assert_eq;
+
+fn preflight(policy: &str, extra_args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .args(["preflight", "--sandbox-policy", policy])
+        .args(extra_args)
+        .output()
+        .expect("run codex-linux-sandbox preflight")
+}
+
+#[test]
+fn full_access_is_always_enforceable() {
+    let output = preflight(DANGER_FULL_ACCESS_POLICY, &["--json"]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"enforceable\":true,\"backend\":\"landlock\",\"checks\":[]}\n"
+    );
+}
+
+#[test]
+fn none_backend_cannot_enforce_a_read_only_policy() {
+    let output = preflight(r#"{"mode":"read-only"}"#, &["--backend", "none"]);
+
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "fail sandbox: --backend none does not enforce the policy\nverdict: not enforceable\n"
+    );
+}
@@ -0,0 +1,56 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// `nice` (field 19) and `policy` (field 41) from the `/proc/<pid>/stat` the
+/// command printed.
+fn nice_and_policy(stat: &str) -> (i32, i32) {
+    // Fields after the parenthesized command name start at field 3.
+    let (_, fields) = stat.rsplit_once(") ").expect("stat has a command name");
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    (
+        fields[19 - 3].parse().expect("nice"),
+        fields[41 - 3].parse().expect("policy"),
+    )
+}
+
+fn run_cat_stat(args: &[&str]) -> std::process::Output {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(args)
+        .args(["--", "cat", "/proc/self/stat"])
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+#[test]
+fn command_runs_with_the_requested_priority() {
+    let output = run_cat_stat(&["--nice", "19", "--sched", "batch"]);
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(
+        nice_and_policy(&String::from_utf8_lossy(&output.stdout)),
+        (19, libc::SCHED_BATCH)
+    );
+}
+
+#[test]
+fn idle_scheduling_needs_no_privileges() {
+    let output = run_cat_stat(&["--sched", "idle"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let (_, policy) = nice_and_policy(&String::from_utf8_lossy(&output.stdout));
+    assert_eq!(policy, libc::SCHED_IDLE);
+}
+
+#[test]
+fn out_of_range_niceness_is_rejected() {
+    let output = run_cat_stat(&["--nice", "-21"]);
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is outside -20..=19"),
+        "{output:?}"
+    );
+}
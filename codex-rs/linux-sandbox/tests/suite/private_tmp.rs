@@ -0,0 +1,60 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::suite::support::sandbox_command;
+
+#[test]
+fn files_created_in_private_tmp_are_not_visible_in_host_tmp() {
+    let cwd = tempfile::tempdir().expect("tempdir");
+    let probe_name = format!("codex-private-tmp-probe-{}", std::process::id());
+    let host_probe = PathBuf::from("/tmp").join(&probe_name);
+    let policy = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":false,"exclude_slash_tmp":false}"#;
+    let script = format!(
+        r#"touch "/tmp/{probe_name}" 2>/dev/null; touch "$TMPDIR/inside" && printf %s "$TMPDIR""#
+    );
+
+    let output = sandbox_command(policy, cwd.path())
+        .args(["--private-tmp", "--", "sh", "-c", &script])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        !host_probe.exists(),
+        "{host_probe:?} leaked into the host /tmp"
+    );
+    let tmpdir = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(tmpdir.starts_with("/tmp"), "unexpected TMPDIR {tmpdir:?}");
+
+    // Under Landlock the private directory lives on the host; clean it up.
+    if tmpdir != "/tmp" {
+        let _ = std::fs::remove_dir_all(&tmpdir);
+    }
+}
+
+#[test]
+fn wait_removes_the_private_tmp_once_the_command_exits() {
+    let cwd = tempfile::tempdir().expect("tempdir");
+    let policy = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":false,"exclude_slash_tmp":false}"#;
+
+    let output = sandbox_command(policy, cwd.path())
+        .args([
+            "--private-tmp",
+            "--wait",
+            "--",
+            "sh",
+            "-c",
+            r#"mkdir "$TMPDIR/left-behind" && printf %s "$TMPDIR""#,
+        ])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let tmpdir = PathBuf::from(String::from_utf8_lossy(&output.stdout).into_owned());
+    if tmpdir == Path::new("/tmp") {
+        eprintln!("skipping: the private tmp is a bwrap tmpfs");
+        return;
+    }
+    assert!(!tmpdir.exists(), "{tmpdir:?} was left behind");
+}
@@ -0,0 +1,42 @@
+#![cfg(target_os = "linux")]
+use std::process::Command;
+
+use pretty_assertions::assert_eq;
+
+fn profile_syscalls() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"));
+    command.arg("profile-syscalls");
+    command
+}
+
+#[test]
+fn lists_the_syscalls_of_the_command_and_its_children() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let list = tmpdir.path().join("syscalls.txt");
+    let output = profile_syscalls()
+        .arg("--output")
+        .arg(&list)
+        .args(["--", "/bin/sh", "-c", "/bin/echo hi; exit 3"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    let list = std::fs::read_to_string(&list).expect("read syscall list");
+    let syscalls: Vec<&str> = list.lines().collect();
+    // `write` is only made by the forked echo.
+    for expected in ["execve", "write", "exit_group"] {
+        assert!(
+            syscalls.contains(&expected),
+            "{expected} missing from {list}"
+        );
+    }
+    let named: Vec<&str> = syscalls
+        .iter()
+        .copied()
+        .filter(|syscall| !syscall.contains('#'))
+        .collect();
+    let mut sorted = named.clone();
+    sorted.sort_unstable();
+    assert_eq!(named, sorted);
+}
@@ -0,0 +1,65 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn masked_paths_read_as_empty() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let secret = tmpdir.path().join("secret");
+    let ssh = tmpdir.path().join(".ssh");
+    let public = tmpdir.path().join("public");
+    std::fs::write(&secret, "hunter2").expect("write secret");
+    std::fs::create_dir(&ssh).expect("create .ssh");
+    std::fs::write(ssh.join("id_ed25519"), "key").expect("write key");
+    std::fs::write(&public, "hello").expect("write public");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--backend", "bwrap", "--read-deny"])
+        .arg(&secret)
+        .arg("--read-deny")
+        .arg(&ssh)
+        .args([
+            "--",
+            "/bin/sh",
+            "-c",
+            r#"cat "$1"; ls -A "$2"; cat "$3""#,
+            "sh",
+        ])
+        .arg(&secret)
+        .arg(&ssh)
+        .arg(&public)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hello");
+}
+
+#[test]
+fn read_deny_needs_bwrap() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args([
+            "--backend",
+            "none",
+            "--read-deny",
+            "/etc/shadow",
+            "--",
+            "true",
+        ])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--read-deny is only supported by the bwrap backend, not none"),
+        "{output:?}"
+    );
+}
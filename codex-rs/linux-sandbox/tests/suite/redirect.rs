@@ -0,0 +1,69 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Only the sandbox policy cwd is writable.
+const CWD_ONLY_POLICY: &str = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+#[test]
+fn output_is_truncated_unless_appending() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let out = tmpdir.path().join("out.txt");
+    let err = tmpdir.path().join("err.txt");
+    std::fs::write(&out, "old\n").expect("write out");
+    std::fs::write(&err, "old\n").expect("write err");
+
+    let output = sandbox_command(CWD_ONLY_POLICY, tmpdir.path())
+        .arg("--stdout")
+        .arg(&out)
+        .arg("--stderr")
+        .arg(&err)
+        .arg("--stderr-append")
+        .args(["--", "sh", "-c", "echo out; echo err >&2"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(output.stdout, b"");
+    assert_eq!(std::fs::read_to_string(&out).expect("read out"), "out\n");
+    assert_eq!(
+        std::fs::read_to_string(&err).expect("read err"),
+        "old\nerr\n"
+    );
+}
+
+#[test]
+fn stdin_is_read_from_a_file() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let input = tmpdir.path().join("in.txt");
+    std::fs::write(&input, "from a file\n").expect("write input");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--stdin")
+        .arg(&input)
+        .args(["--", "cat"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "from a file\n");
+}
+
+#[test]
+fn output_outside_the_writable_roots_is_rejected() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let out = tmpdir.path().join("out.txt");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--stdout")
+        .arg(&out)
+        .args(["--", "echo", "hi"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is outside the writable roots"),
+        "{output:?}"
+    );
+    assert!(!out.exists());
+}
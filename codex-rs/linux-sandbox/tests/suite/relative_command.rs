@@ -0,0 +1,123 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn relative_command_is_resolved_against_the_policy_cwd() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let work = tmpdir.path().join("work");
+    std::fs::create_dir_all(&work).expect("create work");
+    let script = work.join("script.sh");
+    std::fs::write(&script, "#!/bin/sh\necho \"$0\"\n").expect("write script");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+        .expect("make script executable");
+
+    let output = sandbox_command(READ_ONLY_POLICY, &work)
+        .current_dir(tmpdir.path())
+        .args(["--", "./script.sh"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let script = script.canonicalize().expect("canonicalize script");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", script.display())
+    );
+}
+
+#[test]
+fn relative_command_is_resolved_against_chdir() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let sub = tmpdir.path().join("sub");
+    std::fs::create_dir_all(&sub).expect("create sub");
+    // A decoy in the policy cwd, which must not be the one that runs.
+    for (dir, name) in [(tmpdir.path(), "decoy"), (sub.as_path(), "sub")] {
+        let script = dir.join("script.sh");
+        std::fs::write(&script, format!("#!/bin/sh\necho {name}\n")).expect("write script");
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+            .expect("make script executable");
+    }
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--chdir", "sub", "--", "./script.sh"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "sub\n");
+}
+
+#[test]
+fn missing_relative_command_is_reported() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--", "./missing.sh"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("command ./missing.sh cannot be executed"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn directory_command_is_rejected_before_the_sandbox() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir(tmpdir.path().join("tools")).expect("create tools");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--", "./tools"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is a directory"), "{stderr}");
+    assert!(!stderr.contains("Failed to execvp"), "{stderr}");
+}
+
+#[test]
+fn non_executable_command_is_rejected_before_the_sandbox() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let script = tmpdir.path().join("script.sh");
+    std::fs::write(&script, "#!/bin/sh\necho hi\n").expect("write script");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644))
+        .expect("make script non-executable");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--")
+        .arg(&script)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("is not executable by the effective user"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("Failed to execvp"), "{stderr}");
+}
+
+#[test]
+fn executable_symlink_command_runs() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let script = tmpdir.path().join("script.sh");
+    std::fs::write(&script, "#!/bin/sh\necho hi\n").expect("write script");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+        .expect("make script executable");
+    let link = tmpdir.path().join("link");
+    std::os::unix::fs::symlink(&script, &link).expect("symlink script");
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--")
+        .arg(&link)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+}
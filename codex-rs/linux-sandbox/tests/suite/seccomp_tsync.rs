@@ -0,0 +1,56 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use crate::suite::support::which_python;
+use pretty_assertions::assert_eq;
+
+/// Opens a TCP socket from four threads and then the main thread, printing
+/// how each attempt went.
+const THREADED_PROBE: &str = r#"
+import socket, threading
+results = []
+def probe():
+    try:
+        socket.socket(socket.AF_INET, socket.SOCK_STREAM).close()
+        results.append("allowed")
+    except PermissionError:
+        results.append("denied")
+threads = [threading.Thread(target=probe) for _ in range(4)]
+for thread in threads:
+    thread.start()
+for thread in threads:
+    thread.join()
+probe()
+print(" ".join(results))
+"#;
+
+#[test]
+fn every_thread_of_the_command_is_filtered() {
+    if which_python().is_none() {
+        eprintln!("skipping: python3 is not installed");
+        return;
+    }
+    // The threads start after the exec, so they inherit the filter either
+    // way; TSYNC itself is covered by the unit tests.
+    for tsync in ["on", "off"] {
+        let tmpdir = tempfile::tempdir().expect("tempdir");
+        let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+            .args([
+                "--seccomp-tsync",
+                tsync,
+                "--",
+                "python3",
+                "-c",
+                THREADED_PROBE,
+            ])
+            .output()
+            .expect("run codex-linux-sandbox");
+
+        assert!(output.status.success(), "{tsync}: {output:?}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "denied denied denied denied denied\n",
+            "{tsync}"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+#![cfg(target_os = "linux")]
+//! The bwrap backend hides the launcher's own files, and the Landlock
+//! backend refuses to run with its executable in a writable root. The bwrap
+//! tests are skipped on hosts without bwrap.
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::process::Output;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// Only the sandbox policy cwd is writable.
+const CWD_ONLY_POLICY: &str = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+/// Looks at the spec and the launcher binary from inside the sandbox,
+/// without ever writing to the binary.
+const SCRIPT: &str = r#"cat "$0"; echo; if [ -w "$1" ]; then echo writable; else echo read-only; fi; head -c 4 "$1" | od -An -c"#;
+
+fn inspect(spec: &Path, args: &[&str]) -> Output {
+    sandbox_command(DANGER_FULL_ACCESS_POLICY, spec.parent().expect("spec dir"))
+        .args(["--backend", "bwrap", "--spec"])
+        .arg(spec)
+        .args(args)
+        .args(["--", "sh", "-c", SCRIPT])
+        .arg(spec)
+        .arg(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+#[test]
+fn launcher_files_are_hidden_by_default() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let spec = tmpdir.path().join("spec.json");
+    std::fs::write(&spec, r#"{"flags":{"umask":"077"}}"#).expect("write spec");
+
+    let output = inspect(&spec, &[]);
+    assert!(output.status.success(), "{output:?}");
+    // Even under a full-access policy, the spec reads as empty and the
+    // binary can be neither read nor written.
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\nread-only\n");
+
+    let output = inspect(&spec, &["--allow-self-access"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("{\"flags\":{\"umask\":\"077\"}}\nwritable\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("E   L   F"), "{stdout}");
+}
+
+#[test]
+fn landlock_runs_with_a_readable_spec() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let spec = tmpdir.path().join("spec.json");
+    std::fs::write(&spec, r#"{"flags":{"umask":"077"}}"#).expect("write spec");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .arg("--spec")
+        .arg(&spec)
+        .args(["--", "sh", "-c", "umask"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0077\n");
+}
+
+#[test]
+fn a_spec_on_an_fd_is_not_inherited_by_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let spec = tmpdir.path().join("spec.json");
+    std::fs::write(&spec, r#"{"flags":{"umask":"077"}}"#).expect("write spec");
+
+    // The shell opens the spec on fd 3 for the launcher only.
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(r#"exec "$0" --sandbox-policy-cwd "$1" --sandbox-policy "$2" --spec /dev/fd/3 -- sh -c 'umask; if cat <&3; then echo leaked; else echo closed; fi 2>/dev/null' 3<"$3""#)
+        .arg(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .arg(tmpdir.path())
+        .arg(READ_ONLY_POLICY)
+        .arg(&spec)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "0077\nclosed\n");
+}
+
+#[test]
+fn landlock_refuses_to_let_the_command_overwrite_the_launcher() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    // A copy of the launcher inside the only writable root.
+    let launcher = tmpdir.path().join("codex-linux-sandbox");
+    std::fs::copy(env!("CARGO_BIN_EXE_codex-linux-sandbox"), &launcher).expect("copy launcher");
+    std::fs::set_permissions(&launcher, std::fs::Permissions::from_mode(0o755))
+        .expect("make launcher executable");
+    let original = std::fs::read(&launcher).expect("read launcher");
+
+    let output = Command::new(&launcher)
+        .arg("--sandbox-policy-cwd")
+        .arg(tmpdir.path())
+        .args(["--sandbox-policy", CWD_ONLY_POLICY])
+        .args(["--", "sh", "-c", "echo tampered > \"$0\""])
+        .arg(&launcher)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is inside the writable root"),
+        "{output:?}"
+    );
+    assert!(std::fs::read(&launcher).expect("read launcher") == original);
+}
@@ -0,0 +1,27 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// In wait mode the launcher is the command's parent, so the command can
+/// read the launcher's capability sets.
+const LAUNCHER_CAPS: &str = r#"grep -E '^Cap(Eff|Prm|Inh|Amb):' "/proc/$PPID/status""#;
+
+#[test]
+fn self_harden_drops_the_launcher_capabilities() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--wait", "--self-harden", "--", "sh", "-c", LAUNCHER_CAPS])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    // Nothing is needed for a Landlock run with no privileged flags.
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "CapInh:\t0000000000000000\n\
+         CapPrm:\t0000000000000000\n\
+         CapEff:\t0000000000000000\n\
+         CapAmb:\t0000000000000000\n"
+    );
+}
@@ -0,0 +1,95 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+
+/// Only the sandbox policy cwd is writable.
+const CWD_ONLY_POLICY: &str = r#"{"mode":"workspace-write","writable_roots":[],"network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+#[test]
+fn setuid_bit_in_a_writable_root_confers_nothing() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+
+    // The sandbox does not stop the command from setting the bit, only from
+    // gaining anything by exec'ing the file.
+    let output = sandbox_command(CWD_ONLY_POLICY, tmpdir.path())
+        .current_dir(tmpdir.path())
+        .args([
+            "--",
+            "sh",
+            "-c",
+            r#"cp "$(command -v cat)" ./cat-suid && chmod u+s ./cat-suid && ./cat-suid /proc/self/status"#,
+        ])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    let mode = std::fs::metadata(tmpdir.path().join("cat-suid"))
+        .expect("setuid copy")
+        .permissions()
+        .mode();
+    assert_ne!(mode & 0o4000, 0, "{mode:o}");
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    assert!(status.contains("NoNewPrivs:\t1\n"), "{status}");
+    // Real, effective, saved and filesystem uid all stay the caller's.
+    let uids: Vec<&str> = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .expect("Uid line")
+        .split_whitespace()
+        .collect();
+    assert!(uids.iter().all(|uid| *uid == uids[0]), "{status}");
+}
+
+#[test]
+fn bwrap_mounts_writable_roots_nosuid() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().canonicalize().expect("canonicalize tempdir");
+
+    let output = sandbox_command(CWD_ONLY_POLICY, &cwd)
+        .args(["--backend", "bwrap", "--", "cat", "/proc/self/mountinfo"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    // The mount point is the fifth field and its options the sixth; the last
+    // mount on the cwd is the one the command sees.
+    let mountinfo = String::from_utf8_lossy(&output.stdout);
+    let cwd = cwd.to_string_lossy();
+    let options = mountinfo
+        .lines()
+        .rev()
+        .map(|line| line.split(' ').collect::<Vec<_>>())
+        .find(|fields| fields.get(4).copied() == Some(cwd.as_ref()))
+        .and_then(|fields| fields.get(5).copied())
+        .unwrap_or_else(|| panic!("no mount on {cwd}:\n{mountinfo}"));
+    assert!(
+        options.split(',').any(|option| option == "nosuid"),
+        "{options}"
+    );
+}
+
+/// bwrap has no way to bind a path without `nosuid`, so the launcher offers
+/// no opt-out either.
+#[test]
+fn there_is_no_flag_to_allow_setuid() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+
+    let output = sandbox_command(CWD_ONLY_POLICY, tmpdir.path())
+        .args(["--allow-suid", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unexpected argument '--allow-suid'"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,44 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+
+/// Runs `grep` under the sandbox and reports whether SIGPIPE is in its
+/// ignored-signal mask.
+fn child_ignores_sigpipe(extra_args: &[&str]) -> bool {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(extra_args)
+        .args(["--", "grep", "^SigIgn:", "/proc/self/status"])
+        .output()
+        .expect("run codex-linux-sandbox");
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mask = stdout
+        .trim()
+        .strip_prefix("SigIgn:")
+        .map(str::trim)
+        .expect("SigIgn line");
+    let mask = u64::from_str_radix(mask, 16).expect("hex signal mask");
+    mask & (1 << (libc::SIGPIPE - 1)) != 0
+}
+
+#[test]
+fn sigpipe_defaults_to_sig_dfl() {
+    assert!(!child_ignores_sigpipe(&[]));
+    assert!(!child_ignores_sigpipe(&["--sigpipe", "default"]));
+}
+
+#[test]
+fn sigpipe_can_stay_ignored() {
+    assert!(child_ignores_sigpipe(&["--sigpipe", "ignore"]));
+}
+
+#[test]
+fn sigpipe_is_reset_in_the_wait_mode_child() {
+    for wait_args in [&["--wait"][..], &["--wait", "--forward-output"][..]] {
+        assert!(!child_ignores_sigpipe(wait_args), "{wait_args:?}");
+        let ignore = [wait_args, &["--sigpipe", "ignore"][..]].concat();
+        assert!(child_ignores_sigpipe(&ignore), "{ignore:?}");
+    }
+}
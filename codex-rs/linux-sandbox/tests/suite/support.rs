@@ -0,0 +1,34 @@
+#![cfg(target_os = "linux")]
+//! Helpers for invoking the `codex-linux-sandbox` binary directly, for flags
+//! that `codex-core` does not pass through.
+use std::path::Path;
+use std::process::Command;
+
+pub(crate) const DANGER_FULL_ACCESS_POLICY: &str = r#"{"mode":"danger-full-access"}"#;
+pub(crate) const READ_ONLY_POLICY: &str = r#"{"mode":"read-only"}"#;
+
+/// Returns a `codex-linux-sandbox` invocation for `policy`. Callers append any
+/// extra flags followed by `--` and the command to run.
+pub(crate) fn sandbox_command(policy: &str, sandbox_policy_cwd: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"));
+    command
+        .arg("--sandbox-policy-cwd")
+        .arg(sandbox_policy_cwd)
+        .arg("--sandbox-policy")
+        .arg(policy);
+    command
+}
+
+/// Whether the tests that run bwrap itself can run on this host.
+pub(crate) fn bwrap_on_path() -> bool {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::split_paths(&path).any(|dir| dir.join("bwrap").is_file())
+}
+
+/// Path of `python3` on `PATH`, for the tests that need it in the sandbox.
+pub(crate) fn which_python() -> Option<std::path::PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join("python3"))
+        .find(|candidate| candidate.is_file())
+}
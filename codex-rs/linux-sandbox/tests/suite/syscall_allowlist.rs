@@ -0,0 +1,92 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::bwrap_on_path;
+use crate::suite::support::sandbox_command;
+
+#[test]
+fn allowlisted_syscalls_work() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--profile", "minimal-python", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("syscall allowlists are fragile"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn syscalls_outside_the_allowlist_are_blocked() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let target = tmpdir.path().join("created-by-sandbox");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--profile", "minimal-python", "--", "mkdir"])
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    // Neither `mkdir` nor `mkdirat` is on the minimal-python allowlist.
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!target.exists());
+}
+
+#[test]
+fn syscall_allowlist_file_is_merged_with_profile() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let allowlist = tmpdir.path().join("allowlist");
+    // x86_64 coreutils use the legacy `mkdir` syscall; aarch64 only has `mkdirat`.
+    let mkdir_syscall = if cfg!(target_arch = "x86_64") {
+        "mkdir"
+    } else {
+        "mkdirat"
+    };
+    std::fs::write(
+        &allowlist,
+        format!("# needed by mkdir(1)\n{mkdir_syscall}\n"),
+    )
+    .expect("write allowlist");
+    let target = tmpdir.path().join("created-by-sandbox");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--profile", "minimal-python", "--syscall-allowlist"])
+        .arg(&allowlist)
+        .arg("--")
+        .arg("mkdir")
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(target.is_dir());
+}
+
+#[test]
+fn the_bwrap_backend_rejects_an_allowlist() {
+    if !bwrap_on_path() {
+        eprintln!("skipping: bwrap is not on PATH");
+        return;
+    }
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args([
+            "--backend",
+            "bwrap",
+            "--profile",
+            "minimal-python",
+            "--",
+            "touch",
+        ])
+        .arg(&marker)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(!marker.exists());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("not supported by the bwrap backend"),
+        "{output:?}"
+    );
+}
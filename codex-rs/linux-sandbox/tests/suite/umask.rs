@@ -0,0 +1,40 @@
+#![cfg(target_os = "linux")]
+use std::os::unix::fs::PermissionsExt;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn umask_applies_to_files_created_by_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let target = tmpdir.path().join("created-by-sandbox");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--umask", "077", "--", "touch"])
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    let mode = std::fs::metadata(&target)
+        .expect("stat created file")
+        .permissions()
+        .mode();
+    // touch(1) creates files with 0666 before the umask is applied.
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn invalid_umask_is_rejected() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--umask", "999", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is not an octal number"),
+        "{output:?}"
+    );
+}
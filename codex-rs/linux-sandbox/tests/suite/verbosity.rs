@@ -0,0 +1,63 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+const NO_SANDBOX_WARNING: &str = "--backend none runs the command without a sandbox";
+
+fn run_without_sandbox(flags: &[&str]) -> (String, String) {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--backend", "none"])
+        .args(flags)
+        .args(["--", "echo", "hi"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn default_prints_warnings_to_stderr() {
+    let (stdout, stderr) = run_without_sandbox(&[]);
+
+    assert_eq!(stdout, "hi\n");
+    assert!(stderr.contains(NO_SANDBOX_WARNING), "{stderr}");
+    assert!(!stderr.contains("selected the none backend"), "{stderr}");
+}
+
+#[test]
+fn quiet_suppresses_warnings() {
+    let (stdout, stderr) = run_without_sandbox(&["--quiet"]);
+
+    assert_eq!(stdout, "hi\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn verbose_reports_the_backend() {
+    let (stdout, stderr) = run_without_sandbox(&["--verbose"]);
+
+    assert_eq!(stdout, "hi\n");
+    assert!(stderr.contains("selected the none backend"), "{stderr}");
+    assert!(stderr.contains(NO_SANDBOX_WARNING), "{stderr}");
+}
+
+#[test]
+fn quiet_still_prints_errors() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--quiet", "--", "./missing.sh"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("command ./missing.sh cannot be executed"),
+        "{output:?}"
+    );
+}
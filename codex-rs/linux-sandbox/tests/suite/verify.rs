@@ -0,0 +1,99 @@
+#![cfg(target_os = "linux")]
+use std::path::Path;
+use std::process::Output;
+
+use crate::suite::support::READ_ONLY_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+fn verify(cwd: &Path, backend: &str, marker: &Path) -> Output {
+    sandbox_command(READ_ONLY_POLICY, cwd)
+        .args(["--backend", backend, "--verify", "--", "touch"])
+        .arg(marker)
+        .output()
+        .expect("run codex-linux-sandbox")
+}
+
+/// `(name, result)` of each probe in the report on stdout.
+fn probe_results(output: &Output) -> Vec<(String, String)> {
+    let report: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("parse the --verify report");
+    report["probes"]
+        .as_array()
+        .expect("probes")
+        .iter()
+        .map(|probe| {
+            (
+                probe["name"].as_str().unwrap_or_default().to_string(),
+                probe["result"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn landlock_denies_every_probe_and_skips_the_command() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+
+    let output = verify(tmpdir.path(), "landlock", &marker);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        probe_results(&output),
+        vec![
+            ("write_outside_roots".to_string(), "denied".to_string()),
+            ("connect_to_host".to_string(), "denied".to_string()),
+        ]
+    );
+    assert!(!marker.exists(), "the command ran");
+}
+
+#[test]
+fn running_without_a_sandbox_fails_verification() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+
+    let output = verify(tmpdir.path(), "none", &marker);
+
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert_eq!(
+        probe_results(&output),
+        vec![
+            ("write_outside_roots".to_string(), "allowed".to_string()),
+            ("connect_to_host".to_string(), "allowed".to_string()),
+        ]
+    );
+    assert!(!marker.exists(), "the command ran");
+}
+
+#[test]
+fn probe_timeout_bounds_each_probe() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let marker = tmpdir.path().join("ran");
+
+    let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+        .args(["--verify", "--probe-timeout", "200", "--", "touch"])
+        .arg(&marker)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(!marker.exists(), "the command ran");
+}
+
+#[test]
+fn probe_timeout_must_be_positive_and_needs_verify() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    for args in [
+        &["--verify", "--probe-timeout", "0", "--", "true"][..],
+        &["--probe-timeout", "200", "--", "true"][..],
+    ] {
+        let output = sandbox_command(READ_ONLY_POLICY, tmpdir.path())
+            .args(args)
+            .output()
+            .expect("run codex-linux-sandbox");
+
+        assert!(!output.status.success(), "{args:?} {output:?}");
+    }
+}
@@ -0,0 +1,42 @@
+#![cfg(target_os = "linux")]
+use std::process::Command;
+
+fn version(extra_args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_codex-linux-sandbox"))
+        .arg("version")
+        .args(extra_args)
+        .output()
+        .expect("run codex-linux-sandbox version")
+}
+
+#[test]
+fn json_version_lists_the_capabilities() {
+    let output = version(&["--json"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with(&format!(
+            r#"{{"version":"{}","landlock_abi":{{"min":1,"max":5}},"seccomp":true,"#,
+            env!("CARGO_PKG_VERSION")
+        )),
+        "{stdout}"
+    );
+    assert!(stdout.contains(r#","bwrap":{"available":"#), "{stdout}");
+    assert!(stdout.ends_with("}}\n"), "{stdout}");
+}
+
+#[test]
+fn text_version_starts_with_the_crate_version() {
+    let output = version(&[]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with(&format!(
+            "codex-linux-sandbox {}\n",
+            env!("CARGO_PKG_VERSION")
+        )),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,66 @@
+#![cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
+
+use crate::suite::support::DANGER_FULL_ACCESS_POLICY;
+use crate::suite::support::sandbox_command;
+use pretty_assertions::assert_eq;
+
+/// fd number the report file is passed on in the launcher.
+const REPORT_FD: i32 = 3;
+
+#[test]
+fn wait_mode_exits_with_the_command_status() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path())
+        .args(["--wait", "--", "sh", "-c", "exit 3"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+}
+
+#[test]
+fn report_includes_the_command_cpu_time() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let report_path = tmpdir.path().join("report.json");
+    let report_file = std::fs::File::create(&report_path).expect("create report file");
+    let report_file_fd = report_file.as_raw_fd();
+
+    let mut command = sandbox_command(DANGER_FULL_ACCESS_POLICY, tmpdir.path());
+    command
+        .args(["--wait", "--report-fd", &REPORT_FD.to_string(), "--"])
+        .args([
+            "sh",
+            "-c",
+            r#"i=0; while [ "$i" -lt 500000 ]; do i=$((i + 1)); done"#,
+        ]);
+    // Runs in the forked child just before exec.
+    unsafe {
+        command.pre_exec(move || {
+            // dup2 onto itself keeps O_CLOEXEC, so clear it explicitly.
+            if libc::dup2(report_file_fd, REPORT_FD) < 0
+                || libc::fcntl(REPORT_FD, libc::F_SETFD, 0) < 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let output = command.output().expect("run codex-linux-sandbox");
+    drop(report_file);
+    assert!(output.status.success(), "{output:?}");
+
+    let report = std::fs::read_to_string(&report_path).expect("read report");
+    assert!(
+        report.starts_with(r#"{"exit_code":0,"signal":null,"rusage":{"utime":"#),
+        "{report}"
+    );
+    let utime: i64 = report
+        .split(r#""utime":"#)
+        .nth(1)
+        .and_then(|rest| rest.split(',').next())
+        .and_then(|utime| utime.parse().ok())
+        .expect("utime in report");
+    assert!(utime > 0, "{report}");
+}
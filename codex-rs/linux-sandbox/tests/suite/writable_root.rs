@@ -0,0 +1,86 @@
+#![cfg(target_os = "linux")]
+use crate::suite::support::sandbox_command;
+
+const WORKSPACE_WRITE_POLICY: &str = r#"{"mode":"workspace-write","network_access":false,"exclude_tmpdir_env_var":true,"exclude_slash_tmp":true}"#;
+
+#[test]
+fn cli_root_is_writable_alongside_the_policy_roots() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    let extra = tmpdir.path().join("extra");
+    let elsewhere = tmpdir.path().join("elsewhere");
+    for dir in [&cwd, &extra, &elsewhere] {
+        std::fs::create_dir(dir).expect("create dir");
+    }
+
+    let output = sandbox_command(WORKSPACE_WRITE_POLICY, &cwd)
+        .arg("--writable-root")
+        .arg(&extra)
+        .args([
+            "--",
+            "/bin/sh",
+            "-c",
+            r#"touch "$1/ok" && touch "$2/ok" && ! touch "$3/denied""#,
+            "sh",
+        ])
+        .arg(&cwd)
+        .arg(&extra)
+        .arg(&elsewhere)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(cwd.join("ok").exists());
+    assert!(extra.join("ok").exists());
+    assert!(!elsewhere.join("denied").exists());
+}
+
+#[test]
+fn cli_root_may_not_be_a_system_directory() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let output = sandbox_command(WORKSPACE_WRITE_POLICY, tmpdir.path())
+        .args(["--writable-root", "/etc", "--", "true"])
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("/etc is the system directory /etc"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn symlinked_root_is_writable_through_the_link_and_the_target() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let cwd = tmpdir.path().join("cwd");
+    let target = tmpdir.path().join("target");
+    let link = tmpdir.path().join("link");
+    for dir in [&cwd, &target] {
+        std::fs::create_dir(dir).expect("create dir");
+    }
+    std::os::unix::fs::symlink(&target, &link).expect("symlink");
+
+    let output = sandbox_command(WORKSPACE_WRITE_POLICY, &cwd)
+        .arg("--writable-root")
+        .arg(&link)
+        .args([
+            "--",
+            "/bin/sh",
+            "-c",
+            r#"touch "$1/via-link" && touch "$2/direct""#,
+            "sh",
+        ])
+        .arg(&link)
+        .arg(&target)
+        .output()
+        .expect("run codex-linux-sandbox");
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(target.join("via-link").exists());
+    assert!(target.join("direct").exists());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is a symlink"),
+        "{output:?}"
+    );
+}